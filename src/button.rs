@@ -0,0 +1,18 @@
+use crate::style;
+use gpui::*;
+
+/// 表单和面板中使用的文字按钮
+pub fn form_button(id: impl Into<ElementId>, label: impl Into<SharedString>) -> Stateful<Div> {
+    div()
+        .id(id)
+        .flex()
+        .px_3()
+        .h_8()
+        .rounded_md()
+        .justify_center()
+        .items_center()
+        .bg(rgb(style::BUTTON_COLOR))
+        .text_color(rgb(style::PRIMARY_COLOR))
+        .hover(|this| this.bg(rgb(style::BUTTON_COLOR_HOVER)))
+        .child(label.into())
+}
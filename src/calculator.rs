@@ -1,549 +1,143 @@
-use gpui::{Context, EventEmitter};
-use std::fmt;
+mod reducer;
+pub mod store;
 
-/// 计算器事件类型
-#[derive(Debug, Clone)]
-pub enum CalculatorEvent {
-    /// 数字按键事件，包含数字字符
-    NumberPressed,
-    /// 操作符按键事件，包含操作符字符
-    OperatorPressed,
-    /// 小数点按键事件
-    DecimalPressed,
-    /// 括号按键事件，包含括号字符
-    ParenthesisPressed,
-    /// 百分比按键事件
-    PercentagePressed,
-    /// 逗号格式化切换事件
-    CommaFormattingToggled,
-    /// 清除按键事件
-    ClearPressed,
-    /// 删除按键事件
-    DeletePressed,
-    /// 等于按键事件，触发计算
-    EqualsPressed,
-    /// 表达式发生变化，包含新的表达式字符串
-    ExpressionChanged,
-    /// 计算结果，包含计算结果值
-    ResultCalculated,
-    /// 计算器状态重置
-    StateReset,
-}
-
-/// 计算器状态
-#[derive(Debug, Clone)]
-pub struct CalculatorState {
-    /// 当前表达式
-    pub expression: String,
-    /// 上一个计算结果（如果有）
-    pub last_result: Option<f64>,
-    /// 是否刚执行过计算
-    pub just_calculated: bool,
-    /// 是否表达式为空
-    pub is_empty: bool,
-    /// 是否启用逗号格式化显示（每三位加逗号）
-    pub comma_formatting: bool,
-}
+pub use reducer::{CalculatorCore, CalculatorEvent, Command};
 
-impl Default for CalculatorState {
-    fn default() -> Self {
-        Self {
-            expression: "0".to_string(),
-            last_result: None,
-            just_calculated: false,
-            is_empty: false,
-            comma_formatting: false,
-        }
-    }
-}
+use crate::logic::{CalcError, Value};
+use gpui::{Context, Entity, EventEmitter, Subscription};
+use std::path::PathBuf;
 
-impl fmt::Display for CalculatorState {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.just_calculated && self.last_result.is_some() {
-            write!(f, "{}", self.last_result.unwrap())
-        } else {
-            write!(f, "{}", self.expression)
-        }
-    }
-}
-
-/// 计算器实体 - 管理计算器状态和事件
+/// 计算器实体 - 包装 CalculatorCore，把命令产生的事件转发给 GPUI，并负责保存会话
+#[derive(Clone, Default)]
 pub struct Calculator {
-    state: CalculatorState,
+    core: CalculatorCore,
+    /// 会话文件路径，为 None 时不保存
+    session_path: Option<PathBuf>,
+    /// 读取会话文件失败时的错误信息
+    restore_error: Option<String>,
+    /// 最近一次保存会话文件失败时的错误信息
+    save_error: Option<String>,
 }
 
 impl Calculator {
-    /// 创建新的计算器实例
-    pub fn new() -> Self {
+    /// 用已经初始化好的核心（汇率、区域格式等）创建计算器实体
+    pub fn new(core: CalculatorCore) -> Self {
         Self {
-            state: CalculatorState::default(),
-        }
-    }
-
-    /// 处理数字输入
-    pub fn input_number(&mut self, number: char, cx: &mut Context<Self>) {
-        if !number.is_ascii_digit() {
-            return;
-        }
-
-        if self.state.just_calculated {
-            self.state.expression.clear();
-            self.state.just_calculated = false;
-        }
-
-        if self.state.expression == "0" {
-            self.state.expression = number.to_string();
-        } else {
-            self.state.expression.push(number);
-        }
-
-        self.state.is_empty = self.state.expression.is_empty();
-
-        cx.emit(CalculatorEvent::NumberPressed);
-        cx.emit(CalculatorEvent::ExpressionChanged);
-        cx.notify();
-    }
-
-    /// 处理操作符输入
-    pub fn input_operator(&mut self, operator: char, cx: &mut Context<Self>) {
-        // 百分比由单独的 input_percentage 方法处理
-        if operator == '%' {
-            self.input_percentage(cx);
-            return;
-        }
-
-        // 标准化操作符
-        let normalized_op = match operator {
-            '×' => '*',
-            '÷' => '/',
-            _ => operator,
-        };
-
-        // 检查标准化后的操作符是否有效
-        let valid_operators = ['+', '-', '*', '/'];
-        if !valid_operators.contains(&normalized_op) {
-            return;
-        }
-
-        if self.state.just_calculated {
-            if let Some(result) = self.state.last_result {
-                self.state.expression = format!("{}", result);
-            }
-            self.state.just_calculated = false;
-        }
-
-        // 确保表达式不以操作符结尾
-        if let Some(last_char) = self.state.expression.chars().last() {
-            let all_operators = ['+', '-', '*', '/', '×', '÷'];
-            if all_operators.contains(&last_char) {
-                // 替换最后一个操作符
-                self.state.expression.pop();
+            core,
+            session_path: None,
+            restore_error: None,
+            save_error: None,
+        }
+    }
+
+    /// 从会话文件恢复，之后每次计算和退出时保存到该文件；
+    /// 文件无法读取时改名备份，从空白会话开始
+    pub fn restore_session(&mut self, path: PathBuf) {
+        match store::load(&path) {
+            Ok(Some(saved)) => {
+                let failures = self.core.restore(saved);
+                if !failures.is_empty() {
+                    self.restore_error = Some(format!(
+                        "Session restored; {} saved value(s) reset to 0 ({})",
+                        failures.len(),
+                        failures.join("; ")
+                    ));
+                }
             }
-        }
-
-        self.state.expression.push(normalized_op);
-
-        cx.emit(CalculatorEvent::OperatorPressed);
-        cx.emit(CalculatorEvent::ExpressionChanged);
-        cx.notify();
-    }
-
-    /// 处理百分比输入
-    pub fn input_percentage(&mut self, cx: &mut Context<Self>) {
-        if self.state.just_calculated {
-            if let Some(result) = self.state.last_result {
-                self.state.expression = format!("{}", result);
+            Ok(None) => {}
+            Err(error) => {
+                self.restore_error = Some(match store::quarantine(&path) {
+                    Some(backup) => format!(
+                        "Session not restored ({}); moved to {}",
+                        error,
+                        backup.display()
+                    ),
+                    None => format!("Session not restored: {}", error),
+                });
             }
-            self.state.just_calculated = false;
         }
-
-        // 添加百分号到表达式
-        self.state.expression.push('%');
-
-        cx.emit(CalculatorEvent::PercentagePressed);
-        cx.emit(CalculatorEvent::ExpressionChanged);
-        cx.notify();
+        self.session_path = Some(path);
     }
 
-    /// 处理括号输入
-    pub fn input_parenthesis(&mut self, parenthesis: char, cx: &mut Context<Self>) {
-        if !['(', ')'].contains(&parenthesis) {
+    /// 把当前会话写入会话文件
+    pub fn save_session(&mut self) {
+        let Some(path) = &self.session_path else {
             return;
-        }
-
-        if self.state.just_calculated {
-            self.state.expression = "0".to_string();
-            self.state.just_calculated = false;
-        }
-
-        // 检查括号是否与表达式匹配
-        if parenthesis == ')' {
-            // 统计现有的左右括号数量，确保不会添加不匹配的右括号
-            let left_count = self.state.expression.chars().filter(|&c| c == '(').count();
-            let right_count = self.state.expression.chars().filter(|&c| c == ')').count();
-            if left_count <= right_count {
-                // 右括号多于左括号，不添加
-                return;
-            }
-        }
-
-        // 对于左括号，如果前面是数字，可能需要添加乘号
-        if parenthesis == '(' {
-            if let Some(last_char) = self.state.expression.chars().last() {
-                if last_char.is_ascii_digit() || last_char == ')' {
-                    // 数字后面直接跟左括号，需要添加乘号
-                    self.state.expression.push('*');
-                }
-            }
-        }
-
-        // 对于右括号，如果后面是数字，可能需要添加乘号
-        if parenthesis == ')' {
-            // 这里主要是为了显示，实际计算由解析器处理
-            // 右括号后面跟随数字时，会在计算时由解析器处理
-        }
-
-        self.state.expression.push(parenthesis);
-
-        cx.emit(CalculatorEvent::ParenthesisPressed);
-        cx.emit(CalculatorEvent::ExpressionChanged);
-        cx.notify();
-    }
-
-    /// 处理小数点输入
-    pub fn input_decimal(&mut self, cx: &mut Context<Self>) {
-        if self.state.just_calculated {
-            self.state.expression = "0".to_string();
-            self.state.just_calculated = false;
-        }
-
-        // 检查当前数字是否已经包含小数点
-        let parts: Vec<&str> = self.state.expression.split(['+', '-', '*', '/']).collect();
-        if let Some(current_number) = parts.last() {
-            if !current_number.contains('.') {
-                self.state.expression.push('.');
-
-                cx.emit(CalculatorEvent::DecimalPressed);
-                cx.emit(CalculatorEvent::ExpressionChanged);
-                cx.notify();
-            }
-        } else {
-            self.state.expression.push('.');
-
-            cx.emit(CalculatorEvent::DecimalPressed);
-            cx.emit(CalculatorEvent::ExpressionChanged);
-            cx.notify();
-        }
+        };
+        self.save_error = store::save(path, &self.core.to_saved())
+            .err()
+            .map(|error| format!("Session not saved: {}", error));
     }
 
-    /// 清除计算器状态
-    pub fn clear(&mut self, cx: &mut Context<Self>) {
-        self.state = CalculatorState::default();
-
-        cx.emit(CalculatorEvent::ClearPressed);
-        cx.emit(CalculatorEvent::StateReset);
-        cx.notify();
+    /// 会话文件读取或保存失败时的错误信息
+    pub fn session_status(&self) -> Option<String> {
+        self.save_error
+            .clone()
+            .or_else(|| self.restore_error.clone())
     }
 
-    /// 删除最后一个字符
-    pub fn delete(&mut self, cx: &mut Context<Self>) {
-        if self.state.just_calculated {
-            self.clear(cx);
+    /// 执行一条命令，发出产生的事件；状态有变化时通知订阅者重新渲染
+    pub fn apply(&mut self, command: Command, cx: &mut Context<Self>) {
+        let events = self.core.apply(command);
+        if events.is_empty() {
             return;
         }
-
-        self.state.expression.pop();
-
-        if self.state.expression.is_empty() {
-            self.state.expression = "0".to_string();
+        // 每次计算、固定历史记录、修改记忆寄存器和 RPN 栈后保存会话
+        if events.iter().any(|event| {
+            matches!(
+                event,
+                CalculatorEvent::ResultCalculated { .. }
+                    | CalculatorEvent::PinToggled { .. }
+                    | CalculatorEvent::MemoryChanged
+                    | CalculatorEvent::StackChanged
+            )
+        }) {
+            self.save_session();
+        }
+        for event in events {
+            cx.emit(event);
         }
-
-        self.state.is_empty = self.state.expression.is_empty();
-
-        cx.emit(CalculatorEvent::DeletePressed);
-        cx.emit(CalculatorEvent::ExpressionChanged);
         cx.notify();
     }
 
-    /// 切换逗号格式化显示模式
-    pub fn toggle_comma_formatting(&mut self, cx: &mut Context<Self>) {
-        self.state.comma_formatting = !self.state.comma_formatting;
-
-        cx.emit(CalculatorEvent::CommaFormattingToggled);
-        cx.notify();
-    }
-
-    /// 执行计算
-    pub fn calculate(&mut self, cx: &mut Context<Self>) -> Option<f64> {
-        use crate::logic;
-
-        if self.state.expression.is_empty() || self.state.expression == "0" {
-            return None;
-        }
-
-        // 标准化表达式字符串（将×和÷替换为*和/）
-        let mut normalized_expr = self.state.expression.replace('×', "*").replace('÷', "/");
-
-        // 处理百分比表达式
-        normalized_expr = self.process_percentage_expression(&normalized_expr);
-
-        match logic::calculate(&normalized_expr) {
-            result => {
-                self.state.last_result = Some(result);
-                self.state.just_calculated = true;
-
-                cx.emit(CalculatorEvent::EqualsPressed);
-                cx.emit(CalculatorEvent::ResultCalculated);
-                cx.notify();
-
-                Some(result)
-            }
-        }
-    }
-
-    /// 处理百分比表达式，将 % 转换为 /100.0 或根据上下文处理
-    fn process_percentage_expression(&self, expr: &str) -> String {
-        let mut result = String::new();
-        let mut chars = expr.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '%' {
-                // 处理百分比：转换为 /100.0
-                // 如果是紧跟在数字后面，比如 "50%" 变成 "50/100.0"
-                // 如果是 "50+10%" 需要根据上下文，这里简单处理为除以100
-                result.push_str("/100.0");
-            } else {
-                result.push(c);
-            }
-        }
-
-        result
+    /// 计算器核心，用于读取显示内容、数据集和各项设置
+    pub fn core(&self) -> &CalculatorCore {
+        &self.core
     }
 
-    // 获取当前表达式
-    // pub fn expression(&self) -> &str {
-    //     &self.state.expression
-    // }
-
-    /// 格式化数字字符串，整数部分每三位添加逗号
-    fn format_with_commas(&self, number_str: &str) -> String {
-        if !self.state.comma_formatting {
-            return number_str.to_string();
-        }
-
-        // 检查是否为负数
-        let (sign, abs_number_str) = if number_str.starts_with('-') {
-            ("-", &number_str[1..])
-        } else {
-            ("", number_str)
-        };
-
-        // 分割整数部分和小数部分
-        let parts: Vec<&str> = abs_number_str.split('.').collect();
-        let integer_part = parts[0];
-        let decimal_part = if parts.len() > 1 { parts[1] } else { "" };
-
-        // 处理整数部分的逗号格式化
-        let mut formatted = String::new();
-        formatted.push_str(sign);
-
-        let chars: Vec<char> = integer_part.chars().collect();
-        let len = chars.len();
-
-        for (i, &ch) in chars.iter().enumerate() {
-            let pos_from_end = len - i;
-            formatted.push(ch);
-
-            // 每三位添加逗号，但不在开头或结尾
-            if pos_from_end > 1 && (pos_from_end - 1) % 3 == 0 && i < len - 1 {
-                formatted.push(',');
+    /// 订阅某一种计算器事件：select 从事件中取出关心的数据（如 CalculatorEvent::result），
+    /// 取到时调用 handler，其他事件被忽略
+    pub fn subscribe<T: 'static, D: 'static>(
+        calculator: &Entity<Calculator>,
+        cx: &mut Context<T>,
+        select: fn(&CalculatorEvent) -> Option<D>,
+        mut handler: impl FnMut(&mut T, D, &mut Context<T>) + 'static,
+    ) -> Subscription {
+        cx.subscribe(calculator, move |this, _, event, cx| {
+            if let Some(data) = select(event) {
+                handler(this, data, cx);
             }
-        }
-
-        // 重新组合小数部分
-        if !decimal_part.is_empty() {
-            formatted.push('.');
-            formatted.push_str(decimal_part);
-        }
-
-        formatted
+        })
     }
+}
 
-    /// 格式化表达式中的数字，整数部分每三位添加逗号
-    fn format_expression_with_commas(&self, expr: &str) -> String {
-        if !self.state.comma_formatting {
-            return expr.to_string();
-        }
-
-        let mut result = String::new();
-        let mut current_number = String::new();
-        let mut chars = expr.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            if ch.is_ascii_digit() || ch == '.' {
-                // 数字或小数点，累积到当前数字
-                current_number.push(ch);
-            } else {
-                // 遇到非数字字符，先处理累积的数字
-                if !current_number.is_empty() {
-                    result.push_str(&self.format_with_commas(&current_number));
-                    current_number.clear();
-                }
-                // 添加当前字符（运算符、括号等）
-                result.push(ch);
-            }
-        }
-
-        // 处理末尾可能存在的数字
-        if !current_number.is_empty() {
-            result.push_str(&self.format_with_commas(&current_number));
+impl CalculatorEvent {
+    /// ResultCalculated 事件的计算结果或错误
+    pub fn result(&self) -> Option<Result<Value, CalcError>> {
+        match self {
+            CalculatorEvent::ResultCalculated { result, .. } => Some(result.clone()),
+            _ => None,
         }
-
-        result
     }
 
-    /// 获取当前显示内容
-    pub fn display_text(&self) -> String {
-        if self.state.just_calculated {
-            if let Some(result) = self.state.last_result {
-                // 格式化结果，移除不必要的尾随零
-                let result_str = format!("{}", result);
-                let trimmed_result = if result_str.ends_with(".0") {
-                    result_str.trim_end_matches(".0").to_string()
-                } else {
-                    result_str
-                };
-
-                // 应用逗号格式化
-                self.format_with_commas(&trimmed_result)
-            } else {
-                "0".to_string()
-            }
-        } else if self.state.expression.is_empty() {
-            "0".to_string()
-        } else {
-            // 将*和/转换回×和÷以便显示
-            let display_expr = self.state.expression.replace('*', "×").replace('/', "÷");
-
-            // 对表达式中的所有数字应用逗号格式化
-            self.format_expression_with_commas(&display_expr)
+    /// ExpressionChanged 事件中变化后的表达式
+    pub fn expression(&self) -> Option<String> {
+        match self {
+            CalculatorEvent::ExpressionChanged { expression, .. } => Some(expression.clone()),
+            _ => None,
         }
     }
-
-    /// 获取计算器状态
-    #[allow(dead_code)]
-    pub fn state(&self) -> &CalculatorState {
-        &self.state
-    }
-
-    // 从字符串设置表达式（用于测试或恢复状态）
-    // pub fn set_expression(&mut self, expr: &str, cx: &mut Context<Self>) {
-    //     self.state.expression = expr.to_string();
-    //     self.state.just_calculated = false;
-    //     self.state.is_empty = expr.is_empty();
-
-    //     cx.emit(CalculatorEvent::ExpressionChanged);
-    //     cx.notify();
-    // }
 }
 
 /// 为计算器实现事件发射器
 impl EventEmitter<CalculatorEvent> for Calculator {}
-
-impl Clone for Calculator {
-    fn clone(&self) -> Self {
-        Self {
-            state: self.state.clone(),
-        }
-    }
-}
-
-impl Default for Calculator {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_format_with_commas() {
-        let mut calculator = Calculator::new();
-
-        // 测试没有逗号格式化的情况
-        assert_eq!(calculator.format_with_commas("1234567"), "1234567");
-
-        // 启用逗号格式化
-        calculator.state.comma_formatting = true;
-
-        // 测试正数
-        assert_eq!(calculator.format_with_commas("123"), "123");
-        assert_eq!(calculator.format_with_commas("1234"), "1,234");
-        assert_eq!(calculator.format_with_commas("1234567"), "1,234,567");
-        assert_eq!(calculator.format_with_commas("1234567890"), "1,234,567,890");
-
-        // 测试负数
-        assert_eq!(calculator.format_with_commas("-123"), "-123");
-        assert_eq!(calculator.format_with_commas("-1234"), "-1,234");
-        assert_eq!(calculator.format_with_commas("-1234567"), "-1,234,567");
-
-        // 测试小数
-        assert_eq!(calculator.format_with_commas("1234.56"), "1,234.56");
-        assert_eq!(calculator.format_with_commas("1234567.89"), "1,234,567.89");
-        assert_eq!(calculator.format_with_commas("-1234.56"), "-1,234.56");
-
-        // 测试边界情况
-        assert_eq!(calculator.format_with_commas("0"), "0");
-        assert_eq!(calculator.format_with_commas("0.0"), "0.0");
-        assert_eq!(calculator.format_with_commas(".5"), ".5");
-    }
-
-    #[test]
-    fn test_format_expression_with_commas() {
-        let mut calculator = Calculator::new();
-
-        // 测试没有逗号格式化的情况
-        assert_eq!(
-            calculator.format_expression_with_commas("123+456"),
-            "123+456"
-        );
-
-        // 启用逗号格式化
-        calculator.state.comma_formatting = true;
-
-        // 测试简单表达式
-        assert_eq!(
-            calculator.format_expression_with_commas("123+456"),
-            "123+456"
-        );
-        assert_eq!(
-            calculator.format_expression_with_commas("1234+5678"),
-            "1,234+5,678"
-        );
-
-        // 测试复杂表达式
-        assert_eq!(
-            calculator.format_expression_with_commas("1234.56+7890.12-3456.78"),
-            "1,234.56+7,890.12-3,456.78"
-        );
-
-        // 测试包含运算符和括号的表达式
-        assert_eq!(
-            calculator.format_expression_with_commas("(1234+5678)*9012"),
-            "(1,234+5,678)*9,012"
-        );
-
-        // 测试负数表达式
-        assert_eq!(
-            calculator.format_expression_with_commas("-1234+-5678"),
-            "-1,234+-5,678"
-        );
-
-        // 测试边界情况
-        assert_eq!(calculator.format_expression_with_commas(""), "");
-        assert_eq!(calculator.format_expression_with_commas("+"), "+");
-        assert_eq!(calculator.format_expression_with_commas("123"), "123");
-    }
-}
@@ -0,0 +1,3015 @@
+//! 计算器核心：不依赖 GPUI 的按键状态机
+//!
+//! 所有输入都表示为 [`Command`]，由 [`CalculatorCore::apply`] 修改状态并返回产生的事件，
+//! GPUI 实体只负责转发命令和发出事件，按键序列的行为可以直接在测试中验证。
+
+use super::store::{
+    self, SavedBreakdown, SavedEntry, SavedMemory, SavedSession, SavedSettings, SavedStack,
+    SavedState, SavedValue,
+};
+use crate::logic::finance::Cents;
+use crate::logic::format::NumberFormat;
+use crate::logic::locale::Locale;
+use crate::logic::retail::{self, Breakdown, Rounding};
+use crate::logic::{CalcError, Session, Value, currency::Rates, random::Rng, regression::Fit};
+use num_bigint::BigInt;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 计算器事件类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalculatorEvent {
+    /// 数字按键事件，包含数字字符
+    NumberPressed(char),
+    /// 操作符按键事件，包含标准化后的操作符字符（+ - * /）
+    OperatorPressed(char),
+    /// 小数点按键事件
+    DecimalPressed,
+    /// 括号按键事件，包含括号字符
+    ParenthesisPressed(char),
+    /// 函数按键事件，包含函数名（如 sin、cosh）
+    FunctionPressed(String),
+    /// 百分比按键事件
+    PercentagePressed,
+    /// 逗号格式化切换事件，包含切换后是否启用
+    CommaFormattingToggled(bool),
+    /// 清除按键事件
+    ClearPressed,
+    /// 删除按键事件
+    DeletePressed,
+    /// 等于按键事件，触发计算
+    EqualsPressed,
+    /// 表达式发生变化，包含变化前后的表达式字符串
+    ExpressionChanged {
+        previous: String,
+        expression: String,
+    },
+    /// 计算结果，包含求值的表达式（规范格式）和计算结果或错误
+    ResultCalculated {
+        expression: String,
+        result: Result<Value, CalcError>,
+    },
+    /// 计算器状态重置，包含重置前的状态
+    StateReset { previous: CalculatorState },
+    /// 统计数据集发生变化
+    DatasetChanged,
+    /// 回归拟合结果发生变化
+    FitChanged,
+    /// 零售步骤按键事件，包含步骤名（如 +tax、mu、tip）
+    RetailStepPressed(String),
+    /// 零售模式开关、税率或取整方式发生变化
+    RetailSettingsChanged,
+    /// 数值显示格式发生变化，包含新的格式
+    NumberFormatChanged(NumberFormat),
+    /// 区域数字格式发生变化，包含新的区域
+    LocaleChanged(Locale),
+    /// 从剪贴板粘贴了文本，包含整理后的文本
+    Pasted(String),
+    /// 取回了历史记录，包含记录的下标
+    HistoryRecalled(usize),
+    /// 历史记录的固定状态发生变化
+    PinToggled { index: usize, pinned: bool },
+    /// 撤销了一次修改
+    Undone,
+    /// 重做了一次修改
+    Redone,
+    /// 记忆寄存器 M 或编号寄存器的内容发生变化
+    MemoryChanged,
+    /// 取回了记忆寄存器，编号寄存器包含编号，M 为 None
+    MemoryRecalled(Option<usize>),
+    /// RPN 模式切换事件，包含切换后是否启用
+    RpnModeToggled(bool),
+    /// RPN 栈发生变化
+    StackChanged,
+}
+
+/// 计算器命令，对应一次按键或一次设置修改
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// 数字键
+    Number(char),
+    /// 运算符键（+ - × ÷，% 按百分比处理）
+    Operator(char),
+    /// 括号键
+    Parenthesis(char),
+    /// 函数键，插入函数名和左括号
+    Function(String),
+    /// 小数点键
+    Decimal,
+    /// AC 键
+    Clear,
+    /// 退格键
+    Delete,
+    /// 等号键
+    Equals,
+    /// 切换逗号格式化显示
+    ToggleCommaFormatting,
+    /// 切换零售模式
+    ToggleRetailMode,
+    /// 零售步骤（如 tip、mu、split）
+    RetailStep(String),
+    /// TAX+（true）或 TAX-（false），立即计算
+    ApplyTax(bool),
+    /// 把当前值存为税率
+    StoreTaxRate,
+    /// 切换小费和分摊的取整方式
+    CycleRounding,
+    /// 设置数值显示格式
+    SetNumberFormat(NumberFormat),
+    /// 设置区域数字格式
+    SetLocale(Locale),
+    /// 粘贴文本
+    Paste(String),
+    /// 替换统计数据集
+    SetDataset(Vec<f64>),
+    /// 设置最近一次回归拟合
+    SetLastFit(Fit),
+    /// 把第 n 条历史记录的表达式取回到输入
+    RecallExpression(usize),
+    /// 把第 n 条历史记录的结果取回到输入
+    RecallResult(usize),
+    /// 固定或取消固定第 n 条历史记录
+    TogglePin(usize),
+    /// 撤销上一次修改
+    Undo,
+    /// 重做上一次撤销的修改
+    Redo,
+    /// M+ / M− 键：先计算当前表达式，再把结果加到（true）或减出（false）记忆寄存器 M
+    MemoryAdd(bool),
+    /// MR 键：取回记忆寄存器 M
+    MemoryRecall,
+    /// MC 键：清空记忆寄存器 M
+    MemoryClear,
+    /// STO n：先计算当前表达式，再把结果存入第 n 个编号寄存器
+    StoreRegister(usize),
+    /// RCL n：取回第 n 个编号寄存器
+    RecallRegister(usize),
+    /// 清空全部编号寄存器
+    ClearRegisters,
+    /// 切换 RPN 模式
+    ToggleRpnMode,
+    /// RPN x⇄y：交换 X 和 Y
+    Swap,
+    /// RPN R↓：栈向下滚动，X 移到栈底
+    RollDown,
+    /// RPN LASTx：取回上一次运算前的 X
+    LastX,
+}
+
+/// 撤销栈最多保存的修改数
+const UNDO_LIMIT: usize = 500;
+
+/// 撤销栈中的快照：输入状态、各项设置、记忆寄存器和 RPN 栈；
+/// 计算历史和统计数据集不在快照中，撤销计算不会删除历史记录，固定历史记录和修改数据集也不能撤销
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    /// 输入状态
+    state: CalculatorState,
+    /// 显示和零售设置
+    settings: SettingsSnapshot,
+    /// 记忆寄存器
+    memory: Memory,
+    /// RPN 栈
+    stack: Stack,
+}
+
+/// RPN 模式的栈
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stack {
+    /// 栈中的值，X 在末尾
+    pub levels: Vec<Value>,
+    /// 上一次运算前的 X
+    pub last_x: Option<Value>,
+}
+
+/// 编号寄存器的个数（STO 0–9 / RCL 0–9）
+pub const REGISTER_COUNT: usize = 10;
+
+/// 记忆寄存器：M+ / M− 累加的 M 和十个编号寄存器，为 None 时表示空
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Memory {
+    /// 记忆寄存器 M
+    pub m: Option<Value>,
+    /// 编号寄存器
+    pub registers: [Option<Value>; REGISTER_COUNT],
+}
+
+/// 实时预览：对输入中的表达式求值所需的全部内容，可以移到后台线程求值
+#[derive(Debug, Clone)]
+pub struct Preview {
+    /// 标准化并补全右括号后的表达式（区域格式）
+    expression: String,
+    /// 表达式是否完整，末尾的运算符不参与预览时为 false
+    complete: bool,
+    /// 零售模式的设置，不在零售模式时为 None
+    retail: Option<retail::Settings>,
+    /// 求值会话的副本，预览不会改变计算器的随机数状态
+    session: Session,
+}
+
+impl Preview {
+    /// 表达式是否完整
+    pub fn complete(&self) -> bool {
+        self.complete
+    }
+
+    /// 按 = 键相同的规则求值，出错时为 None
+    pub fn evaluate(mut self) -> Option<Value> {
+        if let Some(settings) = &self.retail
+            && let Some(outcome) =
+                retail::evaluate_retail(&self.session.locale.delocalize(&self.expression), settings)
+        {
+            return outcome
+                .ok()
+                .map(|breakdown| Value::Number(breakdown.result.to_f64()));
+        }
+        let expression = process_percentage_expression(&self.expression);
+        crate::logic::evaluate_in(&expression, &mut self.session).ok()
+    }
+}
+
+/// 显示区一行能容纳的字符数（400px 宽、text_3xl 等宽字体），数值结果超过时改用科学计数法
+const DISPLAY_WIDTH: usize = 20;
+
+/// 计算器状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalculatorState {
+    /// 当前表达式
+    pub expression: String,
+    /// 上一个计算结果（如果有）
+    pub last_result: Option<Value>,
+    /// 是否刚执行过计算
+    pub just_calculated: bool,
+    /// 是否表达式为空
+    pub is_empty: bool,
+    /// 是否启用逗号格式化显示（每三位加逗号）
+    pub comma_formatting: bool,
+    /// 零售模式下最近一次计算的明细
+    pub breakdown: Option<Breakdown>,
+    /// 上一次计算的最后一个运算符和右侧运算数（如 2+3 的 + 和 3），连续按 = 时重复
+    pub repeat: Option<(char, String)>,
+}
+
+impl Default for CalculatorState {
+    fn default() -> Self {
+        Self {
+            expression: "0".to_string(),
+            last_result: None,
+            just_calculated: false,
+            is_empty: false,
+            comma_formatting: false,
+            breakdown: None,
+            repeat: None,
+        }
+    }
+}
+
+impl fmt::Display for CalculatorState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (true, Some(result)) = (self.just_calculated, &self.last_result) {
+            write!(f, "{}", result)
+        } else {
+            write!(f, "{}", self.expression)
+        }
+    }
+}
+
+/// 一条计算历史
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// 实际求值的表达式（已标准化）
+    pub expression: String,
+    /// 计算结果
+    pub result: Value,
+    /// 求值前随机数生成器的状态，以它为种子重新求值可复现含随机函数的结果
+    pub seed: u64,
+    /// 计算时间（Unix 时间戳，秒）
+    pub timestamp: i64,
+    /// 计算时显示的结果文本
+    pub display: String,
+    /// 计算时的显示和零售设置
+    pub settings: SettingsSnapshot,
+    /// 是否固定在历史列表顶部
+    pub pinned: bool,
+}
+
+impl HistoryEntry {
+    /// 按计算时的区域格式显示的表达式，如 1,5×2
+    pub fn expression_text(&self) -> String {
+        self.settings
+            .locale
+            .localize(&self.expression)
+            .replace('*', "×")
+            .replace('/', "÷")
+    }
+}
+
+/// 计算时的设置快照，用于在历史记录中说明结果是按什么设置显示和计算的
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SettingsSnapshot {
+    /// 数值显示格式
+    pub number_format: NumberFormat,
+    /// 区域数字格式
+    pub locale: Locale,
+    /// 是否启用逗号格式化
+    pub comma_formatting: bool,
+    /// 是否处于零售模式
+    pub retail_mode: bool,
+    /// 零售模式的税率和取整方式
+    pub retail: retail::Settings,
+    /// 是否处于 RPN 模式
+    pub rpn_mode: bool,
+}
+
+impl From<&SettingsSnapshot> for SavedSettings {
+    fn from(settings: &SettingsSnapshot) -> Self {
+        SavedSettings {
+            number_format: settings.number_format,
+            locale: settings.locale.name.to_string(),
+            comma_formatting: settings.comma_formatting,
+            retail_mode: settings.retail_mode,
+            retail: settings.retail,
+            rpn_mode: settings.rpn_mode,
+        }
+    }
+}
+
+/// 未知的区域代码按默认区域处理
+impl From<&SavedSettings> for SettingsSnapshot {
+    fn from(settings: &SavedSettings) -> Self {
+        SettingsSnapshot {
+            number_format: settings.number_format,
+            locale: Locale::named(&settings.locale).unwrap_or_default(),
+            comma_formatting: settings.comma_formatting,
+            retail_mode: settings.retail_mode,
+            retail: settings.retail,
+            rpn_mode: settings.rpn_mode,
+        }
+    }
+}
+
+/// 把计算结果转换为可以重新求值的表达式文本，质因数分解写成 factor(n)
+fn value_expression(value: &Value) -> String {
+    match (value, value.as_integer()) {
+        (Value::Factors(_), Some(n)) => format!("factor({})", n),
+        _ => result_expression(value),
+    }
+}
+
+/// 把计算结果转换为会话文件中的结构
+fn saved_value(value: &Value) -> SavedValue {
+    match value {
+        Value::Number(x) => SavedValue::Number(x.to_string()),
+        Value::Integer(n) => SavedValue::Integer(n.to_string()),
+        Value::Factors(factors) => SavedValue::Factors(
+            factors
+                .iter()
+                .map(|(prime, power)| (prime.to_string(), *power))
+                .collect(),
+        ),
+        Value::List(items) => SavedValue::List(items.iter().map(saved_value).collect()),
+        Value::Quantity(quantity) => SavedValue::Quantity {
+            value: quantity.value.to_string(),
+            dimension: quantity.dimension,
+            display: quantity.display.clone(),
+        },
+        Value::Date(days) => SavedValue::Date(*days),
+    }
+}
+
+/// 还原保存的计算结果；旧版本保存的表达式文本在计算器的会话中重新求值（使用当前汇率），
+/// 文件内容无效或无法求值时返回错误信息
+fn restored_value(saved: &SavedValue, session: &mut Session) -> Result<Value, String> {
+    let number = |text: &str| {
+        text.parse::<f64>()
+            .map_err(|_| format!("invalid number {}", text))
+    };
+    let integer = |text: &str| {
+        text.parse::<BigInt>()
+            .map_err(|_| format!("invalid integer {}", text))
+    };
+    Ok(match saved {
+        SavedValue::Number(text) => Value::Number(number(text)?),
+        SavedValue::Integer(text) => Value::Integer(integer(text)?),
+        SavedValue::Factors(factors) => Value::Factors(
+            factors
+                .iter()
+                .map(|(prime, power)| Ok((integer(prime)?, *power)))
+                .collect::<Result<_, String>>()?,
+        ),
+        SavedValue::List(items) => Value::List(
+            items
+                .iter()
+                .map(|item| restored_value(item, session))
+                .collect::<Result<_, _>>()?,
+        ),
+        SavedValue::Quantity {
+            value,
+            dimension,
+            display,
+        } => Value::Quantity(crate::logic::units::Quantity {
+            value: number(value)?,
+            dimension: *dimension,
+            display: display.clone(),
+        }),
+        SavedValue::Date(days) => Value::Date(*days),
+        SavedValue::Expression(text) => crate::logic::evaluate_in(text, session)
+            .map_err(|error| format!("{}: {}", text, error))?,
+    })
+}
+
+/// M+ / M− 的累加：精确整数之间保持精确，其余按浮点数计算；不是数值时返回 None
+fn accumulate(memory: Option<&Value>, value: &Value, add: bool) -> Option<Value> {
+    let memory = memory.cloned().unwrap_or(Value::Integer(BigInt::from(0)));
+    match (memory.as_integer(), value.as_integer()) {
+        (Some(a), Some(b)) => Some(Value::Integer(if add { a + b } else { a - b })),
+        _ => {
+            let (a, b) = (memory.as_number()?, value.as_number()?);
+            Some(Value::Number(if add { a + b } else { a - b }))
+        }
+    }
+}
+
+/// RPN 运算记入历史时的运算数文本，不是非负数时加括号
+fn rpn_operand(value: &Value) -> String {
+    let text = value_expression(value);
+    if text.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        text
+    } else {
+        format!("({})", text)
+    }
+}
+
+/// 表达式最外层的最后一个二元运算符（+ - * /）和它右侧的运算数，如 2+3*4 → (*, 4)；
+/// 紧跟在运算符或左括号之后的正负号、科学计数法的指数符号和日期字面量中的 - 不算
+fn last_operation(expr: &str) -> Option<(char, String)> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut depth = 0usize;
+    let mut last = None;
+    // 日期字面量 YYYY-MM-DD 在此位置之前
+    let mut resume = 0;
+    for (i, &c) in chars.iter().enumerate() {
+        if i < resume {
+            continue;
+        }
+        let starts_number = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '.');
+        if c.is_ascii_digit() && starts_number {
+            let rest: String = chars[i..].iter().collect();
+            if let Some(literal) = crate::logic::dates::parse_literal(&rest) {
+                resume = i + literal.map_or(10, |(_, length)| length);
+                continue;
+            }
+        }
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            '+' | '-' | '*' | '/' if depth == 0 && i > 0 => {
+                let previous = chars[i - 1];
+                let exponent = matches!(previous, 'e' | 'E')
+                    && i > 1
+                    && chars[i - 2].is_ascii_digit()
+                    && matches!(c, '+' | '-');
+                if !matches!(previous, '+' | '-' | '*' | '/' | '(') && !exponent {
+                    last = Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    let i = last?;
+    let operand: String = chars[i + 1..].iter().collect();
+    (!operand.is_empty()).then(|| (chars[i], operand))
+}
+
+/// 标准化表达式字符串：将×和÷替换为*和/，并自动补全缺失的右括号，例如函数按键输入的 "sin(30"
+fn normalize_expression(expression: &str) -> String {
+    let mut normalized = expression.replace('×', "*").replace('÷', "/");
+    let left_count = normalized.chars().filter(|&c| c == '(').count();
+    let right_count = normalized.chars().filter(|&c| c == ')').count();
+    normalized.push_str(&")".repeat(left_count.saturating_sub(right_count)));
+    normalized
+}
+
+/// 处理百分比表达式，将 % 转换为 /100.0 或根据上下文处理
+fn process_percentage_expression(expr: &str) -> String {
+    let mut result = String::new();
+    for c in expr.chars() {
+        if c == '%' {
+            // 处理百分比：转换为 /100.0
+            // 如果是紧跟在数字后面，比如 "50%" 变成 "50/100.0"
+            // 如果是 "50+10%" 需要根据上下文，这里简单处理为除以100
+            result.push_str("/100.0");
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// 当前的 Unix 时间戳（秒）
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+/// 把计算结果转换为可以继续编辑的表达式文本，质因数分解取乘积，时长取总秒数
+fn result_expression(result: &Value) -> String {
+    match (result, result.as_integer()) {
+        (_, Some(n)) => n.to_string(),
+        (Value::Quantity(quantity), None) => quantity.expression(),
+        (_, None) => result.to_string(),
+    }
+}
+
+/// 计算器核心 - 管理计算器状态，不依赖 GPUI
+#[derive(Clone)]
+pub struct CalculatorCore {
+    state: CalculatorState,
+    /// 计算历史，按时间顺序排列
+    history: Vec<HistoryEntry>,
+    /// 统计模式的数据集（与表达式状态相互独立）
+    dataset: Vec<f64>,
+    /// 表达式求值会话（保存最近一次回归拟合等）
+    session: Session,
+    /// 汇率文件读取失败时的错误信息
+    rates_error: Option<String>,
+    /// 是否处于零售模式，零售模式下 % 按加价、折扣链计算
+    retail_mode: bool,
+    /// 零售模式的税率记忆和取整方式
+    retail: retail::Settings,
+    /// 数值结果的显示格式（AC 不会重置）
+    number_format: NumberFormat,
+    /// 记忆寄存器（AC 不会清空）
+    memory: Memory,
+    /// 是否处于 RPN 模式，RPN 模式下运算键直接作用于栈
+    rpn_mode: bool,
+    /// RPN 栈
+    stack: Stack,
+    /// 撤销栈，最近的修改在末尾
+    undo_stack: Vec<Snapshot>,
+    /// 重做栈，最近撤销的修改在末尾
+    redo_stack: Vec<Snapshot>,
+    /// 当前显示内容，每次状态变化后计算一次，渲染时不再格式化大整数
+    display: String,
+}
+
+impl CalculatorCore {
+    /// 创建新的计算器实例
+    pub fn new() -> Self {
+        Self {
+            state: CalculatorState::default(),
+            history: Vec::new(),
+            dataset: Vec::new(),
+            session: Session::default(),
+            rates_error: None,
+            retail_mode: false,
+            retail: retail::Settings::default(),
+            number_format: NumberFormat::default(),
+            memory: Memory::default(),
+            rpn_mode: false,
+            stack: Stack::default(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            display: "0".to_string(),
+        }
+    }
+
+    /// 执行一条命令，返回产生的事件；命令没有改变任何状态时返回空列表
+    pub fn apply(&mut self, command: Command) -> Vec<CalculatorEvent> {
+        let previous = self.state.expression.clone();
+        // 数据集、回归拟合和历史记录的固定状态不随撤销改变
+        let undoable = !matches!(
+            command,
+            Command::Undo
+                | Command::Redo
+                | Command::SetDataset(_)
+                | Command::SetLastFit(_)
+                | Command::TogglePin(_)
+        );
+        let before = undoable.then(|| self.snapshot());
+        let mut events = match command {
+            Command::Equals if self.rpn_mode => self.rpn_enter(),
+            Command::Operator(operator) if self.rpn_mode => self.rpn_operator(operator),
+            Command::Function(name) if self.rpn_mode => self.rpn_function(&name),
+            Command::Parenthesis(_) if self.rpn_mode => Vec::new(),
+            Command::Delete if self.rpn_mode => self.rpn_delete(),
+            Command::Clear if self.rpn_mode => self.rpn_clear(),
+            Command::Number(number) => self.input_number(number),
+            Command::Operator(operator) => self.input_operator(operator),
+            Command::Parenthesis(parenthesis) => self.input_parenthesis(parenthesis),
+            Command::Function(name) => self.input_function(&name),
+            Command::Decimal => self.input_decimal(),
+            Command::Clear => self.clear(),
+            Command::Delete => self.delete(),
+            Command::Equals => self.equals(),
+            Command::ToggleCommaFormatting => self.toggle_comma_formatting(),
+            Command::ToggleRetailMode => self.toggle_retail_mode(),
+            Command::RetailStep(step) => self.input_retail_step(&step),
+            Command::ApplyTax(add) => self.apply_tax(add),
+            Command::StoreTaxRate => self.store_tax_rate(),
+            Command::CycleRounding => self.cycle_rounding(),
+            Command::SetNumberFormat(number_format) => self.set_number_format(number_format),
+            Command::SetLocale(locale) => self.set_locale(locale),
+            Command::Paste(text) => self.paste(&text),
+            Command::SetDataset(dataset) => self.set_dataset(dataset),
+            Command::SetLastFit(fit) => self.set_last_fit(fit),
+            Command::RecallExpression(index) => self.recall(index, false),
+            Command::RecallResult(index) => self.recall(index, true),
+            Command::TogglePin(index) => self.toggle_pin(index),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::MemoryAdd(add) => self.memory_add(add),
+            Command::MemoryRecall => self.recall_memory(None),
+            Command::MemoryClear => self.clear_memory(),
+            Command::StoreRegister(register) => self.store_register(register),
+            Command::RecallRegister(register) => self.recall_memory(Some(register)),
+            Command::ClearRegisters => self.clear_registers(),
+            Command::ToggleRpnMode => self.toggle_rpn_mode(),
+            Command::Swap => self.rpn_stack_operation(2, |levels| {
+                let n = levels.len();
+                levels.swap(n - 2, n - 1);
+            }),
+            Command::RollDown => self.rpn_stack_operation(2, |levels| levels.rotate_right(1)),
+            Command::LastX => self.rpn_last_x(),
+        };
+
+        // 状态有变化的命令记入撤销栈，新的修改使之前撤销的修改无法重做
+        if let Some(before) = before
+            && before != self.snapshot()
+        {
+            if self.undo_stack.len() == UNDO_LIMIT {
+                self.undo_stack.remove(0);
+            }
+            self.undo_stack.push(before);
+            self.redo_stack.clear();
+        }
+
+        // 表达式有变化时，紧跟在按键事件之后报告变化前后的表达式
+        if !events.is_empty() && self.state.expression != previous {
+            events.insert(
+                1,
+                CalculatorEvent::ExpressionChanged {
+                    previous,
+                    expression: self.state.expression.clone(),
+                },
+            );
+        }
+        self.display = self.display_text();
+        events
+    }
+
+    /// 当前的输入状态和设置
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state.clone(),
+            settings: self.settings_snapshot(),
+            memory: self.memory.clone(),
+            stack: self.stack.clone(),
+        }
+    }
+
+    /// 恢复输入状态和设置
+    fn restore_snapshot(&mut self, snapshot: Snapshot) {
+        self.state = snapshot.state;
+        self.number_format = snapshot.settings.number_format;
+        self.session.locale = snapshot.settings.locale;
+        self.retail_mode = snapshot.settings.retail_mode;
+        self.retail = snapshot.settings.retail;
+        self.rpn_mode = snapshot.settings.rpn_mode;
+        self.memory = snapshot.memory;
+        self.stack = snapshot.stack;
+    }
+
+    /// 撤销上一次修改
+    fn undo(&mut self) -> Vec<CalculatorEvent> {
+        let Some(snapshot) = self.undo_stack.pop() else {
+            return Vec::new();
+        };
+        self.redo_stack.push(self.snapshot());
+        self.restore_snapshot(snapshot);
+
+        vec![CalculatorEvent::Undone]
+    }
+
+    /// 重做上一次撤销的修改
+    fn redo(&mut self) -> Vec<CalculatorEvent> {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return Vec::new();
+        };
+        self.undo_stack.push(self.snapshot());
+        self.restore_snapshot(snapshot);
+
+        vec![CalculatorEvent::Redone]
+    }
+
+    /// 是否有可以撤销的修改
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// 是否有可以重做的修改
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// 读取默认位置的汇率文件，供表达式中的货币换算使用
+    pub fn load_rates(&mut self) {
+        match Rates::load_default() {
+            Ok(rates) => {
+                self.session.rates = rates;
+                self.rates_error = None;
+            }
+            Err(error) => {
+                self.session.rates = None;
+                self.rates_error = Some(error);
+            }
+        }
+    }
+
+    /// 汇率状态：已读取时为汇率时间戳，读取失败时为错误信息，没有汇率文件时为 None
+    pub fn rates_status(&self) -> Option<String> {
+        match (&self.session.rates, &self.rates_error) {
+            (Some(rates), _) => Some(format!("Rates: {}", rates.timestamp)),
+            (None, Some(error)) => Some(error.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// 处理数字输入
+    fn input_number(&mut self, number: char) -> Vec<CalculatorEvent> {
+        if !number.is_ascii_digit() {
+            return Vec::new();
+        }
+
+        if self.state.just_calculated {
+            self.state.expression.clear();
+            self.state.just_calculated = false;
+        }
+
+        if self.state.expression == "0" {
+            self.state.expression = number.to_string();
+        } else {
+            self.state.expression.push(number);
+        }
+
+        self.state.is_empty = self.state.expression.is_empty();
+
+        vec![CalculatorEvent::NumberPressed(number)]
+    }
+
+    /// 处理操作符输入
+    fn input_operator(&mut self, operator: char) -> Vec<CalculatorEvent> {
+        // 百分比由单独的 input_percentage 方法处理
+        if operator == '%' {
+            return self.input_percentage();
+        }
+
+        // 标准化操作符
+        let normalized_op = match operator {
+            '×' => '*',
+            '÷' => '/',
+            _ => operator,
+        };
+
+        // 检查标准化后的操作符是否有效
+        let valid_operators = ['+', '-', '*', '/'];
+        if !valid_operators.contains(&normalized_op) {
+            return Vec::new();
+        }
+
+        if self.state.just_calculated {
+            if let Some(result) = &self.state.last_result {
+                self.state.expression = self.result_text(result);
+            }
+            self.state.just_calculated = false;
+        }
+
+        // 确保表达式不以操作符结尾
+        if let Some(last_char) = self.state.expression.chars().last() {
+            let all_operators = ['+', '-', '*', '/', '×', '÷'];
+            if all_operators.contains(&last_char) {
+                // 替换最后一个操作符
+                self.state.expression.pop();
+            }
+        }
+
+        self.state.expression.push(normalized_op);
+
+        vec![CalculatorEvent::OperatorPressed(normalized_op)]
+    }
+
+    /// 处理百分比输入
+    fn input_percentage(&mut self) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated {
+            if let Some(result) = &self.state.last_result {
+                self.state.expression = self.result_text(result);
+            }
+            self.state.just_calculated = false;
+        }
+
+        // 添加百分号到表达式
+        self.state.expression.push('%');
+
+        vec![CalculatorEvent::PercentagePressed]
+    }
+
+    /// 处理括号输入
+    fn input_parenthesis(&mut self, parenthesis: char) -> Vec<CalculatorEvent> {
+        if !['(', ')'].contains(&parenthesis) {
+            return Vec::new();
+        }
+
+        if self.state.just_calculated {
+            self.state.expression = "0".to_string();
+            self.state.just_calculated = false;
+        }
+
+        // 检查括号是否与表达式匹配
+        if parenthesis == ')' {
+            // 统计现有的左右括号数量，确保不会添加不匹配的右括号
+            let left_count = self.state.expression.chars().filter(|&c| c == '(').count();
+            let right_count = self.state.expression.chars().filter(|&c| c == ')').count();
+            if left_count <= right_count {
+                // 右括号多于左括号，不添加
+                return Vec::new();
+            }
+        }
+
+        // 对于左括号，如果前面是数字，可能需要添加乘号
+        if parenthesis == '('
+            && let Some(last_char) = self.state.expression.chars().last()
+            && (last_char.is_ascii_digit() || last_char == ')')
+        {
+            // 数字后面直接跟左括号，需要添加乘号
+            self.state.expression.push('*');
+        }
+
+        // 对于右括号，如果后面是数字，可能需要添加乘号
+        if parenthesis == ')' {
+            // 这里主要是为了显示，实际计算由解析器处理
+            // 右括号后面跟随数字时，会在计算时由解析器处理
+        }
+
+        self.state.expression.push(parenthesis);
+
+        vec![CalculatorEvent::ParenthesisPressed(parenthesis)]
+    }
+
+    /// 处理函数输入，在表达式末尾插入函数名和左括号
+    fn input_function(&mut self, name: &str) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated || self.state.expression == "0" {
+            self.state.expression.clear();
+            self.state.just_calculated = false;
+        }
+
+        // 数字或右括号后面直接跟函数，添加乘号
+        if let Some(last_char) = self.state.expression.chars().last()
+            && (last_char.is_ascii_digit() || last_char == ')')
+        {
+            self.state.expression.push('*');
+        }
+
+        self.state.expression.push_str(name);
+        self.state.expression.push('(');
+        self.state.is_empty = false;
+
+        vec![CalculatorEvent::FunctionPressed(name.to_string())]
+    }
+
+    /// 输入零售步骤（如 tip、mu、split），上一次计算的结果作为基础金额
+    fn input_retail_step(&mut self, step: &str) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated {
+            if let Some(result) = &self.state.last_result {
+                self.state.expression = self.result_text(result);
+            }
+            self.state.just_calculated = false;
+        }
+
+        self.state.expression.push_str(step);
+
+        vec![CalculatorEvent::RetailStepPressed(step.to_string())]
+    }
+
+    /// TAX+ / TAX- 键：按记忆的税率加税或扣税，立即计算并显示明细
+    fn apply_tax(&mut self, add: bool) -> Vec<CalculatorEvent> {
+        let mut events = self.input_retail_step(if add { "+tax" } else { "-tax" });
+        events.extend(self.calculate());
+        events
+    }
+
+    /// 把当前表达式的值存为税率（百分数）
+    fn store_tax_rate(&mut self) -> Vec<CalculatorEvent> {
+        let rate = if self.state.just_calculated {
+            self.state.last_result.as_ref().and_then(Value::as_number)
+        } else {
+            let expr = self.state.expression.replace('×', "*").replace('÷', "/");
+            crate::logic::evaluate(&self.session.locale.delocalize(&expr))
+                .ok()
+                .and_then(|value| value.as_number())
+        }
+        .filter(|rate| rate.is_finite() && *rate >= 0.0);
+        let Some(rate) = rate else {
+            return Vec::new();
+        };
+        self.retail.tax_rate = rate;
+        self.state.last_result = Some(Value::Number(rate));
+        self.state.breakdown = None;
+        self.state.just_calculated = true;
+
+        vec![CalculatorEvent::RetailSettingsChanged]
+    }
+
+    /// 切换零售模式
+    fn toggle_retail_mode(&mut self) -> Vec<CalculatorEvent> {
+        self.retail_mode = !self.retail_mode;
+        if self.retail_mode {
+            self.rpn_mode = false;
+        }
+
+        vec![CalculatorEvent::RetailSettingsChanged]
+    }
+
+    /// 切换小费和分摊的取整方式
+    fn cycle_rounding(&mut self) -> Vec<CalculatorEvent> {
+        self.retail.rounding = self.retail.rounding.next();
+
+        vec![CalculatorEvent::RetailSettingsChanged]
+    }
+
+    /// 是否处于零售模式
+    pub fn retail_mode(&self) -> bool {
+        self.retail_mode
+    }
+
+    /// 当前的取整方式
+    pub fn rounding(&self) -> Rounding {
+        self.retail.rounding
+    }
+
+    /// 零售模式的状态：税率和取整方式，不在零售模式时为 None
+    pub fn retail_status(&self) -> Option<String> {
+        self.retail_mode.then(|| {
+            format!(
+                "Retail · Tax {}% · Round {}",
+                self.retail.tax_rate,
+                self.retail.rounding.label()
+            )
+        })
+    }
+
+    /// 零售计算的明细行（按区域格式显示数字），只在显示计算结果时返回
+    pub fn breakdown_lines(&self) -> Vec<String> {
+        match (&self.state.breakdown, self.state.just_calculated) {
+            (Some(breakdown), true) => breakdown
+                .lines
+                .iter()
+                .map(|line| self.session.locale.localize(line))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 把计算结果转换为当前区域格式的表达式文本，用于继续编辑
+    fn result_text(&self, result: &Value) -> String {
+        self.session.locale.localize(&result_expression(result))
+    }
+
+    /// 当前的区域数字格式
+    pub fn locale(&self) -> Locale {
+        self.session.locale
+    }
+
+    /// 切换区域数字格式，正在编辑的表达式同时转换为新格式
+    fn set_locale(&mut self, locale: Locale) -> Vec<CalculatorEvent> {
+        let canonical = self.session.locale.delocalize(&self.state.expression);
+        self.state.expression = locale.localize(&canonical);
+        self.session.locale = locale;
+
+        vec![CalculatorEvent::LocaleChanged(locale)]
+    }
+
+    /// 按系统区域设置（LC_ALL、LC_NUMERIC、LANG）选择数字格式
+    pub fn use_system_locale(&mut self) {
+        self.session.locale = Locale::from_env();
+        self.display = self.display_text();
+    }
+
+    /// 粘贴文本到表达式末尾，文本中的数字按当前区域格式解析
+    fn paste(&mut self, text: &str) -> Vec<CalculatorEvent> {
+        let text: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return Vec::new();
+        }
+
+        if self.state.just_calculated || self.state.expression == "0" {
+            self.state.expression.clear();
+            self.state.just_calculated = false;
+        }
+        self.state.expression.push_str(&text);
+        self.state.is_empty = false;
+
+        vec![CalculatorEvent::Pasted(text)]
+    }
+
+    /// 处理小数点输入
+    fn input_decimal(&mut self) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated {
+            self.state.expression = "0".to_string();
+            self.state.just_calculated = false;
+        }
+
+        // 检查当前数字是否已经包含小数点（小数点符号取决于区域格式）
+        let decimal = self.session.locale.decimal;
+        let current_number = self
+            .state
+            .expression
+            .rsplit(|c: char| !(c.is_ascii_digit() || c == decimal))
+            .next()
+            .unwrap_or_default();
+        if current_number.contains(decimal) {
+            return Vec::new();
+        }
+        self.state.expression.push(decimal);
+
+        vec![CalculatorEvent::DecimalPressed]
+    }
+
+    /// 清除计算器状态
+    fn clear(&mut self) -> Vec<CalculatorEvent> {
+        let previous = std::mem::take(&mut self.state);
+
+        vec![
+            CalculatorEvent::ClearPressed,
+            CalculatorEvent::StateReset { previous },
+        ]
+    }
+
+    /// 删除最后一个字符
+    fn delete(&mut self) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated {
+            return self.clear();
+        }
+
+        // 删除左括号时连同前面的函数名一起删除，零售步骤（如 tip、+tax）也整体删除
+        match self.state.expression.pop() {
+            Some('(') => {
+                self.delete_word();
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let word = format!("{}{}", self.delete_word(), c);
+                // +tax、-tax 连同前面的符号一起删除
+                if word == "tax" {
+                    self.state.expression.pop();
+                }
+            }
+            _ => {}
+        }
+
+        if self.state.expression.is_empty() {
+            self.state.expression = "0".to_string();
+        }
+
+        self.state.is_empty = self.state.expression.is_empty();
+
+        vec![CalculatorEvent::DeletePressed]
+    }
+
+    /// 删除表达式末尾的字母，返回删除的部分
+    fn delete_word(&mut self) -> String {
+        let expression = &mut self.state.expression;
+        let start = expression
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .len();
+        expression.split_off(start)
+    }
+
+    /// 切换逗号格式化显示模式
+    fn toggle_comma_formatting(&mut self) -> Vec<CalculatorEvent> {
+        self.state.comma_formatting = !self.state.comma_formatting;
+
+        vec![CalculatorEvent::CommaFormattingToggled(
+            self.state.comma_formatting,
+        )]
+    }
+
+    /// 当前的数值显示格式
+    pub fn number_format(&self) -> NumberFormat {
+        self.number_format
+    }
+
+    /// 设置数值显示格式
+    fn set_number_format(&mut self, number_format: NumberFormat) -> Vec<CalculatorEvent> {
+        self.number_format = number_format;
+
+        vec![CalculatorEvent::NumberFormatChanged(number_format)]
+    }
+
+    /// = 键：连续按 = 时对上一个结果重复上一次的运算；之后输入一个新数再按 =，
+    /// 新数代替左侧运算数（如 2×5= 之后输入 7=，得到 7×5）
+    fn equals(&mut self) -> Vec<CalculatorEvent> {
+        if let Some((operator, operand)) = self.state.repeat.clone() {
+            let left = if self.state.just_calculated {
+                self.state
+                    .last_result
+                    .as_ref()
+                    .map(|result| self.result_text(result))
+            } else {
+                let locale = self.session.locale;
+                let expression = &self.state.expression;
+                expression
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == locale.decimal)
+                    .then(|| expression.clone())
+            };
+            if let Some(left) = left {
+                self.state.expression = format!("{}{}{}", left, operator, operand);
+            }
+        }
+        self.calculate()
+    }
+
+    /// 执行计算
+    fn calculate(&mut self) -> Vec<CalculatorEvent> {
+        use crate::logic;
+
+        if self.state.expression.is_empty() || self.state.expression == "0" {
+            return Vec::new();
+        }
+
+        let mut normalized_expr = normalize_expression(&self.state.expression);
+        let repeat = last_operation(&normalized_expr);
+
+        // 零售模式下先按零售规则计算（折扣链、税、小费等），不是零售表达式时照常求值
+        let retail_outcome = if self.retail_mode {
+            retail::evaluate_retail(
+                &self.session.locale.delocalize(&normalized_expr),
+                &self.retail,
+            )
+        } else {
+            None
+        };
+        if retail_outcome.is_none() {
+            // 处理百分比表达式
+            normalized_expr = process_percentage_expression(&normalized_expr);
+        }
+
+        // 结果只在这里计算一次并保存，渲染时读取保存的值，随机函数不会被重新求值
+        let seed = self.session.rng.state();
+        let outcome = match retail_outcome {
+            Some(Ok(breakdown)) => {
+                let result = Value::Number(breakdown.result.to_f64());
+                self.state.breakdown = Some(breakdown);
+                Ok(result)
+            }
+            Some(Err(error)) => {
+                // 零售计算的错误显示在明细中
+                self.state.breakdown = Some(Breakdown {
+                    lines: vec![error.to_string()],
+                    result: Cents(0),
+                });
+                Err(error)
+            }
+            None => {
+                self.state.breakdown = None;
+                logic::evaluate_in(&normalized_expr, &mut self.session)
+            }
+        };
+        // 计算失败时结果为 0，错误随 ResultCalculated 事件报告
+        let result = outcome.clone().unwrap_or(Value::Number(0.0));
+        self.state.last_result = Some(result.clone());
+        self.state.just_calculated = true;
+        // 出错或零售计算之后不重复运算
+        self.state.repeat = repeat.filter(|_| outcome.is_ok() && self.state.breakdown.is_none());
+        // 历史记录保存规范格式的表达式，与当时的区域格式无关
+        let expression = self.session.locale.delocalize(&normalized_expr);
+        self.push_history(expression.clone(), result, seed);
+
+        vec![
+            CalculatorEvent::EqualsPressed,
+            CalculatorEvent::ResultCalculated {
+                expression,
+                result: outcome,
+            },
+        ]
+    }
+
+    /// 把一次计算记入历史，expression 为规范格式，显示文本和设置取当前的
+    fn push_history(&mut self, expression: String, result: Value, seed: u64) {
+        self.history.push(HistoryEntry {
+            expression,
+            result,
+            seed,
+            timestamp: now(),
+            display: self.display_text(),
+            settings: self.settings_snapshot(),
+            pinned: false,
+        });
+    }
+
+    // 获取当前表达式
+    // pub fn expression(&self) -> &str {
+    //     &self.state.expression
+    // }
+
+    /// 把规范格式的数字字符串转换为区域格式，开启分组时按区域分组方式添加分组符号
+    fn format_with_commas(&self, number_str: &str) -> String {
+        let locale = self.session.locale;
+        let localized = locale.localize(number_str);
+        if self.state.comma_formatting {
+            locale.group(&localized)
+        } else {
+            localized
+        }
+    }
+
+    /// 按显示格式格式化数值并应用分组，超出显示宽度时改用科学计数法
+    fn format_number(&self, x: f64) -> String {
+        let text = self.format_with_commas(&self.number_format.format(x));
+        if text.chars().count() > DISPLAY_WIDTH {
+            self.session
+                .locale
+                .localize(&self.number_format.overflow(x, DISPLAY_WIDTH))
+        } else {
+            text
+        }
+    }
+
+    /// 给表达式（区域格式）中的数字添加分组符号
+    fn format_expression_with_commas(&self, expr: &str) -> String {
+        if !self.state.comma_formatting {
+            return expr.to_string();
+        }
+
+        let locale = self.session.locale;
+        let mut result = String::new();
+        let mut current_number = String::new();
+        for ch in expr.chars() {
+            if ch.is_ascii_digit() || ch == locale.decimal {
+                // 数字或小数点，累积到当前数字
+                current_number.push(ch);
+            } else {
+                // 遇到非数字字符，先处理累积的数字
+                if !current_number.is_empty() {
+                    result.push_str(&locale.group(&current_number));
+                    current_number.clear();
+                }
+                // 添加当前字符（运算符、括号等）
+                result.push(ch);
+            }
+        }
+
+        // 处理末尾可能存在的数字
+        if !current_number.is_empty() {
+            result.push_str(&locale.group(&current_number));
+        }
+
+        result
+    }
+
+    /// 按显示格式显示计算结果
+    pub fn format_value(&self, value: &Value) -> String {
+        match value {
+            Value::Number(result) => self.format_number(*result),
+            // AUTO 格式下大整数完整显示，其他格式按浮点数显示
+            Value::Integer(n) => match (
+                self.number_format,
+                value.as_number().filter(|x| x.is_finite()),
+            ) {
+                (NumberFormat::Auto, _) | (_, None) => self.format_with_commas(&n.to_string()),
+                (_, Some(x)) => self.format_number(x),
+            },
+            // 质因数分解和列表按原样显示，只替换小数点和分隔符
+            _ => self.session.locale.localize(&value.to_string()),
+        }
+    }
+
+    /// 当前显示内容（缓存的值）
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    /// 计算当前显示内容
+    fn display_text(&self) -> String {
+        if self.state.just_calculated {
+            match &self.state.last_result {
+                // 零售计算的金额固定显示两位小数
+                Some(_) if self.state.breakdown.is_some() => {
+                    let result = self.state.breakdown.as_ref().map_or(Cents(0), |b| b.result);
+                    self.format_with_commas(&result.to_string())
+                }
+                Some(result) => self.format_value(result),
+                None => "0".to_string(),
+            }
+        } else if self.state.expression.is_empty() {
+            "0".to_string()
+        } else {
+            // 将*和/转换回×和÷以便显示
+            let display_expr = self.state.expression.replace('*', "×").replace('/', "÷");
+
+            // 对表达式中的所有数字应用逗号格式化
+            self.format_expression_with_commas(&display_expr)
+        }
+    }
+
+    /// 获取计算器状态
+    #[allow(dead_code)]
+    pub fn state(&self) -> &CalculatorState {
+        &self.state
+    }
+
+    /// 获取计算历史
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// 当前的显示和零售设置
+    fn settings_snapshot(&self) -> SettingsSnapshot {
+        SettingsSnapshot {
+            number_format: self.number_format,
+            locale: self.session.locale,
+            comma_formatting: self.state.comma_formatting,
+            retail_mode: self.retail_mode,
+            retail: self.retail,
+            rpn_mode: self.rpn_mode,
+        }
+    }
+
+    /// 搜索历史记录，返回匹配的下标：固定的记录在前，其余按时间从新到旧；
+    /// 表达式或结果包含查询文本（不区分大小写，忽略空白）即为匹配
+    pub fn search_history(&self, query: &str) -> Vec<usize> {
+        let query: String = query.split_whitespace().collect::<String>().to_lowercase();
+        let matches = |entry: &HistoryEntry| {
+            [&entry.expression, &entry.expression_text(), &entry.display]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&query))
+        };
+        let mut indices: Vec<usize> = (0..self.history.len())
+            .rev()
+            .filter(|&index| matches(&self.history[index]))
+            .collect();
+        indices.sort_by_key(|&index| !self.history[index].pinned);
+        indices
+    }
+
+    /// 把历史记录的表达式或结果取回到当前输入；取回表达式时随机数生成器回到
+    /// 这次计算开始时的状态，再按 = 会重放出相同的随机值
+    fn recall(&mut self, index: usize, result: bool) -> Vec<CalculatorEvent> {
+        let Some(entry) = self.history.get(index) else {
+            return Vec::new();
+        };
+        let text = if result {
+            self.result_text(&entry.result)
+        } else {
+            self.session.rng = Rng::new(entry.seed);
+            self.session.locale.localize(&entry.expression)
+        };
+        self.insert_recalled(text);
+
+        vec![CalculatorEvent::HistoryRecalled(index)]
+    }
+
+    /// 把取回的文本放入当前输入：刚计算过或输入为 0 时替换，否则接在后面
+    fn insert_recalled(&mut self, text: String) {
+        // RPN 模式下取回的内容作为新的输入，正在输入的数先压入栈
+        if self.rpn_mode {
+            if self.state.expression != "0" && self.rpn_commit().is_some() {
+                return;
+            }
+            self.state.expression = text;
+            self.state.just_calculated = false;
+            return;
+        }
+        if self.state.just_calculated || self.state.expression == "0" {
+            self.state.expression = text;
+            self.state.just_calculated = false;
+        } else if text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == self.session.locale.decimal)
+        {
+            self.state.expression.push_str(&text);
+        } else {
+            // 接在已有输入后面时加括号，保持取回部分的运算顺序
+            self.state.expression.push_str(&format!("({})", text));
+        }
+        self.state.is_empty = false;
+    }
+
+    /// 当前的值：刚计算过时取上一个结果，否则先计算当前表达式（与按 = 相同）；
+    /// 计算出错时值为 None
+    fn current_value(&mut self) -> (Vec<CalculatorEvent>, Option<Value>) {
+        // RPN 模式下先压入正在输入的数，当前的值为 X
+        if self.rpn_mode {
+            return match self.rpn_commit() {
+                None => (Vec::new(), self.stack.levels.last().cloned()),
+                Some(event) => (vec![event], None),
+            };
+        }
+        if self.state.just_calculated {
+            return (Vec::new(), self.state.last_result.clone());
+        }
+        if self.state.expression.is_empty() || self.state.expression == "0" {
+            return (Vec::new(), Some(Value::Integer(BigInt::from(0))));
+        }
+        let events = self.calculate();
+        let value = events.iter().find_map(|event| match event {
+            CalculatorEvent::ResultCalculated { result, .. } => result.clone().ok(),
+            _ => None,
+        });
+        (events, value)
+    }
+
+    /// M+ / M−：把当前的值加到或减出记忆寄存器 M，不是数值时 M 不变
+    fn memory_add(&mut self, add: bool) -> Vec<CalculatorEvent> {
+        let (mut events, value) = self.current_value();
+        if let Some(sum) = value.and_then(|value| accumulate(self.memory.m.as_ref(), &value, add)) {
+            self.memory.m = Some(sum);
+            events.push(CalculatorEvent::MemoryChanged);
+        }
+        events
+    }
+
+    /// STO n：把当前的值存入第 n 个编号寄存器
+    fn store_register(&mut self, register: usize) -> Vec<CalculatorEvent> {
+        if register >= REGISTER_COUNT {
+            return Vec::new();
+        }
+        let (mut events, value) = self.current_value();
+        if let Some(value) = value {
+            self.memory.registers[register] = Some(value);
+            events.push(CalculatorEvent::MemoryChanged);
+        }
+        events
+    }
+
+    /// MR / RCL n：把记忆寄存器 M 或第 n 个编号寄存器取回到当前输入，寄存器为空时不变
+    fn recall_memory(&mut self, register: Option<usize>) -> Vec<CalculatorEvent> {
+        let value = match register {
+            Some(register) => self.memory.registers.get(register).cloned().flatten(),
+            None => self.memory.m.clone(),
+        };
+        let Some(value) = value else {
+            return Vec::new();
+        };
+        self.insert_recalled(self.result_text(&value));
+
+        vec![CalculatorEvent::MemoryRecalled(register)]
+    }
+
+    /// MC：清空记忆寄存器 M
+    fn clear_memory(&mut self) -> Vec<CalculatorEvent> {
+        if self.memory.m.take().is_none() {
+            return Vec::new();
+        }
+
+        vec![CalculatorEvent::MemoryChanged]
+    }
+
+    /// 清空全部编号寄存器
+    fn clear_registers(&mut self) -> Vec<CalculatorEvent> {
+        if self.memory.registers.iter().all(Option::is_none) {
+            return Vec::new();
+        }
+        self.memory.registers = Default::default();
+
+        vec![CalculatorEvent::MemoryChanged]
+    }
+
+    /// 记忆寄存器的内容
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// 输入中的表达式的实时预览；刚计算过、RPN 模式下、输入只是一个数或调用了
+    /// 耗时的函数（如 factor）时为 None。末尾的运算符不参与预览，此时预览标记为不完整
+    pub fn preview(&self) -> Option<Preview> {
+        if self.state.just_calculated || self.rpn_mode {
+            return None;
+        }
+        let expression = &self.state.expression;
+        let operand = expression.trim_end_matches(['+', '-', '*', '/', '×', '÷']);
+        let decimal = self.session.locale.decimal;
+        if operand.chars().all(|c| c.is_ascii_digit() || c == decimal) {
+            return None;
+        }
+        // 预览在后台线程求值，开始后无法中途停止，耗时的函数和巨大的整数只在按 = 时计算
+        let normalized = normalize_expression(operand);
+        if crate::logic::is_slow_to_evaluate(&normalized, &self.session.locale) {
+            return None;
+        }
+
+        Some(Preview {
+            expression: normalized,
+            complete: operand.len() == expression.len(),
+            retail: self.retail_mode.then_some(self.retail),
+            session: self.session.clone(),
+        })
+    }
+
+    /// 切换 RPN 模式：进入时刚计算的结果压入栈成为 X，并关闭零售模式；
+    /// 退出时 X 作为上一个结果，可以接着输入运算符
+    fn toggle_rpn_mode(&mut self) -> Vec<CalculatorEvent> {
+        self.rpn_mode = !self.rpn_mode;
+        if self.rpn_mode {
+            self.retail_mode = false;
+            if let (true, Some(result)) = (self.state.just_calculated, &self.state.last_result) {
+                self.stack.levels.push(result.clone());
+            }
+            if self.state.just_calculated {
+                self.rpn_idle();
+            }
+        }
+
+        vec![CalculatorEvent::RpnModeToggled(self.rpn_mode)]
+    }
+
+    /// 是否处于 RPN 模式
+    pub fn rpn_mode(&self) -> bool {
+        self.rpn_mode
+    }
+
+    /// RPN 栈中 X 以上各层的显示文本，Y 在前；正在输入时输入的数是 X，栈顶是 Y
+    pub fn stack_levels(&self) -> Vec<String> {
+        let levels = &self.stack.levels;
+        let above = if self.state.just_calculated {
+            &levels[..levels.len().saturating_sub(1)]
+        } else {
+            &levels[..]
+        };
+        above
+            .iter()
+            .rev()
+            .map(|value| self.format_value(value))
+            .collect()
+    }
+
+    /// 栈中的值和正在输入的数一共有几个
+    fn rpn_depth(&self) -> usize {
+        self.stack.levels.len() + usize::from(!self.state.just_calculated)
+    }
+
+    /// 结束输入，显示栈顶的 X
+    fn rpn_idle(&mut self) {
+        self.state.last_result = self.stack.levels.last().cloned();
+        self.state.just_calculated = true;
+        self.state.breakdown = None;
+        self.state.repeat = None;
+    }
+
+    /// 把正在输入的数求值后压入栈，不在输入时不变；无法求值时返回报告错误的事件
+    fn rpn_commit(&mut self) -> Option<CalculatorEvent> {
+        if self.state.just_calculated {
+            return None;
+        }
+        // 求值时按区域格式解析输入，事件中的表达式为规范格式
+        let entry = self.state.expression.replace('×', "*").replace('÷', "/");
+        match crate::logic::evaluate_in(&entry, &mut self.session) {
+            Ok(value) => {
+                self.stack.levels.push(value);
+                self.rpn_idle();
+                None
+            }
+            Err(error) => Some(CalculatorEvent::ResultCalculated {
+                expression: self.session.locale.delocalize(&entry),
+                result: Err(error),
+            }),
+        }
+    }
+
+    /// ENTER：把正在输入的数压入栈，不在输入时复制 X
+    fn rpn_enter(&mut self) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated {
+            let Some(x) = self.stack.levels.last().cloned() else {
+                return Vec::new();
+            };
+            self.stack.levels.push(x);
+        } else if let Some(event) = self.rpn_commit() {
+            return vec![CalculatorEvent::EqualsPressed, event];
+        }
+
+        vec![
+            CalculatorEvent::EqualsPressed,
+            CalculatorEvent::StackChanged,
+        ]
+    }
+
+    /// 用栈顶的 arity 个值（Y 在 X 之前）计算，成功时替换为结果并记入历史，失败时栈不变
+    fn rpn_apply(
+        &mut self,
+        arity: usize,
+        expression: impl FnOnce(&[Value]) -> String,
+        operation: impl FnOnce(&[Value], &mut Session) -> Result<Value, CalcError>,
+    ) -> Vec<CalculatorEvent> {
+        let operands = self.stack.levels.split_off(self.stack.levels.len() - arity);
+        let expression = expression(&operands);
+        let seed = self.session.rng.state();
+        let outcome = operation(&operands, &mut self.session);
+        let changed = outcome.is_ok();
+        match &outcome {
+            Ok(result) => {
+                self.stack.last_x = operands.last().cloned();
+                self.stack.levels.push(result.clone());
+                self.rpn_idle();
+                self.push_history(expression.clone(), result.clone(), seed);
+            }
+            Err(_) => self.stack.levels.extend(operands),
+        }
+
+        let mut events = vec![CalculatorEvent::ResultCalculated {
+            expression,
+            result: outcome,
+        }];
+        if changed {
+            events.push(CalculatorEvent::StackChanged);
+        }
+        events
+    }
+
+    /// RPN 运算键：Y 和 X 运算，结果成为 X；% 把 X 换成 Y 的 X%，Y 保留
+    fn rpn_operator(&mut self, operator: char) -> Vec<CalculatorEvent> {
+        let operator = match operator {
+            '×' => '*',
+            '÷' => '/',
+            _ => operator,
+        };
+        if !matches!(operator, '+' | '-' | '*' | '/' | '%') || self.rpn_depth() < 2 {
+            return Vec::new();
+        }
+        let mut events = vec![if operator == '%' {
+            CalculatorEvent::PercentagePressed
+        } else {
+            CalculatorEvent::OperatorPressed(operator)
+        }];
+        if let Some(event) = self.rpn_commit() {
+            events.push(event);
+            return events;
+        }
+
+        if operator == '%' {
+            let y = self.stack.levels[self.stack.levels.len() - 2].clone();
+            events.extend(self.rpn_apply(
+                1,
+                |operands| format!("{}*{}/100", rpn_operand(&y), rpn_operand(&operands[0])),
+                |operands, _| {
+                    crate::logic::apply_binary('*', y.clone(), operands[0].clone()).and_then(
+                        |product| {
+                            crate::logic::apply_binary(
+                                '/',
+                                product,
+                                Value::Integer(BigInt::from(100)),
+                            )
+                        },
+                    )
+                },
+            ));
+        } else {
+            events.extend(self.rpn_apply(
+                2,
+                |operands| {
+                    format!(
+                        "{}{}{}",
+                        rpn_operand(&operands[0]),
+                        operator,
+                        rpn_operand(&operands[1])
+                    )
+                },
+                |operands, _| {
+                    crate::logic::apply_binary(operator, operands[0].clone(), operands[1].clone())
+                },
+            ));
+        }
+        events
+    }
+
+    /// RPN 函数键：以 X 为参数调用函数，结果成为 X
+    fn rpn_function(&mut self, name: &str) -> Vec<CalculatorEvent> {
+        if self.rpn_depth() < 1 {
+            return Vec::new();
+        }
+        let mut events = vec![CalculatorEvent::FunctionPressed(name.to_string())];
+        if let Some(event) = self.rpn_commit() {
+            events.push(event);
+            return events;
+        }
+        events.extend(self.rpn_apply(
+            1,
+            |operands| format!("{}({})", name, value_expression(&operands[0])),
+            |operands, session| crate::logic::apply_function(name, operands, session),
+        ));
+        events
+    }
+
+    /// 先压入正在输入的数，栈中至少有 depth 个值时执行栈操作
+    fn rpn_stack_operation(
+        &mut self,
+        depth: usize,
+        operation: impl FnOnce(&mut Vec<Value>),
+    ) -> Vec<CalculatorEvent> {
+        if self.rpn_depth() < depth {
+            return Vec::new();
+        }
+        if let Some(event) = self.rpn_commit() {
+            return vec![event];
+        }
+        operation(&mut self.stack.levels);
+        self.rpn_idle();
+
+        vec![CalculatorEvent::StackChanged]
+    }
+
+    /// LASTx：把上一次运算前的 X 压入栈
+    fn rpn_last_x(&mut self) -> Vec<CalculatorEvent> {
+        let Some(last_x) = self.stack.last_x.clone() else {
+            return Vec::new();
+        };
+        self.rpn_stack_operation(0, |levels| levels.push(last_x))
+    }
+
+    /// RPN 模式的 ← 键：正在输入时删除最后一个字符，删完时结束输入；不在输入时丢弃 X
+    fn rpn_delete(&mut self) -> Vec<CalculatorEvent> {
+        if self.state.just_calculated {
+            let mut events = vec![CalculatorEvent::DeletePressed];
+            events.extend(self.rpn_stack_operation(1, |levels| {
+                levels.pop();
+            }));
+            return if events.len() > 1 { events } else { Vec::new() };
+        }
+        if self.state.expression.chars().count() > 1 {
+            return self.delete();
+        }
+        self.rpn_idle();
+
+        vec![CalculatorEvent::DeletePressed]
+    }
+
+    /// RPN 模式的 AC 键：清空输入和栈
+    fn rpn_clear(&mut self) -> Vec<CalculatorEvent> {
+        let mut events = self.clear();
+        self.stack.levels.clear();
+        events.push(CalculatorEvent::StackChanged);
+        events
+    }
+
+    /// 固定或取消固定一条历史记录
+    fn toggle_pin(&mut self, index: usize) -> Vec<CalculatorEvent> {
+        let Some(entry) = self.history.get_mut(index) else {
+            return Vec::new();
+        };
+        entry.pinned = !entry.pinned;
+
+        vec![CalculatorEvent::PinToggled {
+            index,
+            pinned: entry.pinned,
+        }]
+    }
+
+    /// 转换为会话文件的内容
+    pub fn to_saved(&self) -> SavedSession {
+        SavedSession {
+            version: store::VERSION,
+            settings: SavedSettings::from(&self.settings_snapshot()),
+            state: SavedState {
+                expression: self.state.expression.clone(),
+                last_result: self.state.last_result.as_ref().map(saved_value),
+                just_calculated: self.state.just_calculated,
+                breakdown: self
+                    .state
+                    .breakdown
+                    .as_ref()
+                    .map(|breakdown| SavedBreakdown {
+                        lines: breakdown.lines.clone(),
+                        cents: breakdown.result.0,
+                    }),
+            },
+            history: self
+                .history
+                .iter()
+                .map(|entry| SavedEntry {
+                    expression: entry.expression.clone(),
+                    result: saved_value(&entry.result),
+                    seed: entry.seed,
+                    timestamp: entry.timestamp,
+                    display: entry.display.clone(),
+                    settings: SavedSettings::from(&entry.settings),
+                    pinned: entry.pinned,
+                })
+                .collect(),
+            dataset: self.dataset.clone(),
+            stack: SavedStack {
+                levels: self.stack.levels.iter().map(saved_value).collect(),
+                last_x: self.stack.last_x.as_ref().map(saved_value),
+            },
+            memory: SavedMemory {
+                m: self.memory.m.as_ref().map(saved_value),
+                registers: self
+                    .memory
+                    .registers
+                    .iter()
+                    .map(|register| register.as_ref().map(saved_value))
+                    .collect(),
+            },
+        }
+    }
+
+    /// 从会话文件的内容恢复历史、当前输入、设置、数据集、记忆寄存器和 RPN 栈；
+    /// 返回无法还原的计算结果的错误信息，这些结果还原为 0
+    pub fn restore(&mut self, saved: SavedSession) -> Vec<String> {
+        let settings = SettingsSnapshot::from(&saved.settings);
+        self.number_format = settings.number_format;
+        self.session.locale = settings.locale;
+        self.retail_mode = settings.retail_mode;
+        self.retail = settings.retail;
+        self.rpn_mode = settings.rpn_mode;
+        let mut failures = Vec::new();
+        let mut restore = |saved: &SavedValue| {
+            restored_value(saved, &mut self.session).unwrap_or_else(|error| {
+                failures.push(error);
+                Value::Number(0.0)
+            })
+        };
+        self.state = CalculatorState {
+            is_empty: saved.state.expression.is_empty(),
+            expression: saved.state.expression,
+            last_result: saved.state.last_result.as_ref().map(&mut restore),
+            just_calculated: saved.state.just_calculated,
+            comma_formatting: settings.comma_formatting,
+            breakdown: saved.state.breakdown.map(|breakdown| Breakdown {
+                lines: breakdown.lines,
+                result: Cents(breakdown.cents),
+            }),
+            // 重复的运算不随会话保存
+            repeat: None,
+        };
+        self.history = saved
+            .history
+            .into_iter()
+            .map(|entry| HistoryEntry {
+                result: restore(&entry.result),
+                settings: SettingsSnapshot::from(&entry.settings),
+                expression: entry.expression,
+                seed: entry.seed,
+                timestamp: entry.timestamp,
+                display: entry.display,
+                pinned: entry.pinned,
+            })
+            .collect();
+        self.dataset = saved.dataset;
+        self.stack = Stack {
+            levels: saved.stack.levels.iter().map(&mut restore).collect(),
+            last_x: saved.stack.last_x.as_ref().map(&mut restore),
+        };
+        self.memory = Memory {
+            m: saved.memory.m.as_ref().map(&mut restore),
+            registers: std::array::from_fn(|register| {
+                saved
+                    .memory
+                    .registers
+                    .get(register)
+                    .and_then(Option::as_ref)
+                    .map(&mut restore)
+            }),
+        };
+        self.display = self.display_text();
+        failures
+    }
+
+    /// 获取统计数据集
+    pub fn dataset(&self) -> &[f64] {
+        &self.dataset
+    }
+
+    /// 替换统计数据集
+    fn set_dataset(&mut self, dataset: Vec<f64>) -> Vec<CalculatorEvent> {
+        if self.dataset == dataset {
+            return Vec::new();
+        }
+        self.dataset = dataset;
+
+        vec![CalculatorEvent::DatasetChanged]
+    }
+
+    /// 设置最近一次回归拟合，之后表达式中的 predict(x) 使用该拟合
+    fn set_last_fit(&mut self, fit: Fit) -> Vec<CalculatorEvent> {
+        if self.session.last_fit.as_ref() == Some(&fit) {
+            return Vec::new();
+        }
+        self.session.last_fit = Some(fit);
+
+        vec![CalculatorEvent::FitChanged]
+    }
+
+    // 从字符串设置表达式（用于测试或恢复状态）
+    // pub fn set_expression(&mut self, expr: &str) -> Vec<CalculatorEvent> {
+    //     self.state.expression = expr.to_string();
+    //     self.state.just_calculated = false;
+    //     self.state.is_empty = expr.is_empty();
+
+    //     cx.emit(CalculatorEvent::ExpressionChanged);
+    //     cx.notify();
+    // }
+}
+
+impl Default for CalculatorCore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::locale::LOCALES;
+
+    /// 按字符依次按键：数字、. + - × ÷ * / % ( )，= 计算，< 退格，C 清除，返回所有事件
+    fn press(core: &mut CalculatorCore, keys: &str) -> Vec<CalculatorEvent> {
+        keys.chars()
+            .flat_map(|key| {
+                core.apply(match key {
+                    '0'..='9' => Command::Number(key),
+                    '.' => Command::Decimal,
+                    '(' | ')' => Command::Parenthesis(key),
+                    '=' => Command::Equals,
+                    '<' => Command::Delete,
+                    'C' => Command::Clear,
+                    _ => Command::Operator(key),
+                })
+            })
+            .collect()
+    }
+
+    /// 表达式从 previous 变为 expression 的事件
+    fn changed(previous: &str, expression: &str) -> CalculatorEvent {
+        CalculatorEvent::ExpressionChanged {
+            previous: previous.to_string(),
+            expression: expression.to_string(),
+        }
+    }
+
+    /// 从新的计算器开始按键，返回显示内容
+    fn display_after(keys: &str) -> String {
+        let mut core = CalculatorCore::new();
+        press(&mut core, keys);
+        core.display_text()
+    }
+
+    #[test]
+    fn test_number_keys() {
+        assert_eq!(display_after(""), "0");
+        assert_eq!(display_after("007"), "7");
+        assert_eq!(display_after("1203"), "1203");
+
+        let mut core = CalculatorCore::new();
+        assert_eq!(
+            press(&mut core, "4"),
+            vec![CalculatorEvent::NumberPressed('4'), changed("0", "4")]
+        );
+        // 非数字字符不产生事件
+        assert!(core.apply(Command::Number('x')).is_empty());
+        assert_eq!(core.state.expression, "4");
+    }
+
+    #[test]
+    fn test_operator_keys() {
+        assert_eq!(display_after("5+3"), "5+3");
+        assert_eq!(display_after("6*7"), "6×7");
+        // 连续按运算符时替换上一个运算符
+        assert_eq!(display_after("5+×3"), "5×3");
+        assert_eq!(display_after("5+×3="), "15");
+        assert_eq!(display_after("8÷-÷2="), "4");
+
+        let mut core = CalculatorCore::new();
+        assert!(core.apply(Command::Operator('^')).is_empty());
+        assert_eq!(core.state.expression, "0");
+    }
+
+    #[test]
+    fn test_decimal_keys() {
+        assert_eq!(display_after("."), "0.");
+        assert_eq!(display_after("1.2.3"), "1.23");
+        assert_eq!(display_after("1.5+.2"), "1.5+.2");
+        assert_eq!(display_after("1.5+.2="), "1.7");
+
+        // 一个数字里只能有一个小数点，重复按键不产生事件
+        let mut core = CalculatorCore::new();
+        press(&mut core, "3.");
+        assert!(press(&mut core, ".").is_empty());
+
+        // 计算后按小数点开始新的数字
+        assert_eq!(display_after("2+3=.5"), "0.5");
+    }
+
+    #[test]
+    fn test_parenthesis_keys() {
+        // 数字或右括号后面的左括号前自动补乘号
+        assert_eq!(display_after("2(3+4)"), "2×(3+4)");
+        assert_eq!(display_after("2(3+4)="), "14");
+        assert_eq!(display_after("3(1+1)(2+2)="), "24");
+        // 缺少的右括号在计算时补全
+        assert_eq!(display_after("2(3+4="), "14");
+
+        // 没有对应左括号的右括号被忽略
+        let mut core = CalculatorCore::new();
+        press(&mut core, "5");
+        assert!(press(&mut core, ")").is_empty());
+        assert_eq!(core.state.expression, "5");
+    }
+
+    #[test]
+    fn test_percentage_keys() {
+        assert_eq!(display_after("50%="), "0.5");
+        assert_eq!(display_after("200×10%="), "20");
+        assert_eq!(display_after("8=%"), "8%");
+    }
+
+    #[test]
+    fn test_equals_key() {
+        let mut core = CalculatorCore::new();
+        // 没有输入时按等号不计算
+        assert!(press(&mut core, "=").is_empty());
+        assert!(core.history().is_empty());
+
+        assert_eq!(
+            press(&mut core, "1+2="),
+            vec![
+                CalculatorEvent::NumberPressed('1'),
+                changed("0", "1"),
+                CalculatorEvent::OperatorPressed('+'),
+                changed("1", "1+"),
+                CalculatorEvent::NumberPressed('2'),
+                changed("1+", "1+2"),
+                CalculatorEvent::EqualsPressed,
+                CalculatorEvent::ResultCalculated {
+                    expression: "1+2".to_string(),
+                    result: Ok(Value::Integer(3.into())),
+                },
+            ]
+        );
+        assert_eq!(core.display_text(), "3");
+        assert_eq!(core.history()[0].expression, "1+2");
+        assert_eq!(core.history()[0].result, Value::Integer(3.into()));
+    }
+
+    #[test]
+    fn test_error_result() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::Paste("nope(1)".to_string()));
+        let events = core.apply(Command::Equals);
+        assert_eq!(
+            events.last().and_then(CalculatorEvent::result),
+            Some(Err(CalcError::UnknownFunction("nope".to_string())))
+        );
+        // 计算失败时显示 0
+        assert_eq!(core.display_text(), "0");
+    }
+
+    #[test]
+    fn test_continue_after_result() {
+        // 计算后按运算符以结果继续计算，按数字开始新的表达式
+        assert_eq!(display_after("2+3=×4="), "20");
+        assert_eq!(display_after("2+3=7"), "7");
+        assert_eq!(display_after("2+3=(1)"), "0×(1)");
+        assert_eq!(display_after("1÷4=+1="), "1.25");
+
+        // 结果为质因数分解时以乘积继续
+        let mut core = CalculatorCore::new();
+        core.apply(Command::Function("factor".to_string()));
+        press(&mut core, "12=");
+        assert_eq!(core.display_text(), "2² × 3");
+        press(&mut core, "+1=");
+        assert_eq!(core.display_text(), "13");
+    }
+
+    #[test]
+    fn test_function_keys() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::Function("cosh".to_string()));
+        assert_eq!(core.state.expression, "cosh(");
+        press(&mut core, "0=");
+        assert_eq!(core.display_text(), "1");
+
+        // 数字后面的函数前自动补乘号
+        press(&mut core, "C3");
+        core.apply(Command::Function("cosh".to_string()));
+        assert_eq!(core.state.expression, "3*cosh(");
+        press(&mut core, "0=");
+        assert_eq!(core.display_text(), "3");
+    }
+
+    #[test]
+    fn test_delete_key() {
+        assert_eq!(display_after("123<"), "12");
+        assert_eq!(display_after("1<"), "0");
+        assert_eq!(display_after("1<<<"), "0");
+        assert_eq!(display_after("5+<"), "5");
+
+        // 函数名连同左括号整体删除
+        let mut core = CalculatorCore::new();
+        press(&mut core, "5×");
+        core.apply(Command::Function("sin".to_string()));
+        press(&mut core, "<");
+        assert_eq!(core.state.expression, "5*");
+
+        // 计算后退格清除全部内容
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2+2=");
+        let previous = core.state.clone();
+        assert_eq!(
+            press(&mut core, "<"),
+            vec![
+                CalculatorEvent::ClearPressed,
+                changed("2+2", "0"),
+                CalculatorEvent::StateReset { previous },
+            ]
+        );
+        assert_eq!(core.display_text(), "0");
+        // 表达式没有变化时只报告按键
+        assert_eq!(press(&mut core, "<"), vec![CalculatorEvent::DeletePressed]);
+    }
+
+    #[test]
+    fn test_clear_key() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::SetNumberFormat(NumberFormat::Fix(2)));
+        press(&mut core, "12+3C");
+        assert_eq!(core.display_text(), "0");
+        // 显示格式不随 AC 重置
+        press(&mut core, "1÷3=");
+        assert_eq!(core.display_text(), "0.33");
+        assert_eq!(core.history().len(), 1);
+    }
+
+    #[test]
+    fn test_comma_formatting_key() {
+        let mut core = CalculatorCore::new();
+        press(&mut core, "1234567");
+        assert_eq!(
+            core.apply(Command::ToggleCommaFormatting),
+            vec![CalculatorEvent::CommaFormattingToggled(true)]
+        );
+        assert_eq!(core.display_text(), "1,234,567");
+        press(&mut core, "×2=");
+        assert_eq!(core.display_text(), "2,469,134");
+    }
+
+    #[test]
+    fn test_retail_keys() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::ToggleRetailMode);
+        assert!(core.retail_mode());
+
+        // 先存税率，再给金额加税
+        press(&mut core, "8");
+        core.apply(Command::StoreTaxRate);
+        press(&mut core, "100");
+        core.apply(Command::ApplyTax(true));
+        assert_eq!(core.display_text(), "108.00");
+        assert_eq!(core.breakdown_lines().len(), 2);
+
+        // 以结果继续：加小费再分摊
+        core.apply(Command::RetailStep("tip".to_string()));
+        press(&mut core, "10");
+        core.apply(Command::RetailStep("split".to_string()));
+        press(&mut core, "2=");
+        assert_eq!(core.display_text(), "59.40");
+
+        // 零售步骤整体删除
+        press(&mut core, "C5");
+        core.apply(Command::RetailStep("tip".to_string()));
+        press(&mut core, "<");
+        assert_eq!(core.state.expression, "5");
+
+        core.apply(Command::ToggleRetailMode);
+        assert_eq!(display_after("200-10%="), "199.9");
+    }
+
+    #[test]
+    fn test_locale_keys() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::SetLocale(LOCALES[1]));
+        press(&mut core, "1.5+1");
+        assert_eq!(core.state.expression, "1,5+1");
+        press(&mut core, "=");
+        assert_eq!(core.display_text(), "2,5");
+        // 历史记录保存规范格式
+        assert_eq!(core.history()[0].expression, "1.5+1");
+
+        // 切换区域时正在编辑的表达式同时转换
+        press(&mut core, "C2.5");
+        core.apply(Command::SetLocale(LOCALES[0]));
+        assert_eq!(core.state.expression, "2.5");
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::SetLocale(LOCALES[1]));
+        assert_eq!(
+            core.apply(Command::Paste(" 1.234,5 ".to_string())),
+            vec![
+                CalculatorEvent::Pasted("1.234,5".to_string()),
+                changed("0", "1.234,5"),
+            ]
+        );
+        press(&mut core, "×2=");
+        assert_eq!(core.display_text(), "2469");
+        assert!(core.apply(Command::Paste("\n".to_string())).is_empty());
+    }
+
+    #[test]
+    fn test_format_with_commas() {
+        let mut calculator = CalculatorCore::new();
+
+        // 测试没有逗号格式化的情况
+        assert_eq!(calculator.format_with_commas("1234567"), "1234567");
+
+        // 启用逗号格式化
+        calculator.state.comma_formatting = true;
+
+        // 测试正数
+        assert_eq!(calculator.format_with_commas("123"), "123");
+        assert_eq!(calculator.format_with_commas("1234"), "1,234");
+        assert_eq!(calculator.format_with_commas("1234567"), "1,234,567");
+        assert_eq!(calculator.format_with_commas("1234567890"), "1,234,567,890");
+
+        // 测试负数
+        assert_eq!(calculator.format_with_commas("-123"), "-123");
+        assert_eq!(calculator.format_with_commas("-1234"), "-1,234");
+        assert_eq!(calculator.format_with_commas("-1234567"), "-1,234,567");
+
+        // 测试小数
+        assert_eq!(calculator.format_with_commas("1234.56"), "1,234.56");
+        assert_eq!(calculator.format_with_commas("1234567.89"), "1,234,567.89");
+        assert_eq!(calculator.format_with_commas("-1234.56"), "-1,234.56");
+
+        // 测试边界情况
+        assert_eq!(calculator.format_with_commas("0"), "0");
+        assert_eq!(calculator.format_with_commas("0.0"), "0.0");
+        assert_eq!(calculator.format_with_commas(".5"), ".5");
+    }
+
+    #[test]
+    fn test_format_expression_with_commas() {
+        let mut calculator = CalculatorCore::new();
+
+        // 测试没有逗号格式化的情况
+        assert_eq!(
+            calculator.format_expression_with_commas("123+456"),
+            "123+456"
+        );
+
+        // 启用逗号格式化
+        calculator.state.comma_formatting = true;
+
+        // 测试简单表达式
+        assert_eq!(
+            calculator.format_expression_with_commas("123+456"),
+            "123+456"
+        );
+        assert_eq!(
+            calculator.format_expression_with_commas("1234+5678"),
+            "1,234+5,678"
+        );
+
+        // 测试复杂表达式
+        assert_eq!(
+            calculator.format_expression_with_commas("1234.56+7890.12-3456.78"),
+            "1,234.56+7,890.12-3,456.78"
+        );
+
+        // 测试包含运算符和括号的表达式
+        assert_eq!(
+            calculator.format_expression_with_commas("(1234+5678)*9012"),
+            "(1,234+5,678)*9,012"
+        );
+
+        // 测试负数表达式
+        assert_eq!(
+            calculator.format_expression_with_commas("-1234+-5678"),
+            "-1,234+-5,678"
+        );
+
+        // 测试边界情况
+        assert_eq!(calculator.format_expression_with_commas(""), "");
+        assert_eq!(calculator.format_expression_with_commas("+"), "+");
+        assert_eq!(calculator.format_expression_with_commas("123"), "123");
+    }
+
+    #[test]
+    fn test_display_exact_results() {
+        let mut calculator = CalculatorCore::new();
+        calculator.state.just_calculated = true;
+
+        // 质因数分解以乘积形式显示
+        calculator.state.last_result = crate::logic::evaluate("factor(360)").ok();
+        assert_eq!(calculator.display_text(), "2³ × 3² × 5");
+
+        // 大整数完整显示，并支持逗号格式化
+        calculator.state.comma_formatting = true;
+        calculator.state.last_result = crate::logic::evaluate("123456789012345678901").ok();
+        assert_eq!(calculator.display_text(), "123,456,789,012,345,678,901");
+    }
+
+    #[test]
+    fn test_display_retail_breakdown() {
+        let mut calculator = CalculatorCore::new();
+        calculator.state.just_calculated = true;
+        calculator.state.last_result = Some(Value::Number(1710.0));
+        calculator.state.breakdown =
+            retail::evaluate_retail("2000-10%-5%", &retail::Settings::default())
+                .and_then(Result::ok);
+
+        // 零售金额固定两位小数，明细只在显示结果时给出
+        calculator.state.comma_formatting = true;
+        assert_eq!(calculator.display_text(), "1,710.00");
+        assert_eq!(calculator.breakdown_lines().len(), 3);
+        calculator.state.just_calculated = false;
+        assert!(calculator.breakdown_lines().is_empty());
+    }
+
+    #[test]
+    fn test_display_number_format() {
+        let mut calculator = CalculatorCore::new();
+        calculator.state.just_calculated = true;
+        calculator.state.last_result = crate::logic::evaluate("0.1+0.2").ok();
+        assert_eq!(calculator.display_text(), "0.30000000000000004");
+        calculator.number_format = NumberFormat::Fix(2);
+        assert_eq!(calculator.display_text(), "0.30");
+
+        // 超出显示宽度时自动改用科学计数法
+        calculator.state.last_result = Some(Value::Number(1e21));
+        calculator.number_format = NumberFormat::Auto;
+        assert_eq!(calculator.display_text(), "1e21");
+        calculator.number_format = NumberFormat::Fix(2);
+        assert_eq!(calculator.display_text(), "1.00e21");
+
+        calculator.state.comma_formatting = true;
+        calculator.state.last_result = Some(Value::Number(1234567.891));
+        assert_eq!(calculator.display_text(), "1,234,567.89");
+        calculator.number_format = NumberFormat::Eng(2);
+        assert_eq!(calculator.display_text(), "1.23e6");
+
+        // 精确整数只在 AUTO 格式下完整显示
+        calculator.state.last_result = crate::logic::evaluate("123456789").ok();
+        assert_eq!(calculator.display_text(), "123.46e6");
+    }
+
+    #[test]
+    fn test_display_cache() {
+        // 显示内容在命令执行后计算一次，渲染时直接读取
+        let mut core = CalculatorCore::new();
+        assert_eq!(core.display(), "0");
+        press(&mut core, "12+3");
+        assert_eq!(core.display(), "12+3");
+        press(&mut core, "=");
+        assert_eq!(core.display(), "15");
+        core.apply(Command::SetNumberFormat(NumberFormat::Fix(2)));
+        assert_eq!(core.display(), "15.00");
+
+        let mut restored = CalculatorCore::new();
+        restored.restore(core.to_saved());
+        assert_eq!(restored.display(), "15.00");
+    }
+
+    #[test]
+    fn test_display_locale() {
+        let mut calculator = CalculatorCore::new();
+        calculator.state.comma_formatting = true;
+        calculator.state.just_calculated = true;
+        calculator.state.last_result = Some(Value::Number(1234567.891));
+        calculator.session.locale = LOCALES[1];
+        assert_eq!(calculator.display_text(), "1.234.567,891");
+        calculator.session.locale = LOCALES[2];
+        assert_eq!(calculator.display_text(), "1'234'567.891");
+        calculator.session.locale = LOCALES[3];
+        assert_eq!(calculator.display_text(), "12,34,567.891");
+
+        // 输入中的表达式按区域格式分组，求值时还原为规范格式
+        calculator.session.locale = LOCALES[1];
+        calculator.state.just_calculated = false;
+        calculator.state.expression = "1234,5*2".to_string();
+        assert_eq!(calculator.display_text(), "1.234,5×2");
+        let value =
+            crate::logic::evaluate_in(&calculator.state.expression, &mut calculator.session);
+        assert_eq!(value.map(|v| v.to_string()), Ok("2469".to_string()));
+    }
+
+    #[test]
+    fn test_history_entry() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::SetNumberFormat(NumberFormat::Fix(2)));
+        core.apply(Command::SetLocale(LOCALES[1]));
+        press(&mut core, "1.5×3=");
+
+        let entry = &core.history()[0];
+        assert_eq!(entry.expression, "1.5*3");
+        assert_eq!(entry.expression_text(), "1,5×3");
+        assert_eq!(entry.display, "4,50");
+        assert_eq!(entry.settings.number_format, NumberFormat::Fix(2));
+        assert_eq!(entry.settings.locale, LOCALES[1]);
+        assert!(entry.timestamp > 0);
+        assert!(!entry.pinned);
+    }
+
+    #[test]
+    fn test_history_recall() {
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2+3=");
+        press(&mut core, "10-4=");
+
+        // 刚计算完时取回的内容替换当前输入
+        assert_eq!(
+            core.apply(Command::RecallExpression(0)),
+            vec![CalculatorEvent::HistoryRecalled(0), changed("10-4", "2+3"),]
+        );
+        // 接在运算符后面时，数字直接追加，表达式加括号
+        press(&mut core, "×");
+        core.apply(Command::RecallResult(1));
+        assert_eq!(core.state.expression, "2+3*6");
+        press(&mut core, "-");
+        core.apply(Command::RecallExpression(1));
+        assert_eq!(core.display_text(), "2+3×6-(10-4)");
+        press(&mut core, "=");
+        assert_eq!(core.display_text(), "14");
+
+        // 不存在的记录不产生事件
+        assert!(core.apply(Command::RecallResult(99)).is_empty());
+    }
+
+    #[test]
+    fn test_history_replay() {
+        // 取回含随机函数的表达式再按 =，重放出记录中的随机值
+        let mut core = CalculatorCore::new();
+        for _ in 0..2 {
+            core.apply(Command::Paste("rand()+randint(1, 100)".to_string()));
+            press(&mut core, "=");
+        }
+        let first = core.history()[0].clone();
+        assert_ne!(core.history()[1].result, first.result);
+
+        core.apply(Command::RecallExpression(0));
+        press(&mut core, "=");
+        let replayed = &core.history()[2];
+        assert_eq!(replayed.result, first.result);
+        assert_eq!(replayed.seed, first.seed);
+    }
+
+    #[test]
+    fn test_history_search_and_pin() {
+        let mut core = CalculatorCore::new();
+        press(&mut core, "12+1=");
+        press(&mut core, "7×7=");
+        press(&mut core, "12÷4=");
+
+        assert_eq!(core.search_history(""), vec![2, 1, 0]);
+        assert_eq!(core.search_history("12"), vec![2, 0]);
+        assert_eq!(core.search_history("÷"), vec![2]);
+        assert_eq!(core.search_history("49"), vec![1]);
+        assert!(core.search_history("xyz").is_empty());
+
+        // 固定的记录排在最前面
+        assert_eq!(
+            core.apply(Command::TogglePin(0)),
+            vec![CalculatorEvent::PinToggled {
+                index: 0,
+                pinned: true
+            }]
+        );
+        assert_eq!(core.search_history(""), vec![0, 2, 1]);
+        core.apply(Command::TogglePin(0));
+        assert_eq!(core.search_history(""), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::SetLocale(LOCALES[1]));
+        core.apply(Command::SetNumberFormat(NumberFormat::Fix(2)));
+        core.apply(Command::SetDataset(vec![1.0, 2.0]));
+        core.apply(Command::Function("factor".to_string()));
+        press(&mut core, "360=");
+        core.apply(Command::TogglePin(0));
+        press(&mut core, "1.5×2=");
+        press(&mut core, "7+");
+
+        let mut restored = CalculatorCore::new();
+        restored.restore(core.to_saved());
+        assert_eq!(restored.to_saved(), core.to_saved());
+        assert_eq!(restored.display_text(), "7+");
+        assert_eq!(restored.locale(), LOCALES[1]);
+        assert_eq!(restored.dataset(), &[1.0, 2.0]);
+        // 质因数分解还原为质因数分解，浮点数精确还原
+        assert_eq!(restored.history(), core.history());
+        assert_eq!(restored.search_history(""), vec![0, 1]);
+
+        // 继续计算时沿用恢复的输入
+        press(&mut restored, "1=");
+        assert_eq!(restored.display_text(), "8,00");
+    }
+
+    #[test]
+    fn test_restore_currency_and_non_finite() {
+        let path = std::env::temp_dir().join(format!("mycal-restore-{}.csv", std::process::id()));
+        std::fs::write(&path, "USD,1\nEUR,0.5\n").unwrap();
+        let rates = Rates::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut core = CalculatorCore::new();
+        core.session.rates = Some(rates.clone());
+        for expression in ["100 USD to EUR", "1.5^2000", "1.5^2000-1.5^2000"] {
+            core.state.expression = expression.to_string();
+            core.apply(Command::Equals);
+        }
+        core.apply(Command::MemoryAdd(true));
+
+        // 还原时不需要汇率，换算过的货币、inf 和 NaN 原样还原
+        let mut restored = CalculatorCore::new();
+        assert_eq!(restored.restore(core.to_saved()), Vec::<String>::new());
+        assert_eq!(restored.to_saved(), core.to_saved());
+        assert_eq!(restored.history()[0].result.to_string(), "50 EUR");
+        assert_eq!(restored.history()[1].result, Value::Number(f64::INFINITY));
+        assert!(restored.history()[2].result.as_number().unwrap().is_nan());
+
+        // 旧版本保存的表达式文本在计算器的会话中重新求值，无法求值时报告错误
+        let mut saved = core.to_saved();
+        saved.history[0].result = SavedValue::Expression("100 USD to EUR".to_string());
+        let mut legacy = CalculatorCore::new();
+        legacy.session.rates = Some(rates);
+        assert_eq!(legacy.restore(saved.clone()), Vec::<String>::new());
+        assert_eq!(legacy.history()[0].result.to_string(), "50 EUR");
+        let mut without_rates = CalculatorCore::new();
+        assert_eq!(
+            without_rates.restore(saved),
+            vec!["100 USD to EUR: No exchange rate for USD".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut core = CalculatorCore::new();
+        assert!(!core.can_undo());
+        assert_eq!(core.apply(Command::Undo), vec![]);
+
+        // 计算、清除和设置都可以撤销
+        press(&mut core, "12+3=");
+        core.apply(Command::ToggleCommaFormatting);
+        core.apply(Command::SetNumberFormat(NumberFormat::Fix(2)));
+        press(&mut core, "C");
+        assert_eq!(core.display_text(), "0");
+        assert_eq!(
+            core.apply(Command::Undo),
+            vec![CalculatorEvent::Undone, changed("0", "12+3")]
+        );
+        assert_eq!(core.display_text(), "15.00");
+        core.apply(Command::Undo);
+        assert_eq!(core.number_format(), NumberFormat::Auto);
+        core.apply(Command::Undo);
+        assert!(!core.state().comma_formatting);
+        core.apply(Command::Undo);
+        assert_eq!(core.display_text(), "12+3");
+        assert!(!core.state().just_calculated);
+        // 撤销计算不会删除历史记录
+        assert_eq!(core.history().len(), 1);
+
+        // 重做恢复撤销的修改，新的输入清空重做栈
+        assert_eq!(core.apply(Command::Redo), vec![CalculatorEvent::Redone]);
+        assert_eq!(core.display_text(), "15");
+        assert!(core.can_redo());
+        press(&mut core, "<");
+        assert_eq!(core.display_text(), "0");
+        assert!(!core.can_redo());
+        assert_eq!(core.apply(Command::Redo), vec![]);
+
+        // 没有变化的输入不记入撤销栈
+        press(&mut core, "C<<");
+        core.apply(Command::Undo);
+        assert_eq!(core.display_text(), "15");
+        while core.can_undo() {
+            core.apply(Command::Undo);
+        }
+        assert_eq!(core.display_text(), "0");
+    }
+
+    #[test]
+    fn test_undo_keeps_history() {
+        let mut core = CalculatorCore::new();
+        press(&mut core, "12+3=");
+        core.apply(Command::TogglePin(0));
+        core.apply(Command::SetDataset(vec![1.0, 2.0]));
+        press(&mut core, "2×2=");
+        let history = core.history().to_vec();
+
+        // 撤销和重做只改变输入，历史记录、固定状态和数据集保持不变，重做也不会重复记入历史
+        while core.can_undo() {
+            core.apply(Command::Undo);
+        }
+        assert_eq!(core.display_text(), "0");
+        assert_eq!(core.history(), history.as_slice());
+        assert_eq!(core.dataset(), &[1.0, 2.0]);
+        while core.can_redo() {
+            core.apply(Command::Redo);
+        }
+        assert_eq!(core.display_text(), "4");
+        assert_eq!(core.history(), history.as_slice());
+        assert!(core.history()[0].pinned);
+    }
+
+    #[test]
+    fn test_memory_keys() {
+        let mut core = CalculatorCore::new();
+        assert_eq!(core.apply(Command::MemoryRecall), vec![]);
+
+        // M+ 先完成计算，再累加结果
+        press(&mut core, "12×3");
+        let events = core.apply(Command::MemoryAdd(true));
+        assert_eq!(events[0], CalculatorEvent::EqualsPressed);
+        assert_eq!(events.last(), Some(&CalculatorEvent::MemoryChanged));
+        assert_eq!(core.display_text(), "36");
+        press(&mut core, "4.5");
+        core.apply(Command::MemoryAdd(true));
+        press(&mut core, "10");
+        core.apply(Command::MemoryAdd(false));
+        assert_eq!(core.memory().m, Some(Value::Number(30.5)));
+        assert_eq!(core.history().len(), 3);
+
+        // 整数之间的累加保持精确
+        core.apply(Command::MemoryClear);
+        press(&mut core, "123456789012345678901");
+        core.apply(Command::MemoryAdd(true));
+        core.apply(Command::MemoryAdd(true));
+        assert_eq!(
+            core.memory().m,
+            crate::logic::evaluate("246913578024691357802").ok()
+        );
+
+        // MR 接在运算符后面，替换刚计算的结果
+        press(&mut core, "C1+");
+        core.apply(Command::MemoryRecall);
+        assert_eq!(core.display_text(), "1+246913578024691357802");
+        press(&mut core, "=");
+        core.apply(Command::MemoryRecall);
+        assert_eq!(core.display_text(), "246913578024691357802");
+
+        // 出错时 M 不变，AC 不会清空 M
+        core.apply(Command::Paste("nope(1)".to_string()));
+        core.apply(Command::MemoryAdd(true));
+        press(&mut core, "C");
+        assert_eq!(
+            core.memory().m,
+            crate::logic::evaluate("246913578024691357802").ok()
+        );
+        assert_eq!(
+            core.apply(Command::MemoryClear),
+            vec![CalculatorEvent::MemoryChanged]
+        );
+        assert_eq!(core.apply(Command::MemoryClear), vec![]);
+    }
+
+    #[test]
+    fn test_memory_registers() {
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2+5");
+        core.apply(Command::StoreRegister(3));
+        core.apply(Command::Function("factor".to_string()));
+        press(&mut core, "360=");
+        core.apply(Command::StoreRegister(9));
+        assert_eq!(core.apply(Command::StoreRegister(10)), vec![]);
+        assert_eq!(core.memory().registers[3], Some(Value::Number(7.0)));
+        assert!(core.memory().registers[0].is_none());
+
+        press(&mut core, "C");
+        assert_eq!(
+            core.apply(Command::RecallRegister(3)),
+            vec![CalculatorEvent::MemoryRecalled(Some(3)), changed("0", "7")]
+        );
+        press(&mut core, "×");
+        core.apply(Command::RecallRegister(9));
+        assert_eq!(core.display_text(), "7×360");
+        assert_eq!(core.apply(Command::RecallRegister(0)), vec![]);
+
+        // 寄存器的修改可以撤销，并随会话保存
+        core.apply(Command::ClearRegisters);
+        assert!(core.memory().registers.iter().all(Option::is_none));
+        core.apply(Command::Undo);
+        assert_eq!(core.memory().registers[3], Some(Value::Number(7.0)));
+        let mut restored = CalculatorCore::new();
+        restored.restore(core.to_saved());
+        assert_eq!(restored.memory(), core.memory());
+    }
+
+    #[test]
+    fn test_last_operation() {
+        assert_eq!(last_operation("2+3"), Some(('+', "3".to_string())));
+        assert_eq!(last_operation("2+3*4"), Some(('*', "4".to_string())));
+        assert_eq!(last_operation("2*-3"), Some(('*', "-3".to_string())));
+        assert_eq!(last_operation("1e-5*2"), Some(('*', "2".to_string())));
+        assert_eq!(
+            last_operation("10-max(1,2+3)"),
+            Some(('-', "max(1,2+3)".to_string()))
+        );
+        assert_eq!(last_operation("200+10%"), Some(('+', "10%".to_string())));
+        assert_eq!(last_operation("-5"), None);
+        assert_eq!(last_operation("sin(30+1)"), None);
+        assert_eq!(last_operation("1e-5"), None);
+        // 日期字面量中的 - 不是减法
+        assert_eq!(last_operation("2026-10-18"), None);
+        assert_eq!(last_operation("2026-10-18+7"), Some(('+', "7".to_string())));
+        assert_eq!(
+            last_operation("2026-10-18-2026-01-01"),
+            Some(('-', "2026-01-01".to_string()))
+        );
+        assert_eq!(last_operation("x2026-10-18"), Some(('-', "18".to_string())));
+    }
+
+    #[test]
+    fn test_repeated_equals() {
+        // 连续按 = 重复上一次的运算
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2+3=");
+        assert_eq!(core.display_text(), "5");
+        press(&mut core, "=");
+        assert_eq!(core.display_text(), "8");
+        press(&mut core, "=");
+        assert_eq!(core.display_text(), "11");
+        assert_eq!(core.history()[2].expression, "8+3");
+
+        assert_eq!(display_after("10-2==="), "4");
+        assert_eq!(display_after("2+3×4=="), "56");
+        assert_eq!(display_after("200+10%=="), "200.2");
+        assert_eq!(display_after("1.5+1=="), "3.5");
+        assert_eq!(display_after("2026-10-18=="), display_after("2026-10-18="));
+        let mut core = CalculatorCore::new();
+        core.state.expression = "2026-10-18+7 days".to_string();
+        press(&mut core, "==");
+        assert_eq!(core.display_text(), "2026-11-01");
+
+        // 没有二元运算的表达式按 = 时照常重新计算
+        let mut core = CalculatorCore::new();
+        core.apply(Command::Function("cosh".to_string()));
+        press(&mut core, "0)==");
+        assert_eq!(core.display_text(), "1");
+        assert_eq!(core.history()[1].expression, "cosh(0)");
+    }
+
+    #[test]
+    fn test_constant_operand() {
+        // ×5= 之后输入新数再按 =，新数乘以 5
+        assert_eq!(display_after("2×5=7="), "35");
+        assert_eq!(display_after("2×5=7=="), "175");
+        assert_eq!(display_after("8÷2=6="), "3");
+        // 新数之后接运算符时按普通表达式计算，AC 清除重复的运算，M+ 不使用重复的运算
+        assert_eq!(display_after("2×5=7+1="), "8");
+        assert_eq!(display_after("2×5=C7="), "7");
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2×5=7");
+        core.apply(Command::MemoryAdd(true));
+        assert_eq!(core.memory().m, Some(Value::Number(7.0)));
+
+        // 出错后不重复
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2×5=");
+        core.apply(Command::Paste("nope(1)".to_string()));
+        press(&mut core, "=C");
+        assert_eq!(core.state().repeat, None);
+
+        // 其他区域格式下运算数保持区域格式
+        let mut core = CalculatorCore::new();
+        core.apply(Command::SetLocale(LOCALES[1]));
+        press(&mut core, "2×1.5=3=");
+        assert_eq!(core.display_text(), "4,5");
+    }
+
+    /// RPN 模式的计算器，= 键为 ENTER
+    fn rpn() -> CalculatorCore {
+        let mut core = CalculatorCore::new();
+        core.apply(Command::ToggleRpnMode);
+        core
+    }
+
+    #[test]
+    fn test_rpn_arithmetic() {
+        let mut core = rpn();
+        press(&mut core, "3=4+");
+        assert_eq!(core.display_text(), "7");
+        assert_eq!(core.history()[0].expression, "3+4");
+        assert!(core.history()[0].settings.rpn_mode);
+
+        // 2 ENTER 3 ENTER 4 × + = 2 + 3×4
+        let mut core = rpn();
+        press(&mut core, "2=3=4");
+        assert_eq!(core.stack_levels(), vec!["3", "2"]);
+        assert_eq!(core.display_text(), "4");
+        press(&mut core, "×");
+        assert_eq!(core.stack_levels(), vec!["2"]);
+        press(&mut core, "+");
+        assert_eq!(core.display_text(), "14");
+        assert!(core.stack_levels().is_empty());
+
+        // ENTER 复制 X，% 把 X 换成 Y 的 X%
+        let mut core = rpn();
+        press(&mut core, "5==×");
+        assert_eq!(core.display_text(), "25");
+        let mut core = rpn();
+        press(&mut core, "200=10%");
+        assert_eq!(core.display_text(), "20");
+        assert_eq!(core.stack_levels(), vec!["200"]);
+        press(&mut core, "+");
+        assert_eq!(core.display_text(), "220");
+
+        // 函数作用于 X
+        let mut core = rpn();
+        press(&mut core, "0");
+        core.apply(Command::Function("cosh".to_string()));
+        assert_eq!(core.display_text(), "1");
+        assert_eq!(core.history()[0].expression, "cosh(0)");
+    }
+
+    #[test]
+    fn test_rpn_stack_operations() {
+        let mut core = rpn();
+        press(&mut core, "5=2");
+        core.apply(Command::Swap);
+        press(&mut core, "-");
+        assert_eq!(core.display_text(), "-3");
+        assert_eq!(core.history()[0].expression, "2-5");
+
+        // R↓：X 移到栈底
+        let mut core = rpn();
+        press(&mut core, "1=2=3");
+        core.apply(Command::RollDown);
+        assert_eq!(core.display_text(), "2");
+        assert_eq!(core.stack_levels(), vec!["1", "3"]);
+
+        // ← 在输入时删除字符，不在输入时丢弃 X
+        let mut core = rpn();
+        press(&mut core, "7=12<");
+        assert_eq!(core.display_text(), "1");
+        press(&mut core, "<");
+        assert_eq!(core.display_text(), "7");
+        press(&mut core, "<");
+        assert_eq!(core.display_text(), "0");
+        assert_eq!(press(&mut core, "<"), vec![]);
+
+        // LASTx 取回上一次运算前的 X
+        let mut core = rpn();
+        press(&mut core, "10=4÷");
+        assert_eq!(core.display_text(), "2.5");
+        core.apply(Command::LastX);
+        assert_eq!(core.display_text(), "4");
+        press(&mut core, "×");
+        assert_eq!(core.display_text(), "10");
+
+        // AC 清空栈
+        press(&mut core, "C");
+        assert!(core.stack_levels().is_empty());
+        assert_eq!(core.display_text(), "0");
+    }
+
+    #[test]
+    fn test_rpn_errors() {
+        // 栈中的值不够时运算键不起作用
+        let mut core = rpn();
+        assert_eq!(press(&mut core, "+"), vec![]);
+        assert_eq!(core.apply(Command::Swap), vec![]);
+
+        // 出错时栈不变
+        press(&mut core, "4");
+        let events = core.apply(Command::Function("nope".to_string()));
+        assert_eq!(
+            events.last(),
+            Some(&CalculatorEvent::ResultCalculated {
+                expression: "nope(4)".to_string(),
+                result: Err(CalcError::UnknownFunction("nope".to_string()))
+            })
+        );
+        assert_eq!(core.display_text(), "4");
+        assert!(core.history().is_empty());
+
+        // 无法求值的输入留在输入行
+        core.apply(Command::Paste("nope(1)".to_string()));
+        press(&mut core, "=");
+        assert_eq!(core.display_text(), "nope(1)");
+    }
+
+    #[test]
+    fn test_rpn_mode_switch() {
+        // 进入 RPN 模式时刚计算的结果成为 X，退出后 X 作为上一个结果
+        let mut core = CalculatorCore::new();
+        press(&mut core, "2+3=");
+        assert_eq!(
+            core.apply(Command::ToggleRpnMode),
+            vec![CalculatorEvent::RpnModeToggled(true)]
+        );
+        press(&mut core, "2×");
+        assert_eq!(core.display_text(), "10");
+        core.apply(Command::ToggleRpnMode);
+        press(&mut core, "+1=");
+        assert_eq!(core.display_text(), "11");
+
+        // 零售模式和 RPN 模式不能同时启用
+        core.apply(Command::ToggleRpnMode);
+        core.apply(Command::ToggleRetailMode);
+        assert!(!core.rpn_mode());
+
+        // 记忆寄存器和取回历史记录作用于 X
+        let mut core = rpn();
+        press(&mut core, "7");
+        core.apply(Command::MemoryAdd(true));
+        assert_eq!(core.memory().m, Some(Value::Number(7.0)));
+        press(&mut core, "3=4+");
+        core.apply(Command::RecallExpression(0));
+        assert_eq!(core.display_text(), "3+4");
+        assert_eq!(core.stack_levels(), vec!["7", "7"]);
+        press(&mut core, "×");
+        assert_eq!(core.display_text(), "49");
+    }
+
+    #[test]
+    fn test_rpn_locale() {
+        // 德语区域格式下 , 是小数点，1,234 压入栈的是 1.234
+        let mut core = rpn();
+        core.apply(Command::SetLocale(LOCALES[1]));
+        press(&mut core, "1.234=");
+        assert_eq!(core.display_text(), "1,234");
+        press(&mut core, "2×");
+        assert_eq!(core.display_text(), "2,468");
+        assert_eq!(core.history()[0].expression, "1.234*2");
+    }
+
+    #[test]
+    fn test_rpn_undo_and_restore() {
+        let mut core = rpn();
+        press(&mut core, "3=4+");
+        core.apply(Command::Undo);
+        assert_eq!(core.display_text(), "4");
+        assert_eq!(core.stack_levels(), vec!["3"]);
+        press(&mut core, "=");
+        core.apply(Command::Swap);
+
+        let mut restored = CalculatorCore::new();
+        restored.restore(core.to_saved());
+        assert!(restored.rpn_mode());
+        assert_eq!(restored.stack_levels(), vec!["4"]);
+        press(&mut restored, "-");
+        assert_eq!(restored.display_text(), "1");
+    }
+
+    /// 输入中的表达式的预览结果
+    fn preview_of(core: &CalculatorCore) -> Option<(Value, bool)> {
+        let preview = core.preview()?;
+        let complete = preview.complete();
+        preview.evaluate().map(|value| (value, complete))
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut core = CalculatorCore::new();
+        assert!(core.preview().is_none());
+        press(&mut core, "12");
+        assert!(core.preview().is_none());
+        press(&mut core, "+3");
+        assert_eq!(preview_of(&core), Some((Value::Number(15.0), true)));
+        // 末尾的运算符不参与预览，预览标记为不完整
+        press(&mut core, "×");
+        assert_eq!(preview_of(&core), Some((Value::Number(15.0), false)));
+        // 未闭合的括号自动补全
+        press(&mut core, "(2+2");
+        assert_eq!(preview_of(&core), Some((Value::Number(24.0), true)));
+        // 无法求值时没有预览结果，计算之后不再预览
+        core.apply(Command::Paste("nope(1".to_string()));
+        assert_eq!(preview_of(&core), None);
+        press(&mut core, "C1+1=");
+        assert!(core.preview().is_none());
+        // 耗时的函数和巨大的精确整数不预览
+        for text in [
+            "factor(360",
+            "sqrt(2)+nextprime(10)",
+            "9999!",
+            "(10^1000)^1000",
+        ] {
+            let mut core = CalculatorCore::new();
+            core.apply(Command::Paste(text.to_string()));
+            assert_eq!(core.state().expression, text);
+            assert!(core.preview().is_none());
+        }
+
+        // 零售模式按零售规则预览
+        let mut core = CalculatorCore::new();
+        press(&mut core, "200-10%");
+        assert_eq!(preview_of(&core), Some((Value::Number(199.9), true)));
+        core.apply(Command::ToggleRetailMode);
+        assert_eq!(preview_of(&core), Some((Value::Number(180.0), true)));
+        assert!(rpn().preview().is_none());
+    }
+
+    #[test]
+    fn test_preview_keeps_rng() {
+        // 预览使用会话的副本，不改变随机数状态，= 的结果与预览相同
+        let mut core = CalculatorCore::new();
+        core.apply(Command::Function("rand".to_string()));
+        press(&mut core, ")+1");
+        let preview = preview_of(&core).unwrap().0;
+        assert_eq!(preview_of(&core).unwrap().0, preview);
+        press(&mut core, "=");
+        assert_eq!(core.state().last_result, Some(preview));
+    }
+}
@@ -0,0 +1,463 @@
+//! 会话文件：保存和恢复计算历史、当前输入、设置、统计数据集、记忆寄存器和 RPN 栈
+//!
+//! 会话保存在数据目录下的 mycal/session.json（环境变量 MYCAL_SESSION 可指定其他位置），
+//! 文件带有版本号：读取旧版本时按顺序执行迁移，读取失败时把文件改名备份，从空白会话开始。
+//! 计算结果按结构保存，数值保存为 Rust 的最短文本，所以 inf、NaN 和换算过的货币也能原样还原。
+
+use crate::logic::format::NumberFormat;
+use crate::logic::retail;
+use crate::logic::units::Dimension;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 指定会话文件位置的环境变量
+const SESSION_ENV: &str = "MYCAL_SESSION";
+
+/// 当前的会话文件版本
+pub const VERSION: u32 = 4;
+
+/// 一次版本迁移，文件结构不对时返回错误
+type Migration = fn(&mut serde_json::Value) -> Result<(), String>;
+
+/// 版本迁移：MIGRATIONS[i] 把版本 i + 1 的文件升级到版本 i + 2
+const MIGRATIONS: &[Migration] = &[add_memory, add_rpn, tag_expressions];
+
+/// 会话文件的内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSession {
+    /// 文件版本
+    pub version: u32,
+    /// 显示和零售设置
+    pub settings: SavedSettings,
+    /// 当前输入
+    pub state: SavedState,
+    /// 计算历史，按时间顺序排列
+    pub history: Vec<SavedEntry>,
+    /// 统计数据集
+    pub dataset: Vec<f64>,
+    /// 记忆寄存器
+    pub memory: SavedMemory,
+    /// RPN 栈
+    pub stack: SavedStack,
+}
+
+/// 显示和零售设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSettings {
+    /// 数值显示格式
+    pub number_format: NumberFormat,
+    /// 区域代码，如 de
+    pub locale: String,
+    /// 是否启用逗号格式化
+    pub comma_formatting: bool,
+    /// 是否处于零售模式
+    pub retail_mode: bool,
+    /// 零售模式的税率和取整方式
+    pub retail: retail::Settings,
+    /// 是否处于 RPN 模式
+    pub rpn_mode: bool,
+}
+
+/// 当前输入
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedState {
+    /// 当前表达式（区域格式）
+    pub expression: String,
+    /// 上一个计算结果
+    pub last_result: Option<SavedValue>,
+    /// 是否刚执行过计算
+    pub just_calculated: bool,
+    /// 零售计算的明细
+    pub breakdown: Option<SavedBreakdown>,
+}
+
+/// 零售计算的明细
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedBreakdown {
+    /// 每一步一行
+    pub lines: Vec<String>,
+    /// 最终金额（分）
+    pub cents: i64,
+}
+
+/// 一条计算历史
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedEntry {
+    /// 求值的表达式（规范格式）
+    pub expression: String,
+    /// 计算结果
+    pub result: SavedValue,
+    /// 求值前随机数生成器的状态
+    pub seed: u64,
+    /// 计算时间（Unix 时间戳，秒）
+    pub timestamp: i64,
+    /// 计算时显示的结果文本
+    pub display: String,
+    /// 计算时的设置
+    pub settings: SavedSettings,
+    /// 是否固定在历史列表顶部
+    pub pinned: bool,
+}
+
+/// 记忆寄存器，空寄存器为 None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMemory {
+    /// 记忆寄存器 M
+    pub m: Option<SavedValue>,
+    /// 编号寄存器，按编号排列
+    pub registers: Vec<Option<SavedValue>>,
+}
+
+/// RPN 栈
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedStack {
+    /// 栈中的值，X 在末尾
+    pub levels: Vec<SavedValue>,
+    /// 上一次运算前的 X
+    pub last_x: Option<SavedValue>,
+}
+
+/// 计算结果，与 logic::Value 一一对应；JSON 不能表示 inf 和 NaN，数值保存为文本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedValue {
+    /// 数值，如 0.5、inf、NaN
+    Number(String),
+    /// 任意精度整数的十进制文本
+    Integer(String),
+    /// 质因数分解的 (质数, 指数)
+    Factors(Vec<(String, u32)>),
+    /// 列表
+    List(Vec<SavedValue>),
+    /// 带单位的量
+    Quantity {
+        /// SI 单位下的数值
+        value: String,
+        /// 量纲
+        dimension: Dimension,
+        /// 显示单位和它换算到 SI 的比例
+        display: Option<(String, f64)>,
+    },
+    /// 日期，距 1970-01-01 的天数
+    Date(i64),
+    /// 版本 3 及更早的文件保存的表达式文本，读取时在计算器的会话中重新求值
+    Expression(String),
+}
+
+/// 默认会话文件：环境变量 MYCAL_SESSION 指定的文件，否则为数据目录下的 mycal/session.json
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(SESSION_ENV) {
+        return Some(path.into());
+    }
+    Some(dirs::data_dir()?.join("mycal").join("session.json"))
+}
+
+/// 读取会话文件，文件不存在时返回 Ok(None)
+pub fn load(path: &Path) -> Result<Option<SavedSession>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.to_string()),
+    };
+    let json: serde_json::Value = serde_json::from_str(&text).map_err(|error| error.to_string())?;
+    serde_json::from_value(migrate(json)?)
+        .map(Some)
+        .map_err(|error| error.to_string())
+}
+
+/// 把旧版本的会话升级到当前版本，较新版本写入的文件无法读取
+fn migrate(mut json: serde_json::Value) -> Result<serde_json::Value, String> {
+    let version = json
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or("missing version")?;
+    if version == 0 || version > VERSION as u64 {
+        return Err(format!("unsupported version {}", version));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut json)?;
+    }
+    json["version"] = VERSION.into();
+    Ok(json)
+}
+
+/// 版本 1 → 2：增加空的记忆寄存器
+fn add_memory(json: &mut serde_json::Value) -> Result<(), String> {
+    json["memory"] = serde_json::json!({ "m": null, "registers": [] });
+    Ok(())
+}
+
+/// 版本 2 → 3：增加 RPN 模式开关（包括每条历史记录的设置）和空的 RPN 栈
+fn add_rpn(json: &mut serde_json::Value) -> Result<(), String> {
+    settings_of(json)?.insert("rpn_mode".to_string(), false.into());
+    let history = json
+        .get_mut("history")
+        .and_then(serde_json::Value::as_array_mut)
+        .ok_or("invalid history")?;
+    for entry in history {
+        settings_of(entry)?.insert("rpn_mode".to_string(), false.into());
+    }
+    json["stack"] = serde_json::json!({ "levels": [], "last_x": null });
+    Ok(())
+}
+
+/// 版本 3 → 4：计算结果改为按结构保存，原来的表达式文本标记为 Expression
+fn tag_expressions(json: &mut serde_json::Value) -> Result<(), String> {
+    tag_expression(
+        json.pointer_mut("/state/last_result")
+            .ok_or("invalid state")?,
+    )?;
+    let history = json
+        .get_mut("history")
+        .and_then(serde_json::Value::as_array_mut)
+        .ok_or("invalid history")?;
+    for entry in history {
+        tag_expression(entry.get_mut("result").ok_or("invalid history")?)?;
+    }
+    tag_expression(json.pointer_mut("/stack/last_x").ok_or("invalid stack")?)?;
+    let levels = json
+        .pointer_mut("/stack/levels")
+        .and_then(serde_json::Value::as_array_mut)
+        .ok_or("invalid stack")?;
+    for level in levels {
+        tag_expression(level)?;
+    }
+    tag_expression(json.pointer_mut("/memory/m").ok_or("invalid memory")?)?;
+    let registers = json
+        .pointer_mut("/memory/registers")
+        .and_then(serde_json::Value::as_array_mut)
+        .ok_or("invalid memory")?;
+    for register in registers {
+        tag_expression(register)?;
+    }
+    Ok(())
+}
+
+/// 把一个保存的表达式文本标记为 Expression，空值保持不变
+fn tag_expression(value: &mut serde_json::Value) -> Result<(), String> {
+    match value {
+        serde_json::Value::Null => {}
+        serde_json::Value::String(text) => {
+            let text = std::mem::take(text);
+            *value = serde_json::json!({ "Expression": text });
+        }
+        _ => return Err("invalid saved value".to_string()),
+    }
+    Ok(())
+}
+
+/// 会话或历史记录中的设置对象
+fn settings_of(
+    json: &mut serde_json::Value,
+) -> Result<&mut serde_json::Map<String, serde_json::Value>, String> {
+    json.get_mut("settings")
+        .and_then(serde_json::Value::as_object_mut)
+        .ok_or_else(|| "invalid settings".to_string())
+}
+
+/// 写入会话文件：先写临时文件再改名，写到一半退出也不会损坏原文件
+pub fn save(path: &Path, session: &SavedSession) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(session).map_err(|error| error.to_string())?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    let temporary = path.with_extension("json.tmp");
+    std::fs::write(&temporary, text)
+        .and_then(|_| std::fs::rename(&temporary, path))
+        .map_err(|error| error.to_string())
+}
+
+/// 把无法读取的会话文件改名为 session.json.corrupt 备份，返回备份路径
+pub fn quarantine(path: &Path) -> Option<PathBuf> {
+    let backup = path.with_extension("json.corrupt");
+    std::fs::rename(path, &backup).ok().map(|_| backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用的临时会话文件路径
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("mycal-test-{}-{}", std::process::id(), name))
+            .join("session.json")
+    }
+
+    fn session() -> SavedSession {
+        let settings = SavedSettings {
+            number_format: NumberFormat::Fix(2),
+            locale: "de".to_string(),
+            comma_formatting: true,
+            retail_mode: false,
+            retail: retail::Settings::default(),
+            rpn_mode: true,
+        };
+        SavedSession {
+            version: VERSION,
+            settings: settings.clone(),
+            state: SavedState {
+                expression: "1+".to_string(),
+                last_result: Some(SavedValue::Number("inf".to_string())),
+                just_calculated: false,
+                breakdown: None,
+            },
+            history: vec![SavedEntry {
+                expression: "2*3".to_string(),
+                result: SavedValue::Integer("6".to_string()),
+                seed: 42,
+                timestamp: 1_792_332_245,
+                display: "6,00".to_string(),
+                settings,
+                pinned: true,
+            }],
+            dataset: vec![1.0, 2.5],
+            memory: SavedMemory {
+                m: Some(SavedValue::Integer("36".to_string())),
+                registers: vec![
+                    None,
+                    Some(SavedValue::Factors(vec![
+                        ("2".to_string(), 3),
+                        ("3".to_string(), 2),
+                        ("5".to_string(), 1),
+                    ])),
+                ],
+            },
+            stack: SavedStack {
+                levels: vec![
+                    SavedValue::Number("-1.5".to_string()),
+                    SavedValue::Quantity {
+                        value: "NaN".to_string(),
+                        dimension: Dimension::NONE,
+                        display: Some(("EUR".to_string(), 2.0)),
+                    },
+                ],
+                last_x: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let path = temporary_path("roundtrip");
+        assert_eq!(load(&path), Ok(None));
+        save(&path, &session()).unwrap();
+        assert_eq!(load(&path), Ok(Some(session())));
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_corrupt_file() {
+        let path = temporary_path("corrupt");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{\"version\": 1, \"history\": [").unwrap();
+        assert!(load(&path).is_err());
+
+        // 备份后原位置没有文件，下次启动从空白会话开始
+        let backup = quarantine(&path).unwrap();
+        assert!(backup.exists());
+        assert_eq!(load(&path), Ok(None));
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_versions() {
+        let mut json = serde_json::to_value(session()).unwrap();
+        assert!(migrate(json.clone()).is_ok());
+        json["version"] = (VERSION + 1).into();
+        assert_eq!(
+            migrate(json.clone()),
+            Err(format!("unsupported version {}", VERSION + 1))
+        );
+        json.as_object_mut().unwrap().remove("version");
+        assert_eq!(migrate(json), Err("missing version".to_string()));
+    }
+
+    #[test]
+    fn test_migrate_version_1() {
+        // 版本 1 的文件没有记忆寄存器、RPN 模式开关和 RPN 栈，结果保存为表达式文本
+        let mut json = serde_json::to_value(session()).unwrap();
+        json["version"] = 1.into();
+        json["state"]["last_result"] = "inf".into();
+        json["history"][0]["result"] = "6".into();
+        json.as_object_mut().unwrap().remove("memory");
+        json.as_object_mut().unwrap().remove("stack");
+        json["settings"].as_object_mut().unwrap().remove("rpn_mode");
+        json["history"][0]["settings"]
+            .as_object_mut()
+            .unwrap()
+            .remove("rpn_mode");
+        let migrated: SavedSession = serde_json::from_value(migrate(json).unwrap()).unwrap();
+        assert_eq!(migrated.version, VERSION);
+        assert_eq!(
+            migrated.memory,
+            SavedMemory {
+                m: None,
+                registers: Vec::new()
+            }
+        );
+        assert_eq!(migrated.stack.levels, Vec::<SavedValue>::new());
+        assert!(!migrated.settings.rpn_mode);
+        assert_eq!(
+            migrated.state.last_result,
+            Some(SavedValue::Expression("inf".to_string()))
+        );
+        // 表达式和种子原样保留，结果标记为表达式文本，历史记录的设置关闭 RPN 模式
+        let mut history = session().history;
+        for entry in &mut history {
+            entry.settings.rpn_mode = false;
+            entry.result = SavedValue::Expression("6".to_string());
+        }
+        assert_eq!(migrated.history, history);
+    }
+
+    #[test]
+    fn test_migrate_version_3() {
+        // 版本 3 的文件把结果保存为表达式文本，迁移后标记为 Expression，读取时重新求值
+        let mut json = serde_json::to_value(session()).unwrap();
+        json["version"] = 3.into();
+        json["state"]["last_result"] = serde_json::Value::Null;
+        json["history"][0]["result"] = "100 USD to EUR".into();
+        json["memory"] = serde_json::json!({ "m": "36", "registers": [null, "factor(360)"] });
+        json["stack"] = serde_json::json!({ "levels": ["2"], "last_x": "-1.5" });
+        let migrated: SavedSession = serde_json::from_value(migrate(json).unwrap()).unwrap();
+        let expression = |text: &str| SavedValue::Expression(text.to_string());
+        assert_eq!(migrated.state.last_result, None);
+        assert_eq!(migrated.history[0].result, expression("100 USD to EUR"));
+        assert_eq!(
+            migrated.memory,
+            SavedMemory {
+                m: Some(expression("36")),
+                registers: vec![None, Some(expression("factor(360)"))],
+            }
+        );
+        assert_eq!(
+            migrated.stack,
+            SavedStack {
+                levels: vec![expression("2")],
+                last_x: Some(expression("-1.5")),
+            }
+        );
+
+        // 结果不是文本的版本 3 文件无法读取
+        let mut json = serde_json::to_value(session()).unwrap();
+        json["version"] = 3.into();
+        json["history"][0]["result"] = 6.into();
+        assert_eq!(migrate(json), Err("invalid saved value".to_string()));
+    }
+
+    #[test]
+    fn test_migrate_corrupt_version_2() {
+        // 结构不对的旧版本文件返回错误，由调用方备份，而不是在迁移时崩溃
+        let path = temporary_path("corrupt-v2");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        for text in [
+            r#"{"version":2,"settings":5,"history":[1]}"#,
+            r#"{"version":2,"settings":{},"history":[1]}"#,
+            r#"{"version":2,"settings":{},"history":{}}"#,
+        ] {
+            std::fs::write(&path, text).unwrap();
+            assert!(load(&path).is_err(), "{}", text);
+        }
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
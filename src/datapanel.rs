@@ -0,0 +1,204 @@
+use crate::button::form_button;
+use crate::calculator::{Calculator, Command};
+use crate::logic::stats::Summary;
+use crate::numberfield::NumberField;
+use crate::style;
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+/// 统计数据面板 - 每行输入一个数值，实时显示汇总统计
+pub struct DataPanel {
+    /// 计算器实体的引用，数据集保存在计算器中
+    calculator: Entity<Calculator>,
+    /// 每行一个数值输入框
+    rows: Vec<NumberField>,
+}
+
+impl DataPanel {
+    /// 创建新的数据面板
+    pub fn new(calculator: Entity<Calculator>, cx: &mut Context<Self>) -> Self {
+        // 数据集变化时重新渲染统计结果
+        cx.observe(&calculator, |_this, _calculator, cx| cx.notify())
+            .detach();
+
+        Self {
+            calculator,
+            rows: vec![NumberField::new(cx)],
+        }
+    }
+
+    /// 将可解析的行写回计算器的数据集，无法解析的行被忽略
+    fn sync_dataset(&self, cx: &mut Context<Self>) {
+        let dataset: Vec<f64> = self.rows.iter().filter_map(NumberField::value).collect();
+        self.calculator.update(cx, |calculator, cx| {
+            calculator.apply(Command::SetDataset(dataset), cx)
+        });
+    }
+
+    /// 在指定位置之后插入新行并聚焦
+    fn insert_row(&mut self, after: usize, window: &mut Window, cx: &mut Context<Self>) {
+        let index = (after + 1).min(self.rows.len());
+        self.rows.insert(index, NumberField::new(cx));
+        self.rows[index].focus(window);
+        cx.notify();
+    }
+
+    /// 清空所有数据
+    fn clear(&mut self, cx: &mut Context<Self>) {
+        self.rows = vec![NumberField::new(cx)];
+        self.sync_dataset(cx);
+        cx.notify();
+    }
+
+    /// 处理某一行的按键：回车新增一行，空行退格删除该行，上下键切换行
+    fn handle_row_key(
+        &mut self,
+        index: usize,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event.keystroke.key.as_str() {
+            "enter" => self.insert_row(index, window, cx),
+            "up" if index > 0 => self.rows[index - 1].focus(window),
+            "down" if index + 1 < self.rows.len() => self.rows[index + 1].focus(window),
+            "backspace" if self.rows[index].text().is_empty() && self.rows.len() > 1 => {
+                self.rows.remove(index);
+                self.rows[index.saturating_sub(1)].focus(window);
+                self.sync_dataset(cx);
+                cx.notify();
+            }
+            _ => {
+                if self.rows[index].handle_key(&event.keystroke) {
+                    self.sync_dataset(cx);
+                    cx.notify();
+                }
+            }
+        }
+    }
+
+    /// 渲染汇总统计表
+    fn render_summary(&self, cx: &App) -> Div {
+        let summary = Summary::of(self.calculator.read(cx).core().dataset());
+        let ignored = self
+            .rows
+            .iter()
+            .filter(|row| !row.text().trim().is_empty() && row.value().is_none())
+            .count();
+
+        let format_stat = |value: Option<f64>| value.map_or("—".to_string(), |v| v.to_string());
+        let mode = if summary.mode.is_empty() {
+            "—".to_string()
+        } else {
+            summary
+                .mode
+                .iter()
+                .map(f64::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let stats = [
+            ("n", summary.count.to_string()),
+            ("Σx", summary.sum.to_string()),
+            ("mean", format_stat(summary.mean)),
+            ("median", format_stat(summary.median)),
+            ("mode", mode),
+            ("stdev (s)", format_stat(summary.sample_stdev)),
+            ("stdev (σ)", format_stat(summary.population_stdev)),
+            ("var (s²)", format_stat(summary.sample_variance)),
+            ("min", format_stat(summary.min)),
+            ("Q1", format_stat(summary.q1)),
+            ("Q3", format_stat(summary.q3)),
+            ("max", format_stat(summary.max)),
+        ];
+
+        div()
+            .flex()
+            .flex_col()
+            .flex_1()
+            .gap_1()
+            .text_sm()
+            .children(stats.into_iter().map(|(label, value)| {
+                div()
+                    .flex()
+                    .justify_between()
+                    .child(div().text_color(rgb(style::MUTED_COLOR)).child(label))
+                    .child(value)
+            }))
+            .when(ignored > 0, |this| {
+                this.child(
+                    div()
+                        .text_color(rgb(style::PRIMARY_COLOR))
+                        .child(format!("{} invalid row(s) ignored", ignored)),
+                )
+            })
+    }
+}
+
+impl Render for DataPanel {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let rows = self.rows.iter().enumerate().map(|(index, row)| {
+            div()
+                .flex()
+                .gap_1()
+                .items_center()
+                .child(
+                    div()
+                        .w_6()
+                        .text_sm()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(format!("{}", index + 1)),
+                )
+                .child(
+                    row.render(("data-row", index), window)
+                        .on_key_down(cx.listener(move |this, event: &KeyDownEvent, window, cx| {
+                            this.handle_row_key(index, event, window, cx)
+                        })),
+                )
+        });
+        let last_row = self.rows.len() - 1;
+
+        div()
+            .id("data-panel")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_2()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(
+                div()
+                    .flex()
+                    .gap_1()
+                    .items_center()
+                    .child(div().flex_1().child("One value per row"))
+                    .child(form_button("data-add", "Add").on_click(cx.listener(
+                        move |this, _, window, cx| this.insert_row(last_row, window, cx),
+                    )))
+                    .child(
+                        form_button("data-clear", "Clear")
+                            .on_click(cx.listener(|this, _, _, cx| this.clear(cx))),
+                    ),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_1()
+                    .gap_3()
+                    .min_h_0()
+                    .child(
+                        div()
+                            .id("data-rows")
+                            .flex()
+                            .flex_col()
+                            .flex_1()
+                            .gap_1()
+                            .overflow_y_scroll()
+                            .children(rows),
+                    )
+                    .child(self.render_summary(cx)),
+            )
+    }
+}
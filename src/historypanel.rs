@@ -0,0 +1,202 @@
+use crate::calculator::{Calculator, Command};
+use crate::logic::dates::format_timestamp;
+use crate::style;
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+/// 历史面板 - 按时间倒序列出每次计算，点击表达式或结果取回到输入，可以搜索和固定
+pub struct HistoryPanel {
+    /// 计算器实体的引用，历史记录保存在计算器中
+    calculator: Entity<Calculator>,
+    /// 搜索文本
+    query: String,
+    /// 搜索框的焦点
+    focus_handle: FocusHandle,
+}
+
+impl HistoryPanel {
+    /// 创建新的历史面板
+    pub fn new(calculator: Entity<Calculator>, cx: &mut Context<Self>) -> Self {
+        // 每次计算后重新渲染历史列表
+        cx.observe(&calculator, |_this, _calculator, cx| cx.notify())
+            .detach();
+
+        Self {
+            calculator,
+            query: String::new(),
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    /// 向计算器发送命令
+    fn apply(&mut self, command: Command, cx: &mut Context<Self>) {
+        self.calculator
+            .update(cx, |calculator, cx| calculator.apply(command, cx));
+    }
+
+    /// 处理搜索框的按键：输入文字，退格删除，Esc 清空
+    fn handle_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.control || keystroke.modifiers.platform {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "backspace" => {
+                self.query.pop();
+            }
+            "escape" => self.query.clear(),
+            _ => match keystroke.key_char.as_deref() {
+                Some(input) if !input.chars().any(char::is_control) => self.query.push_str(input),
+                _ => return,
+            },
+        }
+        cx.notify();
+    }
+}
+
+impl Render for HistoryPanel {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let core = self.calculator.read(cx).core();
+        let matches = core.search_history(&self.query);
+        let is_empty = core.history().is_empty();
+        let rows: Vec<Div> = matches
+            .into_iter()
+            .map(|index| {
+                let entry = &core.history()[index];
+                let mut details = vec![
+                    format!("{} UTC", format_timestamp(entry.timestamp)),
+                    entry.settings.number_format.to_string(),
+                ];
+                if entry.settings.retail_mode {
+                    details.push("Retail".to_string());
+                }
+                if entry.settings.rpn_mode {
+                    details.push("RPN".to_string());
+                }
+
+                div()
+                    .flex()
+                    .gap_2()
+                    .items_start()
+                    .child(
+                        div()
+                            .id(("history-pin", index))
+                            .cursor_pointer()
+                            .text_color(rgb(if entry.pinned {
+                                style::PRIMARY_COLOR
+                            } else {
+                                style::MUTED_COLOR
+                            }))
+                            .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                            .child(if entry.pinned { "★" } else { "☆" })
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                this.apply(Command::TogglePin(index), cx)
+                            })),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .flex_1()
+                            .min_w_0()
+                            .child(
+                                div()
+                                    .id(("history-expression", index))
+                                    .cursor_pointer()
+                                    .text_sm()
+                                    .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                                    .child(entry.expression_text())
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.apply(Command::RecallExpression(index), cx)
+                                    })),
+                            )
+                            .child(
+                                div()
+                                    .id(("history-result", index))
+                                    .cursor_pointer()
+                                    .text_lg()
+                                    .text_color(rgb(style::PRIMARY_COLOR))
+                                    .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                                    .child(format!("= {}", entry.display))
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.apply(Command::RecallResult(index), cx)
+                                    })),
+                            )
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(rgb(style::MUTED_COLOR))
+                                    .child(details.join(" · ")),
+                            ),
+                    )
+            })
+            .collect();
+
+        let is_focused = self.focus_handle.is_focused(window);
+        let search = div()
+            .id("history-search")
+            .track_focus(&self.focus_handle)
+            .flex()
+            .h_8()
+            .px_2()
+            .items_center()
+            .rounded_md()
+            .border_1()
+            .border_color(rgb(if is_focused {
+                style::PRIMARY_COLOR
+            } else {
+                style::FIELD_COLOR
+            }))
+            .bg(rgb(if is_focused {
+                style::FIELD_FOCUS_COLOR
+            } else {
+                style::FIELD_COLOR
+            }))
+            .overflow_hidden()
+            .on_key_down(
+                cx.listener(|this, event: &KeyDownEvent, _, cx| this.handle_key(event, cx)),
+            )
+            .map(|this| {
+                if self.query.is_empty() {
+                    this.text_color(rgb(style::MUTED_COLOR)).child("Search")
+                } else {
+                    this.child(self.query.clone())
+                }
+            });
+
+        let placeholder = match (is_empty, rows.is_empty()) {
+            (true, _) => Some("No calculations yet"),
+            (false, true) => Some("No matches"),
+            (false, false) => None,
+        };
+
+        div()
+            .id("history-panel")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_2()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(search)
+            .when_some(placeholder, |this, text| {
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(text),
+                )
+            })
+            .child(
+                div()
+                    .id("history-rows")
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .gap_2()
+                    .overflow_y_scroll()
+                    .children(rows),
+            )
+    }
+}
@@ -0,0 +1,262 @@
+use crate::button::form_button;
+use crate::logic::CalcError;
+use crate::logic::finance::{self, Cents, Installment};
+use crate::numberfield::NumberField;
+use crate::style;
+use gpui::*;
+use std::path::PathBuf;
+
+/// 导出 CSV 时建议的文件名
+const CSV_NAME: &str = "amortization.csv";
+
+/// 贷款参数输入框
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    /// 贷款额
+    Principal,
+    /// 年利率（百分数）
+    AnnualRate,
+    /// 还款月数
+    Months,
+}
+
+impl Input {
+    /// 所有输入，按界面顺序排列
+    const ALL: [Input; 3] = [Input::Principal, Input::AnnualRate, Input::Months];
+
+    /// 在 fields 数组中的下标
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// 输入框前的标签
+    fn label(self) -> &'static str {
+        match self {
+            Input::Principal => "Amount",
+            Input::AnnualRate => "Rate %/yr",
+            Input::Months => "Months",
+        }
+    }
+}
+
+/// 贷款面板 - 输入贷款额、年利率和月数，按月生成等额本息还款计划，可导出为 CSV
+pub struct LoanPanel {
+    /// 三个参数输入框
+    fields: [NumberField; 3],
+    /// 最近一次生成的还款计划，参数不完整时为 None
+    schedule: Option<Result<Vec<Installment>, CalcError>>,
+    /// 最近一次导出的结果：成功时为提示，失败时为错误信息
+    export_status: Option<Result<String, String>>,
+}
+
+impl LoanPanel {
+    /// 创建新的贷款面板
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            fields: [
+                NumberField::new(cx),
+                NumberField::new(cx),
+                NumberField::new(cx),
+            ],
+            schedule: None,
+            export_status: None,
+        }
+    }
+
+    /// 用当前参数重新生成还款计划
+    fn recompute(&mut self, cx: &mut Context<Self>) {
+        let value = |input: Input| self.fields[input.index()].value();
+        self.schedule = match (
+            value(Input::Principal),
+            value(Input::AnnualRate),
+            value(Input::Months),
+        ) {
+            (Some(principal), Some(rate), Some(months)) => Some(schedule(principal, rate, months)),
+            _ => None,
+        };
+        self.export_status = None;
+        cx.notify();
+    }
+
+    /// 处理参数输入框的按键，内容变化时重新计算
+    fn handle_key(&mut self, input: Input, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        if self.fields[input.index()].handle_key(&event.keystroke) {
+            self.recompute(cx);
+        }
+    }
+
+    /// 弹出保存对话框，把还款计划写入用户选择的 CSV 文件
+    fn export_csv(&mut self, cx: &mut Context<Self>) {
+        let Some(Ok(schedule)) = &self.schedule else {
+            return;
+        };
+        let csv = finance::schedule_csv(schedule);
+        let directory = dirs::document_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let path = cx.prompt_for_new_path(&directory, Some(CSV_NAME));
+
+        cx.spawn(async move |this, cx| {
+            let status = match path.await {
+                Ok(Ok(Some(path))) => Some(
+                    std::fs::write(&path, csv)
+                        .map(|_| format!("Saved {}", path.display()))
+                        .map_err(|error| format!("Export failed: {}", error)),
+                ),
+                Ok(Ok(None)) | Err(_) => None,
+                Ok(Err(error)) => Some(Err(format!("Export failed: {}", error))),
+            };
+            this.update(cx, |this, cx| {
+                this.export_status = status;
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// 渲染还款汇总和导出结果
+    fn render_summary(&self, schedule: &[Installment]) -> Div {
+        let total = |amount: fn(&Installment) -> Cents| {
+            Cents(schedule.iter().map(|row| amount(row).0).sum())
+        };
+        let summary = div()
+            .flex()
+            .flex_col()
+            .text_sm()
+            .child(format!("Payment: {}", schedule[0].payment))
+            .child(format!("Total interest: {}", total(|row| row.interest)))
+            .child(format!("Total paid: {}", total(|row| row.payment)));
+
+        match &self.export_status {
+            None => summary,
+            Some(Ok(message)) => summary.child(
+                div()
+                    .text_color(rgb(style::MUTED_COLOR))
+                    .child(message.clone()),
+            ),
+            Some(Err(message)) => summary.child(
+                div()
+                    .text_color(rgb(style::PRIMARY_COLOR))
+                    .child(message.clone()),
+            ),
+        }
+    }
+}
+
+/// 按月生成还款计划，年利率为百分数
+fn schedule(principal: f64, annual_rate: f64, months: f64) -> Result<Vec<Installment>, CalcError> {
+    let principal = Cents::round(principal).unwrap_or_default();
+    if months.fract() != 0.0 || months < 1.0 {
+        return Err(CalcError::InvalidArguments(
+            "Months must be a whole number".to_string(),
+        ));
+    }
+    finance::amortization(principal, annual_rate / 100.0 / 12.0, months as u32)
+}
+
+/// 还款计划表的一行，五列右对齐
+fn table_row(cells: [String; 5]) -> Div {
+    div()
+        .flex()
+        .gap_2()
+        .children(cells.into_iter().enumerate().map(|(i, cell)| {
+            let column = div().flex().justify_end().child(cell);
+            if i == 0 {
+                column.w_10()
+            } else {
+                column.flex_1()
+            }
+        }))
+}
+
+impl Render for LoanPanel {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let inputs = Input::ALL.into_iter().map(|input| {
+            div()
+                .flex()
+                .flex_col()
+                .flex_1()
+                .gap_1()
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(input.label()),
+                )
+                .child(
+                    self.fields[input.index()]
+                        .render(("loan-field", input.index()), window)
+                        .on_key_down(cx.listener(move |this, event: &KeyDownEvent, _, cx| {
+                            this.handle_key(input, event, cx)
+                        })),
+                )
+        });
+
+        let body = match &self.schedule {
+            None => div()
+                .text_sm()
+                .text_color(rgb(style::MUTED_COLOR))
+                .child("Enter the amount, annual rate and term for a monthly schedule"),
+            Some(Err(error)) => div()
+                .text_sm()
+                .text_color(rgb(style::PRIMARY_COLOR))
+                .child(error.to_string()),
+            Some(Ok(schedule)) => {
+                let rows = schedule.iter().map(|row| {
+                    table_row([
+                        row.period.to_string(),
+                        row.payment.to_string(),
+                        row.interest.to_string(),
+                        row.principal.to_string(),
+                        row.balance.to_string(),
+                    ])
+                });
+                let header = ["#", "Payment", "Interest", "Principal", "Balance"].map(String::from);
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .min_h_0()
+                    .gap_2()
+                    .child(
+                        div()
+                            .flex()
+                            .items_start()
+                            .child(div().flex_1().child(self.render_summary(schedule)))
+                            .child(
+                                form_button("loan-export", "Export CSV")
+                                    .on_click(cx.listener(|this, _, _, cx| this.export_csv(cx))),
+                            ),
+                    )
+                    .child(
+                        table_row(header)
+                            .text_sm()
+                            .text_color(rgb(style::MUTED_COLOR)),
+                    )
+                    .child(
+                        div()
+                            .id("loan-schedule")
+                            .flex()
+                            .flex_col()
+                            .flex_1()
+                            .text_sm()
+                            .overflow_y_scroll()
+                            .children(rows),
+                    )
+            }
+        };
+
+        div()
+            .id("loan-panel")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_3()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(div().flex().gap_2().children(inputs))
+            .child(body)
+    }
+}
@@ -1,4 +1,26 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{ToPrimitive, Zero};
 use std::collections::VecDeque;
+use std::fmt;
+
+pub mod currency;
+pub mod dates;
+pub mod distributions;
+pub mod finance;
+pub mod format;
+pub mod linalg;
+pub mod locale;
+pub mod numtheory;
+pub mod random;
+pub mod regression;
+pub mod retail;
+pub mod special;
+pub mod stats;
+pub mod trig;
+pub mod units;
+
+use locale::Locale;
 
 #[derive(Debug, Clone, PartialEq)]
 enum Operation {
@@ -6,14 +28,381 @@ enum Operation {
     Minus,
     Times,
     Divide,
+    /// 一元负号
+    Negate,
+    /// 后缀阶乘 x!，非整数时为 Γ(x + 1)
+    Factorial,
+    /// 省略乘号的乘法（如 3 m、2 sin(x)），优先级高于乘除，1 km / 1 m 中的 1 km 是一个整体
+    ImplicitTimes,
+    /// 复合单位中省略的加号（如 3h 20min、5 ft 3 in），优先级高于乘除
+    ImplicitPlus,
+    /// 乘方 a^b，右结合
+    Power,
+    /// 单位转换 `x to unit`，包含目标单位的文本
+    Convert(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 enum Token {
     Number(f64),
+    /// 不含小数点的数字按任意精度整数保存
+    Integer(BigInt),
+    /// 日期字面量 YYYY-MM-DD，距 1970-01-01 的天数
+    Date(i64),
     Operator(Operation),
     LeftParen,
     RightParen,
+    /// 函数名（后面紧跟左括号）；后面没有左括号的名字是单位
+    Function(String),
+    /// 后缀表达式中的单位
+    Unit(String),
+    /// 参数或列表元素分隔符
+    Comma,
+    LeftBracket,
+    RightBracket,
+    /// 花括号列表 {1,2,3}
+    LeftBrace,
+    RightBrace,
+    /// 后缀表达式中的函数调用，包含函数名和参数个数
+    Call(String, usize),
+    /// 后缀表达式中的列表构造，包含元素个数
+    List(usize),
+}
+
+/// 计算结果值
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// 数值
+    Number(f64),
+    /// 任意精度整数，整数之间的加减乘和整除保持精确
+    Integer(BigInt),
+    /// 质因数分解，按质数升序排列的 (质数, 指数)，显示为 2³ × 3 × 5
+    Factors(Vec<(BigInt, u32)>),
+    /// 列表，嵌套列表表示矩阵（按行存储）
+    List(Vec<Value>),
+    /// 带单位的量，量纲抵消后会退化为数值
+    Quantity(units::Quantity),
+    /// 日期，距 1970-01-01 的天数
+    Date(i64),
+}
+
+impl Value {
+    /// 获取数值，列表、带单位的量和日期返回 None
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(num) => Some(*num),
+            Value::List(_) | Value::Quantity(_) | Value::Date(_) => None,
+            _ => self.as_integer()?.to_f64(),
+        }
+    }
+
+    /// 获取精确整数，浮点数和列表返回 None；质因数分解返回乘积
+    pub fn as_integer(&self) -> Option<BigInt> {
+        match self {
+            Value::Integer(n) => Some(n.clone()),
+            Value::Factors(factors) => Some(
+                factors
+                    .iter()
+                    .map(|(prime, exponent)| num_traits::pow(prime.clone(), *exponent as usize))
+                    .product(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// 将一维数值列表转换为向量
+    pub fn as_vector(&self) -> Option<Vec<f64>> {
+        match self {
+            Value::List(items) => items.iter().map(Value::as_number).collect(),
+            _ => None,
+        }
+    }
+
+    /// 将二维数值列表转换为矩阵，要求每行长度相同
+    pub fn as_matrix(&self) -> Option<Vec<Vec<f64>>> {
+        let rows = match self {
+            Value::List(rows) => rows,
+            _ => return None,
+        };
+        let matrix: Vec<Vec<f64>> = rows.iter().map(Value::as_vector).collect::<Option<_>>()?;
+        if matrix.iter().any(|row| row.len() != matrix[0].len()) {
+            return None;
+        }
+        Some(matrix)
+    }
+}
+
+impl From<Vec<f64>> for Value {
+    fn from(values: Vec<f64>) -> Self {
+        Value::List(values.into_iter().map(Value::Number).collect())
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(n: BigInt) -> Self {
+        Value::Integer(n)
+    }
+}
+
+/// 数值比较：整数与数值相等的浮点数视为相等，列表逐项比较，带单位的量比较 SI 数值和量纲
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Quantity(a), Value::Quantity(b)) => {
+                a.value == b.value && a.dimension == b.dimension
+            }
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::List(_) | Value::Quantity(_) | Value::Date(_), _)
+            | (_, Value::List(_) | Value::Quantity(_) | Value::Date(_)) => false,
+            _ => match (self.as_integer(), other.as_integer()) {
+                (Some(a), Some(b)) => a == b,
+                _ => self.as_number() == other.as_number(),
+            },
+        }
+    }
+}
+
+/// 把数字写成上标，用于质因数分解的指数
+fn superscript(n: u32) -> String {
+    n.to_string()
+        .chars()
+        .map(|digit| match digit {
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            _ => '⁹',
+        })
+        .collect()
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(num) => write!(f, "{}", num),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Factors(factors) if factors.is_empty() => write!(f, "1"),
+            Value::Factors(factors) => {
+                for (i, (prime, exponent)) in factors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " × ")?;
+                    }
+                    if *exponent == 1 {
+                        write!(f, "{}", prime)?;
+                    } else {
+                        write!(f, "{}{}", prime, superscript(*exponent))?;
+                    }
+                }
+                Ok(())
+            }
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Quantity(quantity) => write!(f, "{}", quantity),
+            Value::Date(days) => write!(f, "{}", dates::format_date(*days)),
+        }
+    }
+}
+
+/// 计算错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    /// 表达式语法错误
+    Syntax,
+    /// 未知的函数名
+    UnknownFunction(String),
+    /// 函数参数个数或类型不正确，包含说明
+    InvalidArguments(String),
+    /// 数学上无解或结果不可靠，包含说明
+    Math(String),
+    /// 未知的单位名
+    UnknownUnit(String),
+    /// 量纲不一致，包含两边的单位
+    IncompatibleUnits(String, String),
+    /// 汇率文件中没有该货币，包含货币代码
+    MissingRate(String),
+    /// 表达式中使用了带偏移量的温度单位（°C、°F），包含单位符号
+    TemperatureScale(String),
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::Syntax => write!(f, "Syntax error"),
+            CalcError::UnknownFunction(name) => write!(f, "Unknown function: {}", name),
+            CalcError::InvalidArguments(message) => write!(f, "Invalid arguments: {}", message),
+            CalcError::Math(message) => write!(f, "{}", message),
+            CalcError::UnknownUnit(name) => write!(f, "Unknown unit: {}", name),
+            CalcError::IncompatibleUnits(a, b) => write!(f, "Incompatible units: {} and {}", a, b),
+            CalcError::MissingRate(code) => write!(f, "No exchange rate for {}", code),
+            CalcError::TemperatureScale(unit) => write!(
+                f,
+                "{} works only in the unit converter; use K in expressions",
+                unit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+/// 求值会话：保存跨表达式的状态（如最近一次回归拟合和随机数生成器）
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// 最近一次回归拟合，供 predict(x) 使用
+    pub last_fit: Option<regression::Fit>,
+    /// 随机数生成器，seed(n) 可重置
+    pub rng: random::Rng,
+    /// 从汇率文件读取的汇率，没有汇率文件时为 None
+    pub rates: Option<currency::Rates>,
+    /// 表达式中数字的区域格式
+    pub locale: Locale,
+}
+
+/// 内置函数：接收参数列表，返回计算结果
+type Builtin = fn(&[Value]) -> Result<Value, CalcError>;
+
+/// 内置函数注册表（函数名 -> 实现）
+const FUNCTIONS: &[(&str, Builtin)] = &[
+    ("linsolve", linalg::linsolve),
+    ("sum", stats::sum_builtin),
+    ("mean", stats::mean_builtin),
+    ("median", stats::median_builtin),
+    ("mode", stats::mode_builtin),
+    ("stdev", stats::stdev_builtin),
+    ("stdevp", stats::stdevp_builtin),
+    ("var", stats::var_builtin),
+    ("varp", stats::varp_builtin),
+    ("quantile", stats::quantile_builtin),
+    ("min", stats::min_builtin),
+    ("max", stats::max_builtin),
+    ("normpdf", distributions::normpdf),
+    ("normcdf", distributions::normcdf),
+    ("invnorm", distributions::invnorm),
+    ("binompdf", distributions::binompdf),
+    ("binomcdf", distributions::binomcdf),
+    ("poissonpdf", distributions::poissonpdf),
+    ("tcdf", distributions::tcdf),
+    ("chi2cdf", distributions::chi2cdf),
+    ("gamma", special::gamma_builtin),
+    ("lgamma", special::lgamma),
+    ("beta", special::beta),
+    ("erf", special::erf_builtin),
+    ("erfc", special::erfc_builtin),
+    ("besselj", special::besselj),
+    ("bessely", special::bessely),
+    ("lambertw", special::lambertw),
+    ("sin", trig::sin),
+    ("cos", trig::cos),
+    ("tan", trig::tan),
+    ("sec", trig::sec),
+    ("csc", trig::csc),
+    ("cot", trig::cot),
+    ("asin", trig::asin),
+    ("acos", trig::acos),
+    ("atan", trig::atan),
+    ("sinh", trig::sinh),
+    ("cosh", trig::cosh),
+    ("tanh", trig::tanh),
+    ("asinh", trig::asinh),
+    ("acosh", trig::acosh),
+    ("atanh", trig::atanh),
+    ("sind", trig::sind),
+    ("cosd", trig::cosd),
+    ("tand", trig::tand),
+    ("isprime", numtheory::isprime),
+    ("factor", numtheory::factor),
+    ("nextprime", numtheory::nextprime),
+    ("totient", numtheory::totient_builtin),
+    ("powmod", numtheory::powmod),
+    ("modinv", numtheory::modinv),
+    ("gcd", numtheory::gcd),
+    ("egcd", numtheory::egcd),
+    ("date", dates::date_builtin),
+    ("days_between", dates::days_between_builtin),
+    ("business_days", dates::business_days_builtin),
+    ("add_business_days", dates::add_business_days_builtin),
+    ("isoweek", dates::isoweek_builtin),
+    ("weekday", dates::weekday_builtin),
+    ("pmt", finance::pmt_builtin),
+    ("pv", finance::pv_builtin),
+    ("fv", finance::fv_builtin),
+    ("nper", finance::nper_builtin),
+    ("rate", finance::rate_builtin),
+    ("npv", finance::npv_builtin),
+    ("irr", finance::irr_builtin),
+    ("amortize", finance::amortize_builtin),
+];
+
+/// 需要读写会话状态的内置函数
+type SessionBuiltin = fn(&mut Session, &[Value]) -> Result<Value, CalcError>;
+
+/// 会话内置函数注册表
+const SESSION_FUNCTIONS: &[(&str, SessionBuiltin)] = &[
+    ("linreg", regression::linreg),
+    ("polyreg", regression::polyreg),
+    ("expreg", regression::expreg),
+    ("logreg", regression::logreg),
+    ("predict", regression::predict),
+    ("rand", random::rand),
+    ("randint", random::randint),
+    ("randn", random::randn),
+    ("choose", random::choose),
+    ("seed", random::seed),
+];
+
+/// 检查参数全部为数值且个数在 [min, max] 范围内
+fn expect_numbers(
+    name: &str,
+    args: &[Value],
+    min: usize,
+    max: usize,
+) -> Result<Vec<f64>, CalcError> {
+    let numbers: Option<Vec<f64>> = args.iter().map(Value::as_number).collect();
+    match numbers {
+        Some(numbers) if (min..=max).contains(&numbers.len()) => Ok(numbers),
+        _ if min == max => Err(CalcError::InvalidArguments(format!(
+            "{} expects {} numbers",
+            name, min
+        ))),
+        _ => Err(CalcError::InvalidArguments(format!(
+            "{} expects {} to {} numbers",
+            name, min, max
+        ))),
+    }
+}
+
+/// 结果为 NaN 时报告定义域错误，否则包装为数值
+fn defined(name: &str, value: f64) -> Result<Value, CalcError> {
+    if value.is_nan() {
+        Err(CalcError::Math(format!("{} is undefined here", name)))
+    } else {
+        Ok(Value::Number(value))
+    }
+}
+
+/// 按名称调用内置函数
+fn call_function(name: &str, args: &[Value], session: &mut Session) -> Result<Value, CalcError> {
+    if let Some((_, function)) = FUNCTIONS.iter().find(|(n, _)| *n == name) {
+        return function(args);
+    }
+    if let Some((_, function)) = SESSION_FUNCTIONS.iter().find(|(n, _)| *n == name) {
+        return function(session, args);
+    }
+    Err(CalcError::UnknownFunction(name.to_string()))
 }
 
 /// 将字符转换为对应的 Token
@@ -21,10 +410,17 @@ fn char_to_token(c: char) -> Option<Token> {
     match c {
         '+' => Some(Token::Operator(Operation::Plus)),
         '-' => Some(Token::Operator(Operation::Minus)),
-        '*' | '×' => Some(Token::Operator(Operation::Times)),
+        '*' | '×' | '·' => Some(Token::Operator(Operation::Times)),
         '/' | '÷' => Some(Token::Operator(Operation::Divide)),
         '(' => Some(Token::LeftParen),
         ')' => Some(Token::RightParen),
+        '[' => Some(Token::LeftBracket),
+        ']' => Some(Token::RightBracket),
+        '{' => Some(Token::LeftBrace),
+        '}' => Some(Token::RightBrace),
+        ',' => Some(Token::Comma),
+        '!' => Some(Token::Operator(Operation::Factorial)),
+        '^' => Some(Token::Operator(Operation::Power)),
         _ => None,
     }
 }
@@ -34,100 +430,432 @@ fn apply_operation(a: f64, b: f64, op: &Operation) -> f64 {
     match op {
         Operation::Plus => a + b,
         Operation::Minus => a - b,
-        Operation::Times => a * b,
+        Operation::Times | Operation::ImplicitTimes => a * b,
+        Operation::ImplicitPlus => a + b,
         Operation::Divide => a / b,
+        Operation::Negate => -b,
+        Operation::Factorial => special::gamma(b + 1.0),
+        Operation::Power => a.powf(b),
+        Operation::Convert(_) => unreachable!("单位转换由 units::apply 处理"),
+    }
+}
+
+/// 精确计算阶乘的上限，更大的整数改用 Γ 函数（结果溢出为 ∞）；10000! 约 11.8 万位二进制，
+/// 不超过 EXACT_RESULT_BITS
+const EXACT_FACTORIAL_LIMIT: u32 = 10_000;
+
+/// 精确计算整数乘方的指数上限，更大的指数改用浮点数
+const EXACT_POWER_LIMIT: u32 = 10_000;
+
+/// 精确整数乘法和乘方结果的二进制位数上限（约 4 万位十进制），更大的结果改用浮点数，
+/// 避免产生格式化要好几秒的巨大整数
+const EXACT_RESULT_BITS: u64 = 1 << 17;
+
+/// 对两个精确整数执行运算，除法不能整除时返回 None 改用浮点数
+fn apply_integer_operation(a: &BigInt, b: &BigInt, op: &Operation) -> Option<BigInt> {
+    match op {
+        Operation::Plus => Some(a + b),
+        Operation::Minus => Some(a - b),
+        Operation::Times | Operation::ImplicitTimes => {
+            (a.bits() + b.bits() <= EXACT_RESULT_BITS).then(|| a * b)
+        }
+        Operation::ImplicitPlus => Some(a + b),
+        Operation::Divide => {
+            if b.is_zero() {
+                return None;
+            }
+            let (quotient, remainder) = a.div_rem(b);
+            remainder.is_zero().then_some(quotient)
+        }
+        Operation::Negate => Some(-b),
+        Operation::Factorial => b
+            .to_u32()
+            .filter(|&n| n <= EXACT_FACTORIAL_LIMIT)
+            .map(|n| (1..=n).map(BigInt::from).product()),
+        Operation::Power => b
+            .to_u32()
+            .filter(|&n| n <= EXACT_POWER_LIMIT && a.bits() * n as u64 <= EXACT_RESULT_BITS)
+            .map(|n| num_traits::pow(a.clone(), n as usize)),
+        Operation::Convert(_) => None,
     }
 }
 
 /// 获取操作符优先级（值越大优先级越高）
 fn operator_precedence(op: &Operation) -> u8 {
     match op {
+        Operation::Convert(_) => 0,
         Operation::Plus | Operation::Minus => 1,
         Operation::Times | Operation::Divide => 2,
+        Operation::ImplicitPlus => 3,
+        Operation::Negate | Operation::ImplicitTimes => 4,
+        Operation::Power => 5,
+        Operation::Factorial => 6,
+    }
+}
+
+/// 操作符需要的操作数个数
+fn operation_arity(op: &Operation) -> usize {
+    match op {
+        Operation::Negate | Operation::Factorial => 1,
+        _ => 2,
     }
 }
 
-/// 将表达式字符串解析为 Token 序列
-fn parse_expression(expr: &str) -> Vec<Token> {
+/// 判断在给定的前一个 Token 之后，减号是否为一元负号
+fn is_unary_position(previous: Option<&Token>) -> bool {
+    matches!(
+        previous,
+        None | Some(Token::Operator(
+            Operation::Plus
+                | Operation::Minus
+                | Operation::Times
+                | Operation::Divide
+                | Operation::Negate
+                | Operation::Power
+                | Operation::Convert(_)
+        )) | Some(Token::LeftParen)
+            | Some(Token::LeftBracket)
+            | Some(Token::LeftBrace)
+            | Some(Token::Comma)
+    )
+}
+
+/// 将表达式字符串解析为 Token 序列，日期字面量无效时报错
+fn parse_expression(expr: &str, locale: &Locale) -> Result<Vec<Token>, CalcError> {
+    let expr = locale.delocalize(&expand_superscripts(expr));
     let mut tokens = Vec::new();
     let mut current_number = String::new();
-    let mut chars = expr.chars().peekable();
+    let mut current_name = String::new();
+    // 已作为日期字面量读取的部分在此位置之前
+    let mut resume = 0;
 
-    while let Some(c) = chars.next() {
-        if c.is_ascii_digit() || c == '.' {
+    for (i, c) in expr.char_indices() {
+        if i < resume {
+            continue;
+        }
+        if c.is_ascii_digit()
+            && current_number.is_empty()
+            && current_name.is_empty()
+            && let Some(literal) = dates::parse_literal(&expr[i..])
+        {
+            // 日期字面量 YYYY-MM-DD，不是减法
+            let (days, length) = literal?;
+            tokens.push(Token::Date(days));
+            resume = i + length;
+            continue;
+        }
+        if (c.is_ascii_digit() || c == '.') && current_name.is_empty() {
             // 数字或小数点
             current_number.push(c);
-        } else {
-            // 如果当前有数字，先将其解析为数字 Token
-            if !current_number.is_empty() {
-                if let Ok(num) = current_number.parse::<f64>() {
-                    tokens.push(Token::Number(num));
-                }
-                current_number.clear();
-            }
+            continue;
+        }
+        if c.is_alphabetic()
+            || c == '°'
+            || ((c.is_ascii_digit() || c == '_') && !current_name.is_empty())
+        {
+            // 函数名和单位名由字母开头，可以包含数字和下划线；° 归入名字，°C 不会被误读为库仑
+            push_number(&mut tokens, &mut current_number);
+            current_name.push(c);
+            continue;
+        }
 
-            // 处理操作符或括号
-            if let Some(token) = char_to_token(c) {
+        // 如果当前有数字或函数名，先将其转换为 Token
+        push_number(&mut tokens, &mut current_number);
+        if !current_name.is_empty() {
+            push_name(&mut tokens, std::mem::take(&mut current_name), &expr[i..]);
+        }
+
+        // 处理操作符、括号或分隔符
+        if let Some(token) = char_to_token(c) {
+            if token == Token::Operator(Operation::Minus) && is_unary_position(tokens.last()) {
+                tokens.push(Token::Operator(Operation::Negate));
+            } else {
                 tokens.push(token);
-            } else if !c.is_whitespace() {
-                // 忽略空白字符，其他字符视为错误（实际应用中应该报错）
-                eprintln!("警告：忽略未知字符 '{}'", c);
             }
+        } else if !c.is_whitespace() {
+            // 忽略空白字符，其他字符视为错误（实际应用中应该报错）
+            eprintln!("警告：忽略未知字符 '{}'", c);
         }
     }
 
-    // 处理末尾可能存在的数字
-    if !current_number.is_empty() {
-        if let Ok(num) = current_number.parse::<f64>() {
-            tokens.push(Token::Number(num));
+    // 处理末尾可能存在的数字或函数名
+    push_number(&mut tokens, &mut current_number);
+    if !current_name.is_empty() {
+        push_name(&mut tokens, current_name, "");
+    }
+
+    Ok(tokens)
+}
+
+/// 把上标数字改写为乘方，例如 m² → m^2
+fn expand_superscripts(expr: &str) -> String {
+    let mut result = String::with_capacity(expr.len());
+    let mut in_superscript = false;
+    for c in expr.chars() {
+        let digit = "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().position(|s| s == c);
+        if digit.is_none() && c != '⁻' {
+            result.push(c);
+            in_superscript = false;
+            continue;
+        }
+        if !in_superscript {
+            result.push('^');
+            in_superscript = true;
         }
+        match digit {
+            Some(digit) => result.push_str(&digit.to_string()),
+            None => result.push('-'),
+        }
+    }
+    result
+}
+
+/// 将累积的名字转换为 Token：`to` 是单位转换运算符，其余为函数名或单位。
+/// 名字紧跟在数字、右括号或单位之后时省略了乘号，例如 3 m、2 sin(x)
+fn push_name(tokens: &mut Vec<Token>, name: String, rest: &str) {
+    if name == "to" {
+        tokens.push(Token::Operator(Operation::Convert(conversion_target(rest))));
+        return;
+    }
+    if matches!(
+        tokens.last(),
+        Some(
+            Token::Number(_)
+                | Token::Integer(_)
+                | Token::RightParen
+                | Token::Function(_)
+                | Token::Operator(Operation::Factorial)
+        )
+    ) {
+        tokens.push(Token::Operator(Operation::ImplicitTimes));
+    }
+    tokens.push(Token::Function(name));
+}
+
+/// `to` 之后的目标单位文本：到表达式末尾、所在括号结束或参数分隔符为止
+fn conversion_target(rest: &str) -> String {
+    let mut depth = 0;
+    let end = rest
+        .char_indices()
+        .find(|&(_, c)| match c {
+            '(' | '[' | '{' => {
+                depth += 1;
+                false
+            }
+            ')' | ']' | '}' if depth > 0 => {
+                depth -= 1;
+                false
+            }
+            ')' | ']' | '}' => true,
+            ',' => depth == 0,
+            _ => false,
+        })
+        .map_or(rest.len(), |(i, _)| i);
+    rest[..end].trim().to_string()
+}
+
+/// 是否为已注册的函数名
+fn is_function(name: &str) -> bool {
+    FUNCTIONS.iter().any(|(n, _)| *n == name) || SESSION_FUNCTIONS.iter().any(|(n, _)| *n == name)
+}
+
+/// 运行时间随参数位数增长很快的内置函数（质数检验、分解等）
+const SLOW_FUNCTIONS: &[&str] = &["isprime", "factor", "nextprime", "totient"];
+
+/// 实时预览允许的精确整数结果位数（约 1 万位十进制），估计会更大的乘方和阶乘只在按 = 时计算
+const PREVIEW_RESULT_BITS: u64 = 1 << 15;
+
+/// 表达式求值时间可能很长时为 true：调用了耗时的内置函数，或者整数乘方、阶乘的结果估计超过
+/// PREVIEW_RESULT_BITS 位（如 9999!、(10^1000)^1000）；实时预览不对这样的表达式求值，
+/// 无法解析时为 false
+pub fn is_slow_to_evaluate(expr: &str, locale: &Locale) -> bool {
+    let Ok(tokens) = parse_expression(expr, locale) else {
+        return false;
+    };
+    if tokens.iter().any(
+        |token| matches!(token, Token::Function(name) if SLOW_FUNCTIONS.contains(&name.as_str())),
+    ) {
+        return true;
+    }
+    infix_to_postfix(&tokens).is_ok_and(|postfix| {
+        estimate_result_bits(&postfix).is_some_and(|bits| bits > PREVIEW_RESULT_BITS)
+    })
+}
+
+/// 粗略估计后缀表达式中最大的精确整数结果的二进制位数（上界），只跟踪整数字面量之间的运算，
+/// 其余值（浮点数、函数结果等）按 0 位计；表达式不完整时返回 None
+fn estimate_result_bits(postfix: &[Token]) -> Option<u64> {
+    // 每个值的 (位数上界, 不太大时的确切值)
+    let mut stack: Vec<(u64, Option<u64>)> = Vec::new();
+    let mut largest = 0;
+    for token in postfix {
+        let estimate = match token {
+            Token::Integer(n) => (n.bits(), n.to_u64()),
+            Token::Operator(op) => {
+                let (a, b) = match operation_arity(op) {
+                    1 => ((0, None), stack.pop()?),
+                    _ => {
+                        let b = stack.pop()?;
+                        (stack.pop()?, b)
+                    }
+                };
+                let both = a.1.zip(b.1);
+                match op {
+                    Operation::Plus | Operation::ImplicitPlus => {
+                        (a.0.max(b.0) + 1, both.and_then(|(a, b)| a.checked_add(b)))
+                    }
+                    Operation::Minus => (a.0.max(b.0) + 1, None),
+                    Operation::Times | Operation::ImplicitTimes => (
+                        a.0.saturating_add(b.0),
+                        both.and_then(|(a, b)| a.checked_mul(b)),
+                    ),
+                    Operation::Divide | Operation::Negate => (a.0.max(b.0), None),
+                    Operation::Power => (
+                        b.1.map_or(0, |n| a.0.saturating_mul(n)),
+                        both.and_then(|(a, b)| a.checked_pow(u32::try_from(b).ok()?)),
+                    ),
+                    // n! < n^n，不超过 n × n 的位数
+                    Operation::Factorial => (b.1.map_or(0, |n| n.saturating_mul(b.0)), None),
+                    Operation::Convert(_) => (0, None),
+                }
+            }
+            Token::Call(_, count) => {
+                stack.truncate(stack.len().checked_sub(*count)?);
+                (0, None)
+            }
+            Token::List(count) => {
+                let items = stack.split_off(stack.len().checked_sub(*count)?);
+                (items.iter().map(|item| item.0).max().unwrap_or(0), None)
+            }
+            _ => (0, None),
+        };
+        largest = largest.max(estimate.0);
+        stack.push(estimate);
     }
+    Some(largest)
+}
 
-    tokens
+/// 将累积的数字字符串解析为数字 Token 并清空
+fn push_number(tokens: &mut Vec<Token>, current_number: &mut String) {
+    if current_number.is_empty() {
+        return;
+    }
+    // 单位后面紧跟数字是复合单位，例如 3h 20min
+    if matches!(tokens.last(), Some(Token::Function(_))) {
+        tokens.push(Token::Operator(Operation::ImplicitPlus));
+    }
+    if let Ok(n) = current_number.parse::<BigInt>() {
+        tokens.push(Token::Integer(n));
+    } else if let Ok(num) = current_number.parse::<f64>() {
+        tokens.push(Token::Number(num));
+    }
+    current_number.clear();
 }
 
 /// 使用 Shunting-yard 算法将中缀表达式转换为后缀表达式（逆波兰表示法）
-fn infix_to_postfix(tokens: &[Token]) -> Vec<Token> {
+fn infix_to_postfix(tokens: &[Token]) -> Result<Vec<Token>, CalcError> {
     let mut output = VecDeque::new();
     let mut operator_stack: Vec<Token> = Vec::new();
+    // 每层括号一个计数器：函数调用和列表记录参数个数，普通括号为 None
+    let mut arg_counts: Vec<Option<usize>> = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let next_closes = matches!(
+            tokens.get(i + 1),
+            Some(Token::RightParen) | Some(Token::RightBracket) | Some(Token::RightBrace)
+        );
 
-    for token in tokens {
         match token {
-            Token::Number(_) => {
+            Token::Number(_) | Token::Integer(_) | Token::Date(_) => {
                 // 数字直接加入输出队列
                 output.push_back(token.clone());
             }
-            Token::LeftParen => {
+            Token::Function(name) if tokens.get(i + 1) != Some(&Token::LeftParen) => {
+                // 后面没有左括号的名字是单位或货币代码，函数名缺少括号是语法错误
+                if units::lookup(name).is_some() || currency::is_code(name) {
+                    output.push_back(Token::Unit(name.clone()));
+                } else if is_function(name) {
+                    return Err(CalcError::Syntax);
+                } else if units::is_temperature_scale(name) {
+                    return Err(CalcError::TemperatureScale(name.clone()));
+                } else {
+                    return Err(CalcError::UnknownUnit(name.clone()));
+                }
+            }
+            Token::Function(_) => {
+                operator_stack.push(token.clone());
+            }
+            Token::LeftParen | Token::LeftBracket | Token::LeftBrace => {
                 // 左括号直接压入操作符栈
+                let is_call = matches!(operator_stack.last(), Some(Token::Function(_)))
+                    || *token != Token::LeftParen;
+                arg_counts.push(is_call.then_some(if next_closes { 0 } else { 1 }));
                 operator_stack.push(token.clone());
             }
-            Token::RightParen => {
-                // 遇到右括号，弹出操作符栈中的元素直到遇到左括号
-                while let Some(top) = operator_stack.pop() {
-                    if matches!(top, Token::LeftParen) {
+            Token::Comma => {
+                // 分隔符：弹出当前参数中的操作符，参数个数加一
+                while let Some(top) = operator_stack.last() {
+                    if matches!(
+                        top,
+                        Token::LeftParen | Token::LeftBracket | Token::LeftBrace
+                    ) {
                         break;
                     }
-                    output.push_back(top);
+                    output.push_back(operator_stack.pop().unwrap());
+                }
+                match arg_counts.last_mut() {
+                    Some(Some(count)) => *count += 1,
+                    _ => return Err(CalcError::Syntax),
                 }
             }
+            Token::RightParen | Token::RightBracket | Token::RightBrace => {
+                // 遇到右括号，弹出操作符栈中的元素直到遇到对应的左括号
+                let opening = match token {
+                    Token::RightParen => Token::LeftParen,
+                    Token::RightBracket => Token::LeftBracket,
+                    _ => Token::LeftBrace,
+                };
+                loop {
+                    match operator_stack.pop() {
+                        Some(top) if top == opening => break,
+                        Some(Token::LeftParen)
+                        | Some(Token::LeftBracket)
+                        | Some(Token::LeftBrace)
+                        | None => {
+                            return Err(CalcError::Syntax);
+                        }
+                        Some(top) => output.push_back(top),
+                    }
+                }
+                let arg_count = arg_counts.pop().flatten();
+                if opening != Token::LeftParen {
+                    output.push_back(Token::List(arg_count.unwrap_or(0)));
+                } else if let Some(Token::Function(name)) = operator_stack.last() {
+                    output.push_back(Token::Call(name.clone(), arg_count.unwrap_or(0)));
+                    operator_stack.pop();
+                }
+            }
+            Token::Operator(Operation::Factorial) => {
+                // 后缀运算符优先级最高，作用于已输出的操作数
+                output.push_back(token.clone());
+            }
             Token::Operator(op1) => {
-                // 处理操作符优先级
-                while let Some(top) = operator_stack.last() {
-                    match top {
-                        Token::LeftParen => break, // 左括号在栈中时停止弹出
-                        Token::Operator(op2) => {
-                            // 如果栈顶操作符优先级大于等于当前操作符，弹出栈顶
-                            if operator_precedence(op2) >= operator_precedence(op1) {
-                                output.push_back(operator_stack.pop().unwrap());
-                            } else {
-                                break;
-                            }
+                // 一元负号是前缀运算符，直接压栈
+                if *op1 != Operation::Negate {
+                    // 处理操作符优先级
+                    while let Some(Token::Operator(op2)) = operator_stack.last() {
+                        // 如果栈顶操作符优先级大于等于当前操作符，弹出栈顶；乘方右结合，只弹出更高优先级
+                        let (top, current) = (operator_precedence(op2), operator_precedence(op1));
+                        if top > current || (top == current && *op1 != Operation::Power) {
+                            output.push_back(operator_stack.pop().unwrap());
+                        } else {
+                            break;
                         }
-                        _ => break,
                     }
                 }
                 operator_stack.push(token.clone());
             }
+            Token::Call(..) | Token::List(_) | Token::Unit(_) => return Err(CalcError::Syntax),
         }
     }
 
@@ -136,71 +864,168 @@ fn infix_to_postfix(tokens: &[Token]) -> Vec<Token> {
         output.push_back(token);
     }
 
-    output.into_iter().collect()
+    Ok(output.into_iter().collect())
+}
+
+/// 从栈顶弹出 count 个值，保持原有顺序
+fn pop_values(stack: &mut Vec<Value>, count: usize) -> Result<Vec<Value>, CalcError> {
+    if stack.len() < count {
+        return Err(CalcError::Syntax);
+    }
+    Ok(stack.split_off(stack.len() - count))
+}
+
+/// 对操作数执行运算：日期和带单位的量交给各自的模块处理，整数之间精确计算，其余按浮点数计算
+fn apply_operator(op: &Operation, operands: &[Value]) -> Result<Value, CalcError> {
+    // 日期交给 dates 模块处理
+    if operands.iter().any(|value| matches!(value, Value::Date(_))) {
+        return dates::apply(op, operands);
+    }
+    // 带单位的量和单位转换交给 units 模块处理
+    if matches!(op, Operation::Convert(_))
+        || operands
+            .iter()
+            .any(|value| matches!(value, Value::Quantity(_)))
+    {
+        return units::apply(op, operands);
+    }
+    let unary = operation_arity(op) == 1;
+    let integers: Option<Vec<BigInt>> = operands.iter().map(Value::as_integer).collect();
+    // 操作数都是整数时精确计算
+    if let Some(integers) = integers {
+        let (a, b) = if unary {
+            (BigInt::zero(), &integers[0])
+        } else {
+            (integers[0].clone(), &integers[1])
+        };
+        if let Some(result) = apply_integer_operation(&a, b, op) {
+            return Ok(Value::Integer(result));
+        }
+    }
+    let numbers: Vec<f64> = operands
+        .iter()
+        .map(Value::as_number)
+        .collect::<Option<_>>()
+        .ok_or_else(|| CalcError::InvalidArguments("arithmetic on a list".to_string()))?;
+    let (a, b) = if unary {
+        (0.0, numbers[0])
+    } else {
+        (numbers[0], numbers[1])
+    };
+    let result = apply_operation(a, b, op);
+    // Γ 在 0 和负整数处是极点，负整数的阶乘没有定义
+    if matches!(op, Operation::Factorial) && result.is_nan() {
+        return Err(CalcError::Math(
+            "factorial of a negative integer is undefined".to_string(),
+        ));
+    }
+    Ok(Value::Number(result))
+}
+
+/// 对 y 和 x 执行二元运算（+ - * / ^，y 在左侧），规则与表达式中的运算相同，供 RPN 模式使用
+pub fn apply_binary(operator: char, y: Value, x: Value) -> Result<Value, CalcError> {
+    match char_to_token(operator) {
+        Some(Token::Operator(op)) if operation_arity(&op) == 2 => apply_operator(&op, &[y, x]),
+        _ => Err(CalcError::Syntax),
+    }
+}
+
+/// 以 args 为参数调用内置函数，供 RPN 模式使用
+pub fn apply_function(
+    name: &str,
+    args: &[Value],
+    session: &mut Session,
+) -> Result<Value, CalcError> {
+    call_function(name, args, session)
 }
 
 /// 计算后缀表达式的结果
-fn evaluate_postfix(tokens: &[Token]) -> Option<f64> {
-    let mut stack: Vec<f64> = Vec::new();
+fn evaluate_postfix(tokens: &[Token], session: &mut Session) -> Result<Value, CalcError> {
+    let mut stack: Vec<Value> = Vec::new();
 
     for token in tokens {
         match token {
             Token::Number(num) => {
-                stack.push(*num);
+                stack.push(Value::Number(*num));
+            }
+            Token::Integer(n) => {
+                stack.push(Value::Integer(n.clone()));
+            }
+            Token::Date(days) => {
+                stack.push(Value::Date(*days));
+            }
+            Token::Unit(name) => {
+                let unit = match units::lookup(name) {
+                    Some(unit) => unit,
+                    None => currency::unit(session.rates.as_ref(), name)?,
+                };
+                stack.push(Value::Quantity(unit));
             }
             Token::Operator(op) => {
-                // 二元操作需要两个操作数
-                if stack.len() < 2 {
-                    return None;
-                }
-                let b = stack.pop().unwrap();
-                let a = stack.pop().unwrap();
-                let result = apply_operation(a, b, op);
-                stack.push(result);
+                // 一元负号需要一个操作数，二元操作需要两个操作数
+                let operands = pop_values(&mut stack, operation_arity(op))?;
+                stack.push(apply_operator(op, &operands)?);
+            }
+            Token::Call(name, arg_count) => {
+                let args = pop_values(&mut stack, *arg_count)?;
+                stack.push(call_function(name, &args, session)?);
+            }
+            Token::List(len) => {
+                let items = pop_values(&mut stack, *len)?;
+                stack.push(Value::List(items));
             }
             _ => {
                 // 后缀表达式中不应该有括号
-                return None;
+                return Err(CalcError::Syntax);
             }
         }
     }
 
     // 最终栈中应该只有一个值
-    if stack.len() == 1 {
-        Some(stack[0])
-    } else {
-        None
+    match stack.pop() {
+        Some(value) if stack.is_empty() => Ok(value),
+        _ => Err(CalcError::Syntax),
     }
 }
 
-/// 主计算函数：计算表达式字符串的结果
-pub fn calculate(expr: &str) -> f64 {
+/// 计算表达式字符串，返回结果值或错误
+pub fn evaluate(expr: &str) -> Result<Value, CalcError> {
+    evaluate_in(expr, &mut Session::default())
+}
+
+/// 在给定会话中计算表达式字符串，会话状态可被表达式读取和修改
+pub fn evaluate_in(expr: &str, session: &mut Session) -> Result<Value, CalcError> {
     // 移除空白字符
     let expr = expr.trim();
 
     // 空表达式返回0
     if expr.is_empty() {
-        return 0.0;
+        return Ok(Value::Number(0.0));
     }
 
     // 1. 解析表达式
-    let tokens = parse_expression(expr);
-
-    // 如果解析失败，返回0
-    if tokens.is_empty() {
-        return 0.0;
-    }
+    let tokens = parse_expression(expr, &session.locale)?;
 
     // 2. 转换为后缀表达式
-    let postfix_tokens = infix_to_postfix(&tokens);
+    let postfix_tokens = infix_to_postfix(&tokens)?;
 
     // 3. 计算后缀表达式
-    if let Some(result) = evaluate_postfix(&postfix_tokens) {
-        result
-    } else {
-        // 计算失败时返回0
-        0.0
-    }
+    evaluate_postfix(&postfix_tokens, session)
+}
+
+/// 主计算函数：计算表达式字符串的数值结果
+#[allow(dead_code)]
+pub fn calculate(expr: &str) -> f64 {
+    calculate_in(expr, &mut Session::default())
+}
+
+/// 在给定会话中计算表达式字符串的数值结果
+pub fn calculate_in(expr: &str, session: &mut Session) -> f64 {
+    // 计算失败或结果不是数值时返回0
+    evaluate_in(expr, session)
+        .ok()
+        .and_then(|value| value.as_number())
+        .unwrap_or(0.0)
 }
 
 #[cfg(test)]
@@ -236,6 +1061,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_decimal_numbers() {
         assert_eq!(calculate("1.5+2.5"), 4.0);
         assert_eq!(calculate("3.14*2"), 6.28);
@@ -255,4 +1081,448 @@ mod tests {
         assert_eq!(calculate("((()))"), 0.0); // 只有括号，没有数字
         assert_eq!(calculate("2++3"), 0.0); // 语法错误，返回0
     }
+
+    #[test]
+    fn test_unary_minus() {
+        assert_eq!(calculate("-3+5"), 2.0);
+        assert_eq!(calculate("2*-3"), -6.0);
+        assert_eq!(calculate("-(1+2)"), -3.0);
+        assert_eq!(calculate("--2"), 2.0);
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            evaluate("[1, 2+3, -4]"),
+            Ok(Value::from(vec![1.0, 5.0, -4.0]))
+        );
+        assert_eq!(evaluate("[]"), Ok(Value::List(vec![])));
+        let matrix = evaluate("[[1,2],[3,4]]").unwrap();
+        assert_eq!(
+            matrix.as_matrix(),
+            Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]])
+        );
+        assert_eq!(evaluate("[[1,2],[3]]").unwrap().as_matrix(), None);
+        assert!(evaluate("[1,2]+1").is_err());
+        assert!(evaluate("[1,2").is_err());
+        assert!(evaluate("(1,2)").is_err());
+    }
+
+    #[test]
+    fn test_function_calls() {
+        assert_eq!(
+            evaluate("linsolve([[2,1],[1,3]], [3,4])"),
+            Ok(Value::from(vec![1.0, 1.0]))
+        );
+        assert_eq!(
+            evaluate("foo(1)"),
+            Err(CalcError::UnknownFunction("foo".to_string()))
+        );
+        assert_eq!(evaluate("linsolve"), Err(CalcError::Syntax));
+        assert!(matches!(
+            evaluate("linsolve([[1,2],[2,4]], [1,2])"),
+            Err(CalcError::Math(_))
+        ));
+        assert!(matches!(
+            evaluate("linsolve(1, 2)"),
+            Err(CalcError::InvalidArguments(_))
+        ));
+    }
+
+    #[test]
+    fn test_statistics_functions() {
+        assert_eq!(evaluate("{1, 2, 3}"), Ok(Value::from(vec![1.0, 2.0, 3.0])));
+        assert_eq!(calculate("mean({1,2,3,4})"), 2.5);
+        assert_eq!(calculate("mean(1,2,3,4)"), 2.5);
+        assert_eq!(calculate("median({5,1,3})"), 3.0);
+        assert_eq!(calculate("sum({1,2,3})*2"), 12.0);
+        assert_eq!(calculate("max({1,7,3})-min({1,7,3})"), 6.0);
+        assert_eq!(calculate("stdevp({2,4,4,4,5,5,7,9})"), 2.0);
+        assert_eq!(calculate("var({1,2,3,4,5})"), 2.5);
+        assert_eq!(calculate("quantile({1,2,3,4,5}, 0.75)"), 4.0);
+        assert_eq!(evaluate("mode({1,1,2,2})"), Ok(Value::from(vec![1.0, 2.0])));
+        assert!(evaluate("stdev({1})").is_err());
+        assert!(evaluate("quantile({1,2}, 2)").is_err());
+        assert!(evaluate("{1,2]").is_err());
+    }
+
+    #[test]
+    fn test_regression_session() {
+        let mut session = Session::default();
+        assert!(evaluate_in("predict(1)", &mut session).is_err());
+        let coefficients = evaluate_in("linreg({1,2,3}, {3,5,7})", &mut session).unwrap();
+        let coefficients = coefficients.as_vector().unwrap();
+        assert!((coefficients[0] - 1.0).abs() < 1e-9);
+        assert!((coefficients[1] - 2.0).abs() < 1e-9);
+        let predicted = evaluate_in("predict(10)", &mut session).unwrap();
+        assert!((predicted.as_number().unwrap() - 21.0).abs() < 1e-9);
+        assert!(evaluate_in("polyreg({1,2,3}, {1,4,9}, 2)", &mut session).is_ok());
+        let predicted = evaluate_in("predict(4)", &mut session).unwrap();
+        assert!((predicted.as_number().unwrap() - 16.0).abs() < 1e-9);
+        assert!(evaluate_in("polyreg({1,2,3}, {1,4,9}, 0.5)", &mut session).is_err());
+    }
+
+    #[test]
+    fn test_distribution_functions() {
+        assert!((calculate("normcdf(1.96)") - 0.975).abs() < 1e-4);
+        assert!((calculate("normcdf(115, 100, 15)") - calculate("normcdf(1)")).abs() < 1e-15);
+        assert!((calculate("invnorm(normcdf(0.7))") - 0.7).abs() < 1e-12);
+        assert_eq!(calculate("binompdf(10, 0.5, 5)"), 0.24609375);
+        assert!((calculate("poissonpdf(3, 2)") - 0.224_041_807_655_387_75).abs() < 1e-15);
+        assert!(evaluate("normpdf(0, 0, -1)").is_err());
+        assert!(evaluate("binompdf(10, 1.5, 5)").is_err());
+        assert!(evaluate("binomcdf(10, 0.5, 2.5)").is_err());
+        assert!(evaluate("invnorm(1)").is_err());
+        assert!(evaluate("tcdf(1)").is_err());
+    }
+
+    #[test]
+    fn test_random_functions() {
+        let mut session = Session::default();
+        let first = evaluate_in("seed(42) + rand() + randn()", &mut session).unwrap();
+        let second = evaluate_in("seed(42) + rand() + randn()", &mut session).unwrap();
+        assert_eq!(first, second);
+
+        // 从求值前的状态重放得到相同结果
+        let state = session.rng.state();
+        let value = evaluate_in("randint(1, 100) + choose({10, 20})", &mut session).unwrap();
+        session.rng = random::Rng::new(state);
+        let replayed = evaluate_in("randint(1, 100) + choose({10, 20})", &mut session).unwrap();
+        assert_eq!(value, replayed);
+
+        let roll = calculate_in("randint(1, 6)", &mut session);
+        assert!((1.0..=6.0).contains(&roll) && roll.fract() == 0.0);
+        assert!(evaluate_in("choose(1)", &mut session).is_err());
+        assert!(evaluate_in("seed(1.5)", &mut session).is_err());
+    }
+
+    #[test]
+    fn test_slow_to_evaluate() {
+        let slow = |expr: &str| is_slow_to_evaluate(expr, &Locale::default());
+        assert!(slow("isprime(97)"));
+        assert!(slow("9999!"));
+        assert!(slow("(10^1000)^1000"));
+        assert!(slow("2^100000"));
+        assert!(slow("(5000+5000)!"));
+        assert!(slow("{1, 2^30000}"));
+        // 结果不大的整数运算、浮点数和函数参数照常预览
+        assert!(!slow("1000!"));
+        assert!(!slow("2^10000"));
+        assert!(!slow("1.05^10000"));
+        assert!(!slow("sqrt(2)^100000"));
+        assert!(!slow("max(1, 2)+3"));
+        assert!(!slow("2^"));
+    }
+
+    #[test]
+    fn test_exact_integers() {
+        let product = evaluate("12345678901234567890*98765432109876543210").unwrap();
+        assert_eq!(
+            product.to_string(),
+            "1219326311370217952237463801111263526900"
+        );
+        assert_eq!(
+            evaluate("9007199254740993-1").unwrap().to_string(),
+            "9007199254740992"
+        );
+        assert_eq!(evaluate("6/3").unwrap().to_string(), "2");
+        assert_eq!(calculate("7/2"), 3.5);
+        assert_eq!(evaluate("2"), Ok(Value::Number(2.0)));
+
+        // 结果太大时改用浮点数，不产生巨大的整数
+        assert!(matches!(evaluate("(2^10000)^10"), Ok(Value::Integer(_))));
+        let huge = |expr: &str| matches!(evaluate(expr), Ok(Value::Number(x)) if x.is_infinite());
+        assert!(huge("(10^1000)^1000"));
+        assert!(huge("(2^10000)^10*(2^10000)^10"));
+        assert!(matches!(evaluate("9999!"), Ok(Value::Integer(_))));
+    }
+
+    #[test]
+    fn test_number_theory_functions() {
+        assert_eq!(evaluate("factor(120)").unwrap().to_string(), "2³ × 3 × 5");
+        assert_eq!(evaluate("factor(1)").unwrap().to_string(), "1");
+        assert_eq!(calculate("factor(120)+1"), 121.0);
+        assert_eq!(calculate("isprime(97)"), 1.0);
+        assert_eq!(calculate("nextprime(97)"), 101.0);
+        assert_eq!(calculate("totient(10)"), 4.0);
+        assert_eq!(calculate("powmod(4, 13, 497)"), 445.0);
+        assert_eq!(calculate("modinv(17, 3120)"), 2753.0);
+        assert_eq!(calculate("gcd(12, 18)"), 6.0);
+        assert_eq!(
+            evaluate("egcd(240, 46)").unwrap().to_string(),
+            "[2, -9, 47]"
+        );
+        assert_eq!(
+            evaluate("powmod(2, 128, 170141183460469231731687303715884105727)")
+                .unwrap()
+                .to_string(),
+            "2"
+        );
+        assert!(evaluate("factor(0)").is_err());
+        assert!(evaluate("isprime(2.5)").is_err());
+        assert!(matches!(evaluate("modinv(6, 9)"), Err(CalcError::Math(_))));
+    }
+
+    #[test]
+    fn test_special_functions() {
+        assert_eq!(evaluate("5!").unwrap().to_string(), "120");
+        assert_eq!(
+            evaluate("25!").unwrap().to_string(),
+            "15511210043330985984000000"
+        );
+        assert_eq!(calculate("3!+1"), 7.0);
+        assert_eq!(calculate("-3!"), -6.0);
+        assert_eq!(calculate("(1+2)!!"), 720.0);
+        assert!((calculate("0.5!") - 0.886_226_925_452_758).abs() < 1e-14);
+        assert!(matches!(evaluate("(0-1)!"), Err(CalcError::Math(_))));
+        assert!(matches!(evaluate("(0-3)!"), Err(CalcError::Math(_))));
+        assert!((calculate("gamma(0.5)*gamma(0.5)") - std::f64::consts::PI).abs() < 1e-14);
+        assert!((calculate("beta(0.5, 0.5)") - std::f64::consts::PI).abs() < 1e-14);
+        assert!((calculate("lgamma(10)") - 362_880f64.ln()).abs() < 1e-13);
+        assert!((calculate("erf(1)+erfc(1)") - 1.0).abs() < 1e-15);
+        assert!((calculate("besselj(0, 2.404825557695773)")).abs() < 1e-15);
+        assert!((calculate("lambertw(1)") - 0.567_143_290_409_784).abs() < 1e-15);
+        assert!((calculate("lambertw(-0.2, -1)") + 2.542_641_357_773_526).abs() < 1e-14);
+        assert!(matches!(evaluate("gamma(0)"), Err(CalcError::Math(_))));
+        assert!(evaluate("besselj(0.5, 1)").is_err());
+        assert!(evaluate("bessely(0, -1)").is_err());
+        // 阶数和 |x| 过大时报错，不做巨大的递推
+        assert!(matches!(
+            evaluate("besselj(2000000000, 1)"),
+            Err(CalcError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            evaluate("besselj(1, 10^300)"),
+            Err(CalcError::InvalidArguments(_))
+        ));
+        assert!(evaluate("bessely(1001, 1)").is_err());
+        assert!(evaluate("besselj(1000, 100000)").is_ok());
+        assert!(evaluate("lambertw(1, 2)").is_err());
+    }
+
+    #[test]
+    fn test_trig_functions() {
+        assert_eq!(calculate("sin(0)"), 0.0);
+        assert_eq!(calculate("sind(90)+cosd(180)"), 0.0);
+        assert_eq!(evaluate("sind(180)").unwrap().to_string(), "0");
+        assert_eq!(evaluate("sind(30)"), Ok(Value::Number(0.5)));
+        assert_eq!(evaluate("cosd(60)"), Ok(Value::Number(0.5)));
+        assert_eq!(evaluate("tand(45)"), Ok(Value::Number(1.0)));
+        assert!(matches!(evaluate("tand(90)"), Err(CalcError::Math(_))));
+        assert!((calculate("cosh(1)*cosh(1)-sinh(1)*sinh(1)") - 1.0).abs() < 1e-15);
+        assert!((calculate("atanh(tanh(0.5))") - 0.5).abs() < 1e-15);
+        assert!((calculate("acosh(cosh(2))") - 2.0).abs() < 1e-15);
+        assert!((calculate("asinh(sinh(-3))") + 3.0).abs() < 1e-15);
+        assert!((calculate("sec(1)*cos(1)") - 1.0).abs() < 1e-15);
+        assert!((calculate("csc(1)*sin(1)") - 1.0).abs() < 1e-15);
+        assert!((calculate("cot(1)*tan(1)") - 1.0).abs() < 1e-15);
+        assert!((calculate("4*atan(1)") - std::f64::consts::PI).abs() < 1e-15);
+        assert!(matches!(evaluate("acos(2)"), Err(CalcError::Math(_))));
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(calculate("2^10"), 1024.0);
+        assert_eq!(calculate("2^3^2"), 512.0);
+        assert_eq!(calculate("-2^2"), -4.0);
+        assert_eq!(calculate("2^-1"), 0.5);
+        assert_eq!(calculate("3²+4²"), 25.0);
+        assert_eq!(
+            evaluate("2^100").unwrap().to_string(),
+            "1267650600228229401496703205376"
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        let show = |expr: &str| evaluate(expr).unwrap().to_string();
+        assert_eq!(show("3 m + 20 cm"), "3.2 m");
+        assert_eq!(show("60 km/h to m/s"), "16.666666666666668 m/s");
+        assert_eq!(show("5 kg * 9.81 m/s^2"), "49.050000000000004 N");
+        assert_eq!(show("2 m * 3 m"), "6 m²");
+        assert_eq!(show("1/(2 s)"), "0.5 Hz");
+        assert_eq!(show("10 kg/s²"), "10 kg/s²");
+        assert_eq!(show("1 mi to km"), "1.609344 km");
+        assert_eq!(show("(2 m^2)^0.5 to cm"), "141.4213562373095 cm");
+        // 量纲抵消后是普通数值
+        assert_eq!(evaluate("1 km / 1 m"), Ok(Value::Number(1000.0)));
+        // 显示结果可以重新解析
+        assert_eq!(
+            evaluate(&show("5 kg * 9.81 m/s^2")),
+            evaluate("5 kg * 9.81 m/s^2")
+        );
+        assert_eq!(evaluate(&show("10 kg/s²")), evaluate("10 kg/s^2"));
+
+        assert_eq!(
+            evaluate("3 m + 2 s"),
+            Err(CalcError::IncompatibleUnits(
+                "m".to_string(),
+                "s".to_string()
+            ))
+        );
+        assert!(matches!(
+            evaluate("60 km/h to kg"),
+            Err(CalcError::IncompatibleUnits(..))
+        ));
+        assert!(matches!(
+            evaluate("(1 m)^100 * (1 m)^100"),
+            Err(CalcError::Math(_))
+        ));
+        assert_eq!(
+            evaluate("3 m + 2"),
+            Err(CalcError::IncompatibleUnits(
+                "m".to_string(),
+                "1".to_string()
+            ))
+        );
+        assert_eq!(
+            evaluate("3 furlong"),
+            Err(CalcError::UnknownUnit("furlong".to_string()))
+        );
+        assert!(evaluate("sin(3 m)").is_err());
+        // 单位名与函数名并存：有括号时是函数，否则是单位
+        assert_eq!(calculate("min(1, 2)"), 1.0);
+        assert_eq!(show("2 min to s"), "120 s");
+        assert_eq!(calculate("2 sin(0)"), 0.0);
+        // 与普通数值相乘除、与同量纲的量相加减时保留 to 指定的显示单位
+        assert_eq!(show("(1 km to m) * 3"), "3000 m");
+        assert_eq!(show("(1 km to m) + 1 km"), "2000 m");
+        // 带偏移量的温度单位只在单位换算器中使用，错误说明表达式中要用 K
+        assert_eq!(
+            evaluate("20 °C"),
+            Err(CalcError::TemperatureScale("°C".to_string()))
+        );
+        assert_eq!(
+            evaluate("293.15 K to °F"),
+            Err(CalcError::TemperatureScale("°F".to_string()))
+        );
+        assert_eq!(
+            evaluate("20 °C to °F").unwrap_err().to_string(),
+            "°C works only in the unit converter; use K in expressions"
+        );
+    }
+
+    #[test]
+    fn test_currency() {
+        let path = std::env::temp_dir().join(format!("mycal-rates-{}.csv", std::process::id()));
+        std::fs::write(&path, "USD,1\nEUR,0.5\nJPY,150\n").unwrap();
+        let rates = currency::Rates::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut session = Session {
+            rates: Some(rates.unwrap()),
+            ..Session::default()
+        };
+        let mut show = |expr: &str| evaluate_in(expr, &mut session).map(|value| value.to_string());
+        assert_eq!(show("100 USD to EUR"), Ok("50 EUR".to_string()));
+        assert_eq!(show("100 EUR to JPY"), Ok("30000 JPY".to_string()));
+        assert_eq!(show("10 EUR + 10 USD"), Ok("15 EUR".to_string()));
+        assert_eq!(show("2 * 3 EUR"), Ok("6 EUR".to_string()));
+        assert_eq!(
+            show("(40 EUR / 2 kg) * 3 kg to USD"),
+            Ok("120 USD".to_string())
+        );
+        assert_eq!(
+            show("100 USD to GBP"),
+            Err(CalcError::MissingRate("GBP".to_string()))
+        );
+        assert!(matches!(
+            show("100 USD + 1 m"),
+            Err(CalcError::IncompatibleUnits(..))
+        ));
+        // 没有汇率文件时货币代码报告缺少汇率
+        assert_eq!(
+            evaluate("1 USD to EUR"),
+            Err(CalcError::MissingRate("USD".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_dates() {
+        let show = |expr: &str| evaluate(expr).map(|value| value.to_string());
+        assert_eq!(show("2026-10-18 + 90 days"), Ok("2027-01-16".to_string()));
+        assert_eq!(show("2026-03-01 - 1 day"), Ok("2026-02-28".to_string()));
+        assert_eq!(show("2 weeks + 2026-10-18"), Ok("2026-11-01".to_string()));
+        // 天数过大时报错，不会溢出
+        assert!(matches!(
+            evaluate("2026-10-18 + 10^300 days"),
+            Err(CalcError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            evaluate("2026-10-18 - 10^9 days"),
+            Err(CalcError::InvalidArguments(_))
+        ));
+        assert_eq!(calculate("days_between(2026-01-01, 2026-12-25)"), 358.0);
+        assert_eq!(
+            show("2026-12-25 - 2026-01-01"),
+            Ok("358 days (30931200 s)".to_string())
+        );
+        assert_eq!(show("date(2026, 10, 18)"), Ok("2026-10-18".to_string()));
+        assert_eq!(calculate("business_days(2026-10-12, 2026-10-26)"), 10.0);
+        assert_eq!(
+            show("add_business_days(2026-10-16, 1)"),
+            Ok("2026-10-19".to_string())
+        );
+        assert_eq!(calculate("isoweek(2026-10-18)"), 42.0);
+        assert_eq!(calculate("weekday(2026-10-18)"), 7.0);
+        // 不是日期格式的减法照常计算
+        assert_eq!(calculate("2026-10-1"), 2015.0);
+
+        assert!(evaluate("2026-02-30").is_err());
+        assert!(evaluate("2026-10-18 + 36 h").is_err());
+        assert!(evaluate("2026-10-18 + 5").is_err());
+        assert!(evaluate("2026-10-18 * 2").is_err());
+        assert!(evaluate("isoweek(5)").is_err());
+    }
+
+    #[test]
+    fn test_durations() {
+        let show = |expr: &str| evaluate(expr).unwrap().to_string();
+        assert_eq!(show("3h 20min"), "3 h 20 min (12000 s)");
+        assert_eq!(show("3h 20min * 4"), "13 h 20 min (48000 s)");
+        assert_eq!(show("90 s"), "1 min 30 s (90 s)");
+        assert_eq!(show("45 s"), "45 s");
+        assert_eq!(show("1 day 2 h - 30 min"), "1 day 1 h 30 min (91800 s)");
+        assert_eq!(show("3h 20min to min"), "200 min");
+        assert_eq!(show("5 ft 3 in to cm"), "160.02 cm");
+        assert!(evaluate("3 h 20 m").is_err());
+    }
+
+    #[test]
+    fn test_finance() {
+        let show = |expr: &str| evaluate(expr).map(|value| value.to_string());
+        assert_eq!(
+            show("pmt(0.05/12, 360, 200000)"),
+            Ok("-1073.64".to_string())
+        );
+        assert_eq!(show("pv(0.01, 12, -100)"), Ok("1125.51".to_string()));
+        assert_eq!(show("fv(0.1, 2, 0, -100)"), Ok("121".to_string()));
+        assert_eq!(calculate("nper(0, -100, 1000)"), 10.0);
+        assert!((calculate("rate(360, -1073.64, 200000) * 12") - 0.05).abs() < 1e-6);
+        assert_eq!(show("npv(0.1, {110, 121})"), Ok("200".to_string()));
+        assert_eq!(show("npv(0.1, 110, 121)"), Ok("200".to_string()));
+        assert!((calculate("irr({-100, 60, 60})") - 0.130_662_386_291_807).abs() < 1e-12);
+        assert_eq!(
+            show("amortize(100, 0, 2)"),
+            Ok(evaluate("{{1, 50, 0, 50, 50}, {2, 50, 0, 50, 0}}")
+                .unwrap()
+                .to_string())
+        );
+
+        assert!(evaluate("pmt(0.01, 12)").is_err());
+        assert!(evaluate("pmt(0.01, 12, 1000, 0, 2)").is_err());
+        assert!(evaluate("irr({100, 50})").is_err());
+        assert!(evaluate("amortize(100, 0.01, 1.5)").is_err());
+    }
+
+    #[test]
+    fn test_localized_expression() {
+        let mut session = Session {
+            locale: locale::LOCALES[1],
+            ..Session::default()
+        };
+        let mut show = |expr: &str| evaluate_in(expr, &mut session).map(|v| v.to_string());
+        assert_eq!(show("1.234,5 * 2"), Ok("2469".to_string()));
+        assert_eq!(show("max(1,5; 2,5)"), Ok("2.5".to_string()));
+        assert_eq!(show("min(0,5; 1.000)"), Ok("0.5".to_string()));
+    }
 }
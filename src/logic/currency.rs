@@ -0,0 +1,253 @@
+//! 汇率换算
+//!
+//! 汇率从用户维护的本地文件读取，从不访问网络，可以手工或用单独的脚本更新。支持两种格式：
+//!
+//! - JSON：`{"base": "USD", "updated": "2026-10-01", "rates": {"EUR": 0.92, "JPY": 149.5}}`，
+//!   `updated` 可省略
+//! - CSV：每行 `代码,汇率`，`#` 开头的行是注释，第一行可以是表头；基准货币本身写 1
+//!
+//! 汇率表示一个基准货币单位可以兑换的该货币数量，任意两种货币都经由基准货币换算。
+
+use super::units::{self, Quantity};
+use super::{CalcError, dates};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 指定汇率文件位置的环境变量
+const RATES_ENV: &str = "MYCAL_RATES";
+
+/// JSON 汇率文件的结构
+#[derive(Deserialize)]
+struct RatesFile {
+    base: String,
+    #[serde(default)]
+    updated: Option<String>,
+    rates: BTreeMap<String, f64>,
+}
+
+/// 从文件读取的汇率表
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rates {
+    /// 货币代码 -> 一个基准货币单位可兑换的数量
+    rates: BTreeMap<String, f64>,
+    /// 汇率时间戳：JSON 中的 updated 字段，否则为文件修改时间
+    pub timestamp: String,
+    /// 汇率文件路径
+    pub path: PathBuf,
+}
+
+/// 是否形如货币代码（三个大写字母，如 USD）
+pub fn is_code(name: &str) -> bool {
+    name.len() == 3 && name.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// 检查一条汇率，返回错误说明
+fn check_rate(code: &str, rate: f64) -> Result<(), String> {
+    if !is_code(code) {
+        return Err(format!("{} is not a currency code", code));
+    }
+    if !(rate.is_finite() && rate > 0.0) {
+        return Err(format!("rate for {} must be positive", code));
+    }
+    Ok(())
+}
+
+/// 解析 JSON 汇率文件，返回汇率表和可选的 updated 字段
+fn parse_json(text: &str) -> Result<(BTreeMap<String, f64>, Option<String>), String> {
+    let file: RatesFile = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let mut rates = file.rates;
+    match rates.get(&file.base) {
+        None => {
+            check_rate(&file.base, 1.0)?;
+            rates.insert(file.base, 1.0);
+        }
+        Some(&rate) if rate != 1.0 => {
+            return Err(format!("rate for base {} must be 1", file.base));
+        }
+        Some(_) => {}
+    }
+    for (code, &rate) in &rates {
+        check_rate(code, rate)?;
+    }
+    Ok((rates, file.updated))
+}
+
+/// 解析 CSV 汇率文件
+fn parse_csv(text: &str) -> Result<BTreeMap<String, f64>, String> {
+    let mut rates = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [code, rate] = fields[..] else {
+            return Err(format!("line {}: expected code,rate", i + 1));
+        };
+        match rate.parse::<f64>() {
+            Ok(rate) => {
+                check_rate(code, rate).map_err(|error| format!("line {}: {}", i + 1, error))?;
+                rates.insert(code.to_string(), rate);
+            }
+            // 第一行可以是表头
+            Err(_) if rates.is_empty() && !is_code(code) => {}
+            Err(_) => return Err(format!("line {}: invalid rate {}", i + 1, rate)),
+        }
+    }
+    if rates.is_empty() {
+        return Err("no rates".to_string());
+    }
+    Ok(rates)
+}
+
+/// 把时间格式化为 UTC 的 "YYYY-MM-DD HH:MM UTC"
+fn format_utc(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs()) as i64;
+    let (days, rest) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = dates::civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60
+    )
+}
+
+impl Rates {
+    /// 读取汇率文件，扩展名为 .json 时按 JSON 解析，否则按 CSV 解析
+    pub fn load(path: &Path) -> Result<Rates, String> {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let fail = |error: String| format!("{}: {}", name, error);
+
+        let text = std::fs::read_to_string(path).map_err(|error| fail(error.to_string()))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+        let (rates, updated) = if is_json {
+            parse_json(&text).map_err(fail)?
+        } else {
+            (parse_csv(&text).map_err(fail)?, None)
+        };
+        let timestamp = updated.unwrap_or_else(|| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .map_or_else(|_| "unknown".to_string(), format_utc)
+        });
+
+        Ok(Rates {
+            rates,
+            timestamp,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// 默认汇率文件：环境变量 MYCAL_RATES 指定的文件，否则为配置目录下的
+    /// mycal/rates.json 或 mycal/rates.csv，都不存在时返回 None
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(RATES_ENV) {
+            return Some(path.into());
+        }
+        let directory = dirs::config_dir()?.join("mycal");
+        ["rates.json", "rates.csv"]
+            .iter()
+            .map(|name| directory.join(name))
+            .find(|path| path.exists())
+    }
+
+    /// 读取默认汇率文件，没有汇率文件时返回 Ok(None)
+    pub fn load_default() -> Result<Option<Rates>, String> {
+        Self::default_path()
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+}
+
+/// 货币单位：数值为 1 单位该货币折合的基准货币数量，显示为该货币
+pub(super) fn unit(rates: Option<&Rates>, code: &str) -> Result<Quantity, CalcError> {
+    let rate = rates
+        .and_then(|rates| rates.rates.get(code))
+        .ok_or_else(|| CalcError::MissingRate(code.to_string()))?;
+    Ok(Quantity {
+        value: 1.0 / rate,
+        dimension: units::MONEY,
+        display: Some((code.to_string(), 1.0 / rate)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates(rates: BTreeMap<String, f64>) -> Rates {
+        Rates {
+            rates,
+            timestamp: String::new(),
+            path: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let (table, updated) = parse_json(
+            r#"{"base": "USD", "updated": "2026-10-01", "rates": {"EUR": 0.5, "JPY": 150}}"#,
+        )
+        .unwrap();
+        assert_eq!(table.get("USD"), Some(&1.0));
+        assert_eq!(table.get("JPY"), Some(&150.0));
+        assert_eq!(updated.as_deref(), Some("2026-10-01"));
+
+        assert!(parse_json(r#"{"base": "USD", "rates": {"USD": 2}}"#).is_err());
+        assert!(parse_json(r#"{"base": "USD", "rates": {"eur": 1}}"#).is_err());
+        assert!(parse_json(r#"{"base": "USD", "rates": {"EUR": -1}}"#).is_err());
+        assert!(parse_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let table = parse_csv("currency,rate\n# base\nUSD,1\n EUR , 0.5 \n\nGBP,0.8\n").unwrap();
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get("EUR"), Some(&0.5));
+
+        assert_eq!(
+            parse_csv("USD,1\nEUR").unwrap_err(),
+            "line 2: expected code,rate"
+        );
+        assert_eq!(
+            parse_csv("USD,1\nEUR,x").unwrap_err(),
+            "line 2: invalid rate x"
+        );
+        assert!(parse_csv("USD,0").is_err());
+        assert!(parse_csv("# empty\n").is_err());
+    }
+
+    #[test]
+    fn test_unit() {
+        let table = rates(BTreeMap::from([
+            ("USD".to_string(), 1.0),
+            ("EUR".to_string(), 0.5),
+        ]));
+        assert_eq!(unit(Some(&table), "EUR").unwrap().value, 2.0);
+        assert_eq!(
+            unit(Some(&table), "GBP"),
+            Err(CalcError::MissingRate("GBP".to_string()))
+        );
+        assert!(unit(None, "USD").is_err());
+        assert!(is_code("USD") && !is_code("Usd") && !is_code("USDT"));
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01 00:00 UTC");
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096);
+        assert_eq!(format_utc(time), "2024-02-29 12:34 UTC");
+    }
+}
@@ -0,0 +1,389 @@
+//! 日期与时长运算
+//!
+//! 日期是公历日期，以距 1970-01-01 的天数保存，不含时刻和时区。时长就是时间量纲的带单位的量，
+//! 日期加减时长要求时长为整天。周一到周五是工作日，不考虑节假日。
+
+use super::units::{self, Quantity};
+use super::{CalcError, Operation, Value};
+
+/// 一天的秒数
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// 公历日期转换为距 1970-01-01 的天数（Howard Hinnant 的 days_from_civil 算法）
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_index + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// 距 1970-01-01 的天数转换为公历日期 (年, 月, 日)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// 某月的天数
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// 检查并转换公历日期
+fn checked_date(year: i64, month: u32, day: u32) -> Result<i64, CalcError> {
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(CalcError::InvalidArguments(format!(
+            "invalid date {:04}-{:02}-{:02}",
+            year, month, day
+        )));
+    }
+    Ok(days_from_civil(year, month, day))
+}
+
+/// 把天数格式化为 YYYY-MM-DD
+pub fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 把 Unix 时间戳（秒）格式化为 UTC 的 YYYY-MM-DD HH:MM
+pub fn format_timestamp(seconds: i64) -> String {
+    let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    format!(
+        "{} {:02}:{:02}",
+        format_date(days),
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// 识别文本开头的日期字面量 YYYY-MM-DD，返回 (天数, 字面量长度)；不是日期字面量时返回 None
+pub fn parse_literal(text: &str) -> Option<Result<(i64, usize), CalcError>> {
+    let bytes = text.as_bytes();
+    let is_pattern = bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+        && !bytes
+            .get(10)
+            .is_some_and(|b| b.is_ascii_digit() || *b == b'.');
+    if !is_pattern {
+        return None;
+    }
+    let (year, month, day) = (
+        text[..4].parse().ok()?,
+        text[5..7].parse().ok()?,
+        text[8..10].parse().ok()?,
+    );
+    Some(checked_date(year, month, day).map(|days| (days, 10)))
+}
+
+/// 星期几，周一为 1，周日为 7（1970-01-01 是周四）
+pub fn weekday(days: i64) -> i64 {
+    (days + 3).rem_euclid(7) + 1
+}
+
+/// ISO 8601 周数：每周从周一开始，包含该年第一个周四的那一周是第 1 周
+pub fn iso_week(days: i64) -> i64 {
+    // 同一周的周四所在的年份就是 ISO 周所属的年份
+    let thursday = days - weekday(days) + 4;
+    let (year, ..) = civil_from_days(thursday);
+    (thursday - days_from_civil(year, 1, 1)) / 7 + 1
+}
+
+/// [start, end) 中的工作日数，end 早于 start 时为负数
+pub fn business_days(start: i64, end: i64) -> i64 {
+    if end < start {
+        return -business_days(end, start);
+    }
+    let total = end - start;
+    let mut count = total / 7 * 5;
+    for day in start + total / 7 * 7..end {
+        if weekday(day) <= 5 {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// 从 start 起前进（n 为负时后退）n 个工作日后的日期
+pub fn add_business_days(start: i64, n: i64) -> i64 {
+    // 从周末出发时，先退到周五（前进）或进到周一（后退），使第一步落在下一个工作日
+    let mut date = match (weekday(start), n.signum()) {
+        (6, 1) => start - 1,
+        (7, 1) => start - 2,
+        (6, -1) => start + 2,
+        (7, -1) => start + 1,
+        _ => start,
+    };
+    // 整周直接跳过，剩余的不足一周逐日前进
+    date += n / 5 * 7;
+    let mut remaining = n % 5;
+    while remaining != 0 {
+        date += remaining.signum();
+        if weekday(date) <= 5 {
+            remaining -= remaining.signum();
+        }
+    }
+    date
+}
+
+/// 把时长格式化为 "1 day 3 h 20 min 5 s" 的形式
+pub fn format_duration(seconds: f64) -> String {
+    if !seconds.is_finite() || seconds.abs() >= 1e15 {
+        return format!("{} s", seconds);
+    }
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    // 去掉小数秒的浮点误差，保留到纳秒
+    let total = (seconds.abs() * 1e9).round() / 1e9;
+    let days = (total / SECONDS_PER_DAY).floor();
+    let rest = total - days * SECONDS_PER_DAY;
+    let hours = (rest / 3600.0).floor();
+    let minutes = ((rest - hours * 3600.0) / 60.0).floor();
+    let secs = ((rest - hours * 3600.0 - minutes * 60.0) * 1e9).round() / 1e9;
+
+    let mut parts = Vec::new();
+    if days == 1.0 {
+        parts.push("1 day".to_string());
+    } else if days > 0.0 {
+        parts.push(format!("{} days", days));
+    }
+    if hours > 0.0 {
+        parts.push(format!("{} h", hours));
+    }
+    if minutes > 0.0 {
+        parts.push(format!("{} min", minutes));
+    }
+    if secs > 0.0 || parts.is_empty() {
+        parts.push(format!("{} s", secs));
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// 时长换算为整天数，与 expect_count 一样限制在十亿以内
+fn whole_days(duration: &Quantity) -> Result<i64, CalcError> {
+    let days = duration.value / SECONDS_PER_DAY;
+    if duration.dimension != units::TIME || days.fract() != 0.0 {
+        return Err(CalcError::InvalidArguments(
+            "dates can only move by whole days, such as 90 days".to_string(),
+        ));
+    }
+    if days.abs() >= 1e9 {
+        return Err(CalcError::InvalidArguments(
+            "date offset is too large".to_string(),
+        ));
+    }
+    Ok(days as i64)
+}
+
+/// 日期移动后超出范围的错误
+fn out_of_range() -> CalcError {
+    CalcError::InvalidArguments("date out of range".to_string())
+}
+
+/// 对包含日期的操作数执行运算：日期 ± 时长 得到日期，日期 − 日期 得到时长
+pub(super) fn apply(op: &Operation, operands: &[Value]) -> Result<Value, CalcError> {
+    match (op, operands) {
+        (Operation::Plus | Operation::ImplicitPlus, [Value::Date(date), Value::Quantity(q)])
+        | (Operation::Plus | Operation::ImplicitPlus, [Value::Quantity(q), Value::Date(date)]) => {
+            let date = date.checked_add(whole_days(q)?).ok_or_else(out_of_range)?;
+            Ok(Value::Date(date))
+        }
+        (Operation::Minus, [Value::Date(date), Value::Quantity(q)]) => {
+            let date = date.checked_sub(whole_days(q)?).ok_or_else(out_of_range)?;
+            Ok(Value::Date(date))
+        }
+        (Operation::Minus, [Value::Date(a), Value::Date(b)]) => Ok(Value::Quantity(Quantity {
+            value: (a - b) as f64 * SECONDS_PER_DAY,
+            dimension: units::TIME,
+            display: None,
+        })),
+        _ => Err(CalcError::InvalidArguments(
+            "dates support only adding or subtracting durations and subtracting dates".to_string(),
+        )),
+    }
+}
+
+/// 参数中的日期
+fn expect_date(name: &str, value: &Value) -> Result<i64, CalcError> {
+    match value {
+        Value::Date(days) => Ok(*days),
+        _ => Err(CalcError::InvalidArguments(format!(
+            "{} expects a date such as 2026-10-18",
+            name
+        ))),
+    }
+}
+
+/// 参数中的整数
+fn expect_count(name: &str, value: &Value) -> Result<i64, CalcError> {
+    value
+        .as_number()
+        .filter(|n| n.fract() == 0.0 && n.abs() < 1e9)
+        .map(|n| n as i64)
+        .ok_or_else(|| CalcError::InvalidArguments(format!("{} expects a whole number", name)))
+}
+
+/// 检查参数个数
+fn expect_args<'a>(name: &str, args: &'a [Value], count: usize) -> Result<&'a [Value], CalcError> {
+    if args.len() == count {
+        Ok(args)
+    } else {
+        Err(CalcError::InvalidArguments(format!(
+            "{} expects {} arguments",
+            name, count
+        )))
+    }
+}
+
+/// 内置函数 date(year, month, day)
+pub fn date_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let args = expect_args("date", args, 3)?;
+    let [year, month, day] = [0, 1, 2].map(|i| expect_count("date", &args[i]));
+    let (month, day) = (u32::try_from(month?), u32::try_from(day?));
+    match (month, day) {
+        (Ok(month), Ok(day)) => Ok(Value::Date(checked_date(year?, month, day)?)),
+        _ => Err(CalcError::InvalidArguments("invalid date".to_string())),
+    }
+}
+
+/// 内置函数 days_between(a, b)，b 比 a 晚的天数
+pub fn days_between_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let args = expect_args("days_between", args, 2)?;
+    let (a, b) = (
+        expect_date("days_between", &args[0])?,
+        expect_date("days_between", &args[1])?,
+    );
+    Ok(Value::Number((b - a) as f64))
+}
+
+/// 内置函数 business_days(a, b)，[a, b) 中周一到周五的天数
+pub fn business_days_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let args = expect_args("business_days", args, 2)?;
+    let (a, b) = (
+        expect_date("business_days", &args[0])?,
+        expect_date("business_days", &args[1])?,
+    );
+    Ok(Value::Number(business_days(a, b) as f64))
+}
+
+/// 内置函数 add_business_days(date, n)，跳过周末前进 n 个工作日
+pub fn add_business_days_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let args = expect_args("add_business_days", args, 2)?;
+    let date = expect_date("add_business_days", &args[0])?;
+    let n = expect_count("add_business_days", &args[1])?;
+    Ok(Value::Date(add_business_days(date, n)))
+}
+
+/// 内置函数 isoweek(date)，ISO 8601 周数
+pub fn isoweek_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let args = expect_args("isoweek", args, 1)?;
+    Ok(Value::Number(
+        iso_week(expect_date("isoweek", &args[0])?) as f64
+    ))
+}
+
+/// 内置函数 weekday(date)，周一为 1，周日为 7
+pub fn weekday_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let args = expect_args("weekday", args, 1)?;
+    Ok(Value::Number(
+        weekday(expect_date("weekday", &args[0])?) as f64
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_roundtrip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in (-800_000..800_000).step_by(997) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(format_date(days_from_civil(2024, 2, 29)), "2024-02-29");
+        assert_eq!(format_timestamp(1_792_332_245), "2026-10-18 14:04");
+        assert_eq!(format_timestamp(-60), "1969-12-31 23:59");
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(
+            parse_literal("2026-10-18 + 1"),
+            Some(Ok((days_from_civil(2026, 10, 18), 10)))
+        );
+        assert!(matches!(parse_literal("2026-02-29"), Some(Err(_))));
+        assert!(matches!(parse_literal("2024-02-29"), Some(Ok(_))));
+        assert_eq!(parse_literal("2026-10-1"), None);
+        assert_eq!(parse_literal("2026-10-180"), None);
+        assert_eq!(parse_literal("2026-10"), None);
+    }
+
+    #[test]
+    fn test_weeks() {
+        // 2026-10-18 是周日，属于 2026 年第 42 周
+        let date = days_from_civil(2026, 10, 18);
+        assert_eq!(weekday(date), 7);
+        assert_eq!(iso_week(date), 42);
+        // 年初和年末的周可能属于相邻年份
+        assert_eq!(iso_week(days_from_civil(2021, 1, 3)), 53);
+        assert_eq!(iso_week(days_from_civil(2024, 12, 30)), 1);
+        assert_eq!(iso_week(days_from_civil(2026, 1, 1)), 1);
+        assert_eq!(iso_week(days_from_civil(2026, 12, 31)), 53);
+    }
+
+    #[test]
+    fn test_business_days() {
+        let monday = days_from_civil(2026, 10, 12);
+        assert_eq!(business_days(monday, monday + 7), 5);
+        assert_eq!(business_days(monday, monday + 5), 5);
+        assert_eq!(business_days(monday + 5, monday + 7), 0);
+        assert_eq!(business_days(monday + 7, monday), -5);
+        assert_eq!(business_days(monday, monday + 30), 22);
+
+        assert_eq!(add_business_days(monday, 5), monday + 7);
+        assert_eq!(add_business_days(monday + 4, 1), monday + 7);
+        assert_eq!(add_business_days(monday + 5, 1), monday + 7);
+        assert_eq!(add_business_days(monday + 5, 5), monday + 11);
+        assert_eq!(add_business_days(monday + 6, -1), monday + 4);
+        assert_eq!(add_business_days(monday, -1), monday - 3);
+        assert_eq!(add_business_days(monday, 0), monday);
+        // 前进后再后退回到原处
+        for n in 1..20 {
+            assert_eq!(add_business_days(add_business_days(monday, n), -n), monday);
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(12000.0), "3 h 20 min");
+        assert_eq!(format_duration(90061.5), "1 day 1 h 1 min 1.5 s");
+        assert_eq!(format_duration(-5400.0), "-1 h 30 min");
+        assert_eq!(format_duration(7776000.0), "90 days");
+        assert_eq!(format_duration(0.0), "0 s");
+        assert_eq!(format_duration(0.1 + 0.2), "0.3 s");
+    }
+}
@@ -0,0 +1,318 @@
+use super::special::{beta_inc, erfc, gamma_p, ln_gamma};
+use super::{CalcError, Value, expect_numbers};
+use std::f64::consts::{PI, SQRT_2};
+
+/// 二项分布中逐项连乘组合数的 n 上限，更大的 n 改用 ln Γ 以免溢出
+const DIRECT_CHOOSE_LIMIT: u64 = 1000;
+
+/// 正态分布概率密度
+pub fn normal_pdf(x: f64, mean: f64, sd: f64) -> f64 {
+    let z = (x - mean) / sd;
+    (-0.5 * z * z).exp() / (sd * (2.0 * PI).sqrt())
+}
+
+/// 正态分布累积分布函数 P(X ≤ x)
+pub fn normal_cdf(x: f64, mean: f64, sd: f64) -> f64 {
+    0.5 * erfc(-(x - mean) / (sd * SQRT_2))
+}
+
+/// 标准正态分布的分位数（逆累积分布函数），0 < p < 1
+///
+/// 先用 Acklam 有理近似（相对误差 1.15e-9），再做一步 Halley 迭代精化到机器精度。
+pub fn standard_normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    let x = if p < P_LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    // Halley 迭代
+    let error = normal_cdf(x, 0.0, 1.0) - p;
+    let u = error * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x - u / (1.0 + x * u / 2.0)
+}
+
+/// 二项分布概率 P(X = k)，X ~ B(n, p)
+pub fn binomial_pmf(n: u64, p: f64, k: u64) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    // p 为 0 或 1 时对数形式会出现 0·ln 0
+    if p == 0.0 {
+        return if k == 0 { 1.0 } else { 0.0 };
+    }
+    if p == 1.0 {
+        return if k == n { 1.0 } else { 0.0 };
+    }
+    if n <= DIRECT_CHOOSE_LIMIT {
+        // 逐项连乘的中间结果都是整数，小 n 时比 ln Γ 相减精确
+        let j = k.min(n - k);
+        let choose = (1..=j).fold(1.0, |c, i| c * (n - j + i) as f64 / i as f64);
+        return choose * p.powi(k as i32) * (1.0 - p).powi((n - k) as i32);
+    }
+    let (n, k) = (n as f64, k as f64);
+    let ln_choose = ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0);
+    (ln_choose + k * p.ln() + (n - k) * (1.0 - p).ln()).exp()
+}
+
+/// 二项分布累积概率 P(X ≤ k) = I_{1-p}(n - k, k + 1)
+pub fn binomial_cdf(n: u64, p: f64, k: u64) -> f64 {
+    if k >= n {
+        return 1.0;
+    }
+    beta_inc((n - k) as f64, k as f64 + 1.0, 1.0 - p)
+}
+
+/// 泊松分布概率 P(X = k)，X ~ Poisson(λ)
+pub fn poisson_pmf(lambda: f64, k: u64) -> f64 {
+    let k = k as f64;
+    (k * lambda.ln() - lambda - ln_gamma(k + 1.0)).exp()
+}
+
+/// 学生 t 分布累积分布函数 P(T ≤ t)，自由度 ν
+pub fn student_t_cdf(t: f64, nu: f64) -> f64 {
+    let tail = 0.5 * beta_inc(nu / 2.0, 0.5, nu / (nu + t * t));
+    if t >= 0.0 { 1.0 - tail } else { tail }
+}
+
+/// 卡方分布累积分布函数 P(X ≤ x)，自由度 k
+pub fn chi_squared_cdf(x: f64, k: f64) -> f64 {
+    gamma_p(k / 2.0, x / 2.0)
+}
+
+/// 参数校验失败时的错误
+fn invalid(message: &str) -> CalcError {
+    CalcError::InvalidArguments(message.to_string())
+}
+
+/// 转换为非负整数，否则报错
+fn non_negative_integer(value: f64, name: &str) -> Result<u64, CalcError> {
+    if value >= 0.0 && value.fract() == 0.0 && value.is_finite() {
+        Ok(value as u64)
+    } else {
+        Err(invalid(&format!("{} must be a non-negative integer", name)))
+    }
+}
+
+/// 解析 (x, μ = 0, σ = 1) 形式的正态分布参数
+fn normal_args(name: &str, args: &[Value]) -> Result<(f64, f64, f64), CalcError> {
+    let numbers = expect_numbers(name, args, 1, 3)?;
+    let (mean, sd) = match numbers[1..] {
+        [] => (0.0, 1.0),
+        [mean] => (mean, 1.0),
+        [mean, sd] => (mean, sd),
+        _ => unreachable!(),
+    };
+    if sd <= 0.0 {
+        return Err(invalid("standard deviation must be positive"));
+    }
+    Ok((numbers[0], mean, sd))
+}
+
+/// 解析 (n, p, k) 形式的二项分布参数
+fn binomial_args(name: &str, args: &[Value]) -> Result<(u64, f64, u64), CalcError> {
+    let numbers = expect_numbers(name, args, 3, 3)?;
+    if !(0.0..=1.0).contains(&numbers[1]) {
+        return Err(invalid("probability must be between 0 and 1"));
+    }
+    Ok((
+        non_negative_integer(numbers[0], "n")?,
+        numbers[1],
+        non_negative_integer(numbers[2], "k")?,
+    ))
+}
+
+/// 内置函数 normpdf(x, μ, σ)
+pub fn normpdf(args: &[Value]) -> Result<Value, CalcError> {
+    let (x, mean, sd) = normal_args("normpdf", args)?;
+    Ok(Value::Number(normal_pdf(x, mean, sd)))
+}
+
+/// 内置函数 normcdf(x, μ, σ)
+pub fn normcdf(args: &[Value]) -> Result<Value, CalcError> {
+    let (x, mean, sd) = normal_args("normcdf", args)?;
+    Ok(Value::Number(normal_cdf(x, mean, sd)))
+}
+
+/// 内置函数 invnorm(p, μ, σ)
+pub fn invnorm(args: &[Value]) -> Result<Value, CalcError> {
+    let (p, mean, sd) = normal_args("invnorm", args)?;
+    if p <= 0.0 || p >= 1.0 {
+        return Err(invalid("probability must be strictly between 0 and 1"));
+    }
+    Ok(Value::Number(mean + sd * standard_normal_quantile(p)))
+}
+
+/// 内置函数 binompdf(n, p, k)
+pub fn binompdf(args: &[Value]) -> Result<Value, CalcError> {
+    let (n, p, k) = binomial_args("binompdf", args)?;
+    Ok(Value::Number(binomial_pmf(n, p, k)))
+}
+
+/// 内置函数 binomcdf(n, p, k)
+pub fn binomcdf(args: &[Value]) -> Result<Value, CalcError> {
+    let (n, p, k) = binomial_args("binomcdf", args)?;
+    Ok(Value::Number(binomial_cdf(n, p, k)))
+}
+
+/// 内置函数 poissonpdf(λ, k)
+pub fn poissonpdf(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("poissonpdf", args, 2, 2)?;
+    if numbers[0] <= 0.0 {
+        return Err(invalid("λ must be positive"));
+    }
+    let k = non_negative_integer(numbers[1], "k")?;
+    Ok(Value::Number(poisson_pmf(numbers[0], k)))
+}
+
+/// 内置函数 tcdf(t, ν)
+pub fn tcdf(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("tcdf", args, 2, 2)?;
+    if numbers[1] <= 0.0 {
+        return Err(invalid("degrees of freedom must be positive"));
+    }
+    Ok(Value::Number(student_t_cdf(numbers[0], numbers[1])))
+}
+
+/// 内置函数 chi2cdf(x, k)
+pub fn chi2cdf(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("chi2cdf", args, 2, 2)?;
+    if numbers[1] <= 0.0 {
+        return Err(invalid("degrees of freedom must be positive"));
+    }
+    Ok(Value::Number(chi_squared_cdf(numbers[0], numbers[1])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_normal_table() {
+        // 标准正态分布表
+        let table = [
+            (0.0, 0.5),
+            (0.5, 0.691_462_461_274_013_1),
+            (1.0, 0.841_344_746_068_542_9),
+            (1.645, 0.950_015_094_460_878_4),
+            (1.96, 0.975_002_104_851_779_5),
+            (2.576, 0.995_002_467_684_265),
+            (-3.0, 0.001_349_898_031_630_095_7),
+        ];
+        for (z, p) in table {
+            assert_close(normal_cdf(z, 0.0, 1.0), p, 1e-14);
+        }
+        assert_close(normal_pdf(0.0, 0.0, 1.0), 0.398_942_280_401_432_7, 1e-15);
+        assert_close(
+            normal_pdf(110.0, 100.0, 10.0),
+            0.024_197_072_451_914_34,
+            1e-15,
+        );
+        assert_close(
+            normal_cdf(130.0, 100.0, 15.0),
+            0.977_249_868_051_820_8,
+            1e-14,
+        );
+    }
+
+    #[test]
+    fn test_normal_quantile() {
+        let table = [
+            (0.5, 0.0),
+            (0.975, 1.959_963_984_540_054),
+            (0.95, 1.644_853_626_951_471_5),
+            (0.01, -2.326_347_874_040_841),
+            (1e-10, -6.361_340_902_404_056),
+        ];
+        for (p, z) in table {
+            assert_close(standard_normal_quantile(p), z, 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_discrete_distributions() {
+        assert_close(binomial_pmf(10, 0.5, 5), 0.246_093_75, 1e-15);
+        assert_close(binomial_cdf(10, 0.5, 5), 0.623_046_875, 1e-14);
+        assert_close(binomial_pmf(20, 0.3, 6), 0.191_638_982_753_442_4, 1e-14);
+        assert_close(binomial_cdf(20, 0.3, 6), 0.608_009_812_200_923_2, 1e-14);
+        assert_eq!(binomial_pmf(5, 0.0, 0), 1.0);
+        assert_eq!(binomial_pmf(5, 1.0, 4), 0.0);
+        assert_eq!(binomial_cdf(5, 0.3, 5), 1.0);
+        assert_eq!(binomial_pmf(5, 0.3, 6), 0.0);
+
+        assert_close(poisson_pmf(3.0, 2), 0.224_041_807_655_387_75, 1e-15);
+        assert_close(poisson_pmf(2.5, 0), (-2.5f64).exp(), 1e-15);
+    }
+
+    #[test]
+    fn test_continuous_distributions() {
+        // ν = 1 为柯西分布，ν = 2 有闭式解 1/2 + t / (2√(2 + t²))
+        assert_close(student_t_cdf(1.0, 1.0), 0.75, 1e-14);
+        assert_close(student_t_cdf(1.0, 2.0), 0.788_675_134_594_812_9, 1e-14);
+        assert_close(student_t_cdf(-1.0, 2.0), 0.211_324_865_405_187_1, 1e-14);
+        // t 分布表：t₀.₉₇₅(10) = 2.228139
+        assert_close(student_t_cdf(2.228_138_851_986_274, 10.0), 0.975, 1e-9);
+        assert_close(student_t_cdf(0.0, 5.0), 0.5, 1e-15);
+
+        // k = 2 时 P = 1 - e^(-x/2)；卡方分布表：χ²₀.₉₅(1) = 3.841459
+        assert_close(chi_squared_cdf(2.0, 2.0), 1.0 - (-1.0f64).exp(), 1e-14);
+        assert_close(chi_squared_cdf(3.841_458_820_694_124, 1.0), 0.95, 1e-12);
+        assert_close(chi_squared_cdf(18.307_038_053_275_146, 10.0), 0.95, 1e-12);
+    }
+}
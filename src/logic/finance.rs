@@ -0,0 +1,493 @@
+//! 金融函数
+//!
+//! 货币时间价值函数沿用电子表格的约定：现金流出为负、流入为正，type 为 0 表示期末付款、
+//! 1 表示期初付款。金额类结果（pmt、pv、fv、npv 和还款计划）以整数分保存并按四舍五入
+//! 舍入，显示和累加时分位精确；期数和利率不是金额，保持浮点精度。
+
+use super::stats::flatten_numbers;
+use super::{CalcError, Value, expect_numbers};
+use num_bigint::BigInt;
+
+/// 还款计划允许的最大期数
+const MAX_PERIODS: u32 = 12_000;
+
+/// 求根的收敛精度
+const TOLERANCE: f64 = 1e-12;
+
+/// 以整数分表示的金额，加减精确
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Cents(pub i64);
+
+impl Cents {
+    /// 把金额四舍五入到分（.5 远离零），非有限值或超出范围时返回 None
+    pub fn round(amount: f64) -> Option<Cents> {
+        let cents = (amount * 100.0).round();
+        (cents.is_finite() && cents.abs() < i64::MAX as f64).then_some(Cents(cents as i64))
+    }
+
+    /// 转换为浮点数，分位以内的小数能被精确显示
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+/// 固定两位小数，如 -1073.64
+impl std::fmt::Display for Cents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+/// 金额结果：四舍五入到分后包装为数值
+fn money(name: &str, amount: f64) -> Result<Value, CalcError> {
+    Cents::round(amount)
+        .map(|cents| Value::Number(cents.to_f64()))
+        .ok_or_else(|| CalcError::Math(format!("{} is undefined here", name)))
+}
+
+/// 检查每期利率大于 -100%
+fn check_rate(name: &str, rate: f64) -> Result<(), CalcError> {
+    if rate > -1.0 {
+        Ok(())
+    } else {
+        Err(CalcError::InvalidArguments(format!(
+            "{} expects a rate greater than -1",
+            name
+        )))
+    }
+}
+
+/// 解析可选的付款时点参数，只能为 0（期末）或 1（期初）
+fn payment_type(name: &str, value: Option<&f64>) -> Result<f64, CalcError> {
+    match value.copied().unwrap_or(0.0) {
+        t if t == 0.0 || t == 1.0 => Ok(t),
+        _ => Err(CalcError::InvalidArguments(format!(
+            "{} expects type 0 or 1",
+            name
+        ))),
+    }
+}
+
+/// 年金系数 (1 + r·type)·((1 + r)^n − 1)/r，利率为 0 时取极限 n
+fn annuity_factor(rate: f64, nper: f64, kind: f64) -> f64 {
+    if rate == 0.0 {
+        nper
+    } else {
+        (1.0 + rate * kind) * ((1.0 + rate).powf(nper) - 1.0) / rate
+    }
+}
+
+/// 货币时间价值方程的残差 pv·(1 + r)^n + pmt·年金系数 + fv，为 0 时各量平衡
+fn balance(rate: f64, nper: f64, pmt: f64, pv: f64, fv: f64, kind: f64) -> f64 {
+    pv * (1.0 + rate).powf(nper) + pmt * annuity_factor(rate, nper, kind) + fv
+}
+
+/// 每期付款额
+pub fn payment(rate: f64, nper: f64, pv: f64, fv: f64, kind: f64) -> f64 {
+    -(pv * (1.0 + rate).powf(nper) + fv) / annuity_factor(rate, nper, kind)
+}
+
+/// 现值
+pub fn present_value(rate: f64, nper: f64, pmt: f64, fv: f64, kind: f64) -> f64 {
+    -(pmt * annuity_factor(rate, nper, kind) + fv) / (1.0 + rate).powf(nper)
+}
+
+/// 终值
+pub fn future_value(rate: f64, nper: f64, pmt: f64, pv: f64, kind: f64) -> f64 {
+    -(pv * (1.0 + rate).powf(nper) + pmt * annuity_factor(rate, nper, kind))
+}
+
+/// 期数，无解时为 NaN
+pub fn periods(rate: f64, pmt: f64, pv: f64, fv: f64, kind: f64) -> f64 {
+    if rate == 0.0 {
+        return -(pv + fv) / pmt;
+    }
+    let annuity = pmt * (1.0 + rate * kind) / rate;
+    ((annuity - fv) / (annuity + pv)).ln() / (1.0 + rate).ln()
+}
+
+/// 净现值，第一笔现金流在第 1 期期末
+pub fn net_present_value(rate: f64, cash_flows: &[f64]) -> f64 {
+    cash_flows
+        .iter()
+        .zip(1..)
+        .map(|(flow, i)| flow / (1.0 + rate).powi(i))
+        .sum()
+}
+
+/// Brent 法：在 f(a)、f(b) 异号的区间内求根，结合反二次插值、割线和二分，保证收敛
+fn brent(f: &impl Fn(f64) -> f64, mut a: f64, mut b: f64) -> Option<f64> {
+    let (mut fa, mut fb) = (f(a), f(b));
+    if fa * fb > 0.0 {
+        return None;
+    }
+    let (mut c, mut fc) = (a, fa);
+    let (mut d, mut e) = (b - a, b - a);
+    for _ in 0..200 {
+        if fb * fc > 0.0 {
+            (c, fc) = (a, fa);
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + 0.5 * TOLERANCE;
+        let middle = 0.5 * (c - b);
+        if middle.abs() <= tolerance || fb == 0.0 {
+            return Some(b);
+        }
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            // 插值：只有 a、c 重合时用割线，否则用反二次插值
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * middle * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (
+                    s * (2.0 * middle * q * (q - r) - (b - a) * (r - 1.0)),
+                    (q - 1.0) * (r - 1.0) * (s - 1.0),
+                )
+            };
+            if p > 0.0 {
+                q = -q;
+            }
+            p = p.abs();
+            if 2.0 * p < (3.0 * middle * q - (tolerance * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = middle;
+                e = d;
+            }
+        } else {
+            d = middle;
+            e = d;
+        }
+        (a, fa) = (b, fb);
+        b += if d.abs() > tolerance {
+            d
+        } else {
+            tolerance.copysign(middle)
+        };
+        fb = f(b);
+    }
+    None
+}
+
+/// 在 (-1, ∞) 上求利率方程的根：从 guess 出发交替向上、向下扩展区间，
+/// 找到最靠近 guess 的变号区间后用 Brent 法求解
+fn solve_rate(f: impl Fn(f64) -> f64, guess: f64) -> Option<f64> {
+    let guess = if guess > -1.0 { guess } else { 0.1 };
+    if f(guess) == 0.0 {
+        return Some(guess);
+    }
+    let (mut upper, mut lower) = (guess, guess);
+    for k in 0..64 {
+        let next_upper = guess + 0.01 * (2f64.powi(k + 1) - 1.0);
+        if f(upper) * f(next_upper) <= 0.0 {
+            return brent(&f, upper, next_upper);
+        }
+        upper = next_upper;
+
+        let next_lower = -1.0 + (guess + 1.0) * 0.5f64.powi(k + 1);
+        if f(lower) * f(next_lower) <= 0.0 {
+            return brent(&f, next_lower, lower);
+        }
+        lower = next_lower;
+    }
+    None
+}
+
+/// 每期利率，无解时返回 None
+pub fn rate_of(nper: f64, pmt: f64, pv: f64, fv: f64, kind: f64, guess: f64) -> Option<f64> {
+    solve_rate(|rate| balance(rate, nper, pmt, pv, fv, kind), guess)
+}
+
+/// 内部收益率：使第 0 期起的现金流净现值为 0 的利率，无解时返回 None
+pub fn internal_rate(cash_flows: &[f64], guess: f64) -> Option<f64> {
+    let has_inflow = cash_flows.iter().any(|&flow| flow > 0.0);
+    let has_outflow = cash_flows.iter().any(|&flow| flow < 0.0);
+    if !(has_inflow && has_outflow) {
+        return None;
+    }
+    let (first, rest) = cash_flows.split_first()?;
+    solve_rate(|rate| first + net_present_value(rate, rest), guess)
+}
+
+/// 还款计划中的一期
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Installment {
+    /// 期号，从 1 开始
+    pub period: u32,
+    /// 本期还款额
+    pub payment: Cents,
+    /// 其中的利息
+    pub interest: Cents,
+    /// 其中的本金
+    pub principal: Cents,
+    /// 本期还款后的剩余本金
+    pub balance: Cents,
+}
+
+/// 等额本息还款计划：每期还款额和利息四舍五入到分，最后一期还清剩余本金，
+/// 因此各期本金之和恰好等于贷款额
+pub fn amortization(
+    principal: Cents,
+    rate: f64,
+    periods: u32,
+) -> Result<Vec<Installment>, CalcError> {
+    if principal.0 <= 0 {
+        return Err(CalcError::InvalidArguments(
+            "amortize expects a positive principal".to_string(),
+        ));
+    }
+    if !(1..=MAX_PERIODS).contains(&periods) {
+        return Err(CalcError::InvalidArguments(format!(
+            "amortize expects 1 to {} periods",
+            MAX_PERIODS
+        )));
+    }
+    if rate < 0.0 {
+        return Err(CalcError::InvalidArguments(
+            "amortize expects a non-negative rate".to_string(),
+        ));
+    }
+    let amount = payment(rate, periods as f64, -principal.to_f64(), 0.0, 0.0);
+    let level = Cents::round(amount)
+        .ok_or_else(|| CalcError::Math("amortize is undefined here".to_string()))?;
+
+    let mut balance = principal;
+    let mut schedule = Vec::with_capacity(periods as usize);
+    for period in 1..=periods {
+        let interest = Cents::round(balance.to_f64() * rate).unwrap_or_default();
+        let principal = if period == periods {
+            balance
+        } else {
+            Cents((level.0 - interest.0).min(balance.0))
+        };
+        balance = Cents(balance.0 - principal.0);
+        schedule.push(Installment {
+            period,
+            payment: Cents(interest.0 + principal.0),
+            interest,
+            principal,
+            balance,
+        });
+    }
+    Ok(schedule)
+}
+
+/// 把还款计划导出为 CSV，第一行为表头
+pub fn schedule_csv(schedule: &[Installment]) -> String {
+    let mut csv = String::from("period,payment,interest,principal,balance\n");
+    for row in schedule {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            row.period, row.payment, row.interest, row.principal, row.balance
+        ));
+    }
+    csv
+}
+
+/// 内置函数 pmt(rate, nper, pv[, fv[, type]])
+pub fn pmt_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("pmt", args, 3, 5)?;
+    check_rate("pmt", numbers[0])?;
+    let kind = payment_type("pmt", numbers.get(4))?;
+    let fv = numbers.get(3).copied().unwrap_or(0.0);
+    money("pmt", payment(numbers[0], numbers[1], numbers[2], fv, kind))
+}
+
+/// 内置函数 pv(rate, nper, pmt[, fv[, type]])
+pub fn pv_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("pv", args, 3, 5)?;
+    check_rate("pv", numbers[0])?;
+    let kind = payment_type("pv", numbers.get(4))?;
+    let fv = numbers.get(3).copied().unwrap_or(0.0);
+    money(
+        "pv",
+        present_value(numbers[0], numbers[1], numbers[2], fv, kind),
+    )
+}
+
+/// 内置函数 fv(rate, nper, pmt[, pv[, type]])
+pub fn fv_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("fv", args, 3, 5)?;
+    check_rate("fv", numbers[0])?;
+    let kind = payment_type("fv", numbers.get(4))?;
+    let pv = numbers.get(3).copied().unwrap_or(0.0);
+    money(
+        "fv",
+        future_value(numbers[0], numbers[1], numbers[2], pv, kind),
+    )
+}
+
+/// 内置函数 nper(rate, pmt, pv[, fv[, type]])
+pub fn nper_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("nper", args, 3, 5)?;
+    check_rate("nper", numbers[0])?;
+    let kind = payment_type("nper", numbers.get(4))?;
+    let fv = numbers.get(3).copied().unwrap_or(0.0);
+    let n = periods(numbers[0], numbers[1], numbers[2], fv, kind);
+    if n.is_finite() {
+        Ok(Value::Number(n))
+    } else {
+        Err(CalcError::Math("nper has no solution".to_string()))
+    }
+}
+
+/// 内置函数 rate(nper, pmt, pv[, fv[, type[, guess]]])
+pub fn rate_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("rate", args, 3, 6)?;
+    let kind = payment_type("rate", numbers.get(4))?;
+    let fv = numbers.get(3).copied().unwrap_or(0.0);
+    let guess = numbers.get(5).copied().unwrap_or(0.1);
+    rate_of(numbers[0], numbers[1], numbers[2], fv, kind, guess)
+        .map(Value::Number)
+        .ok_or_else(|| CalcError::Math("rate has no solution".to_string()))
+}
+
+/// 内置函数 npv(rate, values...)，现金流可以写成列表
+pub fn npv_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let rate = args.first().and_then(Value::as_number);
+    let cash_flows = flatten_numbers(args.get(1..).unwrap_or_default());
+    match rate {
+        Some(rate) if !cash_flows.is_empty() => {
+            check_rate("npv", rate)?;
+            money("npv", net_present_value(rate, &cash_flows))
+        }
+        _ => Err(CalcError::InvalidArguments(
+            "npv expects a rate and cash flows".to_string(),
+        )),
+    }
+}
+
+/// 内置函数 irr(values[, guess])，现金流为列表，第一笔在第 0 期
+pub fn irr_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let (cash_flows, guess) = match args {
+        [Value::List(_)] => (flatten_numbers(args), 0.1),
+        [Value::List(_), guess] => match guess.as_number() {
+            Some(guess) => (flatten_numbers(&args[..1]), guess),
+            None => (Vec::new(), 0.1),
+        },
+        _ => (flatten_numbers(args), 0.1),
+    };
+    if cash_flows.len() < 2 {
+        return Err(CalcError::InvalidArguments(
+            "irr expects at least 2 cash flows".to_string(),
+        ));
+    }
+    internal_rate(&cash_flows, guess)
+        .map(Value::Number)
+        .ok_or_else(|| CalcError::Math("irr has no solution".to_string()))
+}
+
+/// 内置函数 amortize(principal, rate, nper)，返回每期 {期号, 还款额, 利息, 本金, 剩余本金}
+pub fn amortize_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("amortize", args, 3, 3)?;
+    let principal = Cents::round(numbers[0]).ok_or_else(|| {
+        CalcError::InvalidArguments("amortize expects a positive principal".to_string())
+    })?;
+    if numbers[2].fract() != 0.0 || numbers[2] < 1.0 {
+        return Err(CalcError::InvalidArguments(
+            "amortize expects a whole number of periods".to_string(),
+        ));
+    }
+    let schedule = amortization(
+        principal,
+        numbers[1],
+        numbers[2].min(u32::MAX as f64) as u32,
+    )?;
+    Ok(Value::List(
+        schedule
+            .iter()
+            .map(|row| {
+                Value::List(vec![
+                    Value::Integer(BigInt::from(row.period)),
+                    Value::Number(row.payment.to_f64()),
+                    Value::Number(row.interest.to_f64()),
+                    Value::Number(row.principal.to_f64()),
+                    Value::Number(row.balance.to_f64()),
+                ])
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cents() {
+        assert_eq!(Cents::round(1073.6432), Some(Cents(107364)));
+        assert_eq!(Cents::round(-0.125), Some(Cents(-13)));
+        assert_eq!(Cents::round(f64::NAN), None);
+        assert_eq!(Cents(107364).to_string(), "1073.64");
+        assert_eq!(Cents(-5).to_string(), "-0.05");
+        assert_eq!(Cents(107364).to_f64().to_string(), "1073.64");
+    }
+
+    #[test]
+    fn test_time_value() {
+        // 20 万贷款、年利率 5%、30 年按月还款
+        let pmt = payment(0.05 / 12.0, 360.0, 200_000.0, 0.0, 0.0);
+        assert!((pmt + 1_073.643_246_3).abs() < 1e-6);
+        let pv = present_value(0.05 / 12.0, 360.0, pmt, 0.0, 0.0);
+        assert!((pv - 200_000.0).abs() < 1e-6);
+        assert!((future_value(0.1, 2.0, 0.0, -100.0, 0.0) - 121.0).abs() < 1e-9);
+        assert!((periods(0.05 / 12.0, pmt, 200_000.0, 0.0, 0.0) - 360.0).abs() < 1e-9);
+        assert_eq!(payment(0.0, 10.0, 1000.0, 0.0, 0.0), -100.0);
+        assert_eq!(periods(0.0, -100.0, 1000.0, 0.0, 0.0), 10.0);
+
+        let rate = rate_of(360.0, pmt, 200_000.0, 0.0, 0.0, 0.1).unwrap();
+        assert!((rate - 0.05 / 12.0).abs() < 1e-12);
+        // 期初付款
+        let due = payment(0.01, 12.0, 1000.0, 0.0, 1.0);
+        assert!(balance(0.01, 12.0, due, 1000.0, 0.0, 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_irr() {
+        let flows = [-100.0, 60.0, 60.0];
+        let irr = internal_rate(&flows, 0.1).unwrap();
+        assert!((flows[0] + net_present_value(irr, &flows[1..])).abs() < 1e-9);
+        assert!((irr - 0.130_662_386_291_807).abs() < 1e-12);
+        // 远离初始猜测的负收益率
+        let irr = internal_rate(&[-100.0, 10.0, 10.0], 0.1).unwrap();
+        assert!(irr < -0.5);
+        assert_eq!(internal_rate(&[100.0, 50.0], 0.1), None);
+    }
+
+    #[test]
+    fn test_amortization() {
+        let schedule = amortization(Cents(20_000_000), 0.05 / 12.0, 360).unwrap();
+        assert_eq!(schedule.len(), 360);
+        assert_eq!(schedule[0].payment, Cents(107_364));
+        assert_eq!(schedule[0].interest, Cents(83_333));
+        assert_eq!(schedule[0].principal, Cents(24_031));
+        assert_eq!(schedule[359].balance, Cents(0));
+        let principal: i64 = schedule.iter().map(|row| row.principal.0).sum();
+        assert_eq!(principal, 20_000_000);
+        assert!(
+            schedule
+                .iter()
+                .all(|row| row.payment.0 == row.interest.0 + row.principal.0)
+        );
+
+        let schedule = amortization(Cents(10_000), 0.0, 3).unwrap();
+        let payments: Vec<i64> = schedule.iter().map(|row| row.payment.0).collect();
+        assert_eq!(payments, [3333, 3333, 3334]);
+        assert_eq!(
+            schedule_csv(&schedule[..1]),
+            "period,payment,interest,principal,balance\n1,33.33,0.00,33.33,66.67\n"
+        );
+        assert!(amortization(Cents(0), 0.01, 12).is_err());
+        assert!(amortization(Cents(100), 0.01, 0).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! 数值显示格式：FIX（固定小数位）、SCI（科学计数法）、ENG（工程计数法）和 SIG（有效数字）
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 小数位数或有效数字的上限
+pub const MAX_DIGITS: u8 = 15;
+
+/// 从 AUTO 切换到其他格式时使用的位数
+const DEFAULT_DIGITS: u8 = 4;
+
+/// 数值显示格式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum NumberFormat {
+    /// Rust 默认的最短表示
+    #[default]
+    Auto,
+    /// 固定 n 位小数
+    Fix(u8),
+    /// 科学计数法，尾数保留 n 位小数
+    Sci(u8),
+    /// 工程计数法，指数为 3 的倍数，尾数保留 n 位小数
+    Eng(u8),
+    /// 保留 n 位有效数字
+    Sig(u8),
+}
+
+impl NumberFormat {
+    /// 切换到下一种格式，保留当前位数
+    pub fn next(self) -> Self {
+        let digits = self.digits().unwrap_or(DEFAULT_DIGITS);
+        match self {
+            NumberFormat::Auto => NumberFormat::Fix(digits),
+            NumberFormat::Fix(_) => NumberFormat::Sci(digits),
+            NumberFormat::Sci(_) => NumberFormat::Eng(digits),
+            NumberFormat::Eng(_) => NumberFormat::Sig(digits.max(1)),
+            NumberFormat::Sig(_) => NumberFormat::Auto,
+        }
+    }
+
+    /// 小数位数或有效数字，AUTO 为 None
+    pub fn digits(self) -> Option<u8> {
+        match self {
+            NumberFormat::Auto => None,
+            NumberFormat::Fix(n)
+            | NumberFormat::Sci(n)
+            | NumberFormat::Eng(n)
+            | NumberFormat::Sig(n) => Some(n),
+        }
+    }
+
+    /// 修改位数，限制在允许范围内（有效数字至少 1 位），AUTO 不变
+    pub fn with_digits(self, digits: u8) -> Self {
+        let digits = digits.min(MAX_DIGITS);
+        match self {
+            NumberFormat::Auto => NumberFormat::Auto,
+            NumberFormat::Fix(_) => NumberFormat::Fix(digits),
+            NumberFormat::Sci(_) => NumberFormat::Sci(digits),
+            NumberFormat::Eng(_) => NumberFormat::Eng(digits),
+            NumberFormat::Sig(_) => NumberFormat::Sig(digits.max(1)),
+        }
+    }
+
+    /// 按格式显示数值，无穷大和 NaN 原样显示
+    pub fn format(self, x: f64) -> String {
+        if !x.is_finite() {
+            return x.to_string();
+        }
+        match self {
+            NumberFormat::Auto => x.to_string(),
+            NumberFormat::Fix(n) => {
+                positive_zero(format!("{:.*}", n as usize, round_half_away(x, n as i32)))
+            }
+            NumberFormat::Sci(n) => scientific(x, n as usize),
+            NumberFormat::Eng(n) => engineering(x, n as usize),
+            NumberFormat::Sig(n) => significant(x, n.max(1) as usize),
+        }
+    }
+
+    /// 结果超过 width 个字符时使用的科学计数法：尽量保留当前格式的精度，放不下时减少小数位
+    pub fn overflow(self, x: f64, width: usize) -> String {
+        let fits = |text: &String| text.chars().count() <= width;
+        let preferred = match self {
+            NumberFormat::Auto => format!("{:e}", x),
+            NumberFormat::Sig(n) => scientific(x, n.max(1) as usize - 1),
+            NumberFormat::Fix(n) | NumberFormat::Sci(n) | NumberFormat::Eng(n) => {
+                scientific(x, n as usize)
+            }
+        };
+        if fits(&preferred) {
+            return preferred;
+        }
+        (0..MAX_DIGITS as usize)
+            .rev()
+            .map(|decimals| scientific(x, decimals))
+            .find(fits)
+            .unwrap_or_else(|| scientific(x, 0))
+    }
+}
+
+/// 状态栏上的名称，如 FIX 2
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberFormat::Auto => write!(f, "AUTO"),
+            NumberFormat::Fix(n) => write!(f, "FIX {}", n),
+            NumberFormat::Sci(n) => write!(f, "SCI {}", n),
+            NumberFormat::Eng(n) => write!(f, "ENG {}", n),
+            NumberFormat::Sig(n) => write!(f, "SIG {}", n),
+        }
+    }
+}
+
+/// 舍入后为 0 的负数去掉负号，如 -0.00 显示为 0.00
+fn positive_zero(text: String) -> String {
+    match text.strip_prefix('-') {
+        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => rest.to_string(),
+        _ => text,
+    }
+}
+
+/// 拆分 Rust 科学计数法文本为 (尾数, 指数)
+fn split_exponent(text: &str) -> (&str, i32) {
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((text, "0"));
+    (mantissa, exponent.parse().unwrap_or(0))
+}
+
+/// 按 x 的最短十进制表示舍入到小数点后 decimals 位（可以为负），恰在中间时远离零，
+/// 所以 2.5 舍入为 3、1.005 舍入为 1.01；Rust 的 {:.*} 按二进制值舍入，中间值取偶数
+fn round_half_away(x: f64, decimals: i32) -> f64 {
+    let text = format!("{:e}", x.abs());
+    let (mantissa, exponent) = split_exponent(&text);
+    let digits: Vec<u64> = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| u64::from(b - b'0'))
+        .collect();
+    // 第一位数字在小数点前 exponent + 1 位
+    let keep = exponent + 1 + decimals;
+    if keep < 0 {
+        return 0.0_f64.copysign(x);
+    }
+    let keep = keep as usize;
+    if keep >= digits.len() {
+        return x;
+    }
+    let kept = digits[..keep].iter().fold(0, |n, d| n * 10 + d);
+    let kept = kept + u64::from(digits[keep] >= 5);
+    let rounded: f64 = format!("{}e{}", kept, -decimals).parse().unwrap_or(x);
+    rounded.copysign(x)
+}
+
+/// 尾数保留 decimals 位小数的 Rust 科学计数法文本，中间值远离零舍入
+fn exponential(x: f64, decimals: usize) -> String {
+    let (_, exponent) = split_exponent(&format!("{:e}", x));
+    let rounded = round_half_away(x, decimals as i32 - exponent);
+    format!("{:.*e}", decimals, rounded)
+}
+
+/// 科学计数法，尾数保留 decimals 位小数，如 1.23e5
+pub fn scientific(x: f64, decimals: usize) -> String {
+    positive_zero(exponential(x, decimals))
+}
+
+/// 工程计数法：先确定十进制指数，把小数点右移到使指数为 3 的倍数的位置，
+/// 尾数按 decimals 位小数舍入，舍入进位改变指数时重新计算
+fn engineering(x: f64, decimals: usize) -> String {
+    let (_, mut exponent) = split_exponent(&format!("{:e}", x));
+    for _ in 0..2 {
+        let shift = exponent.rem_euclid(3) as usize;
+        let text = exponential(x, decimals + shift);
+        let (mantissa, rounded_exponent) = split_exponent(&text);
+        if rounded_exponent != exponent {
+            exponent = rounded_exponent;
+            continue;
+        }
+        let (sign, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", mantissa),
+        };
+        let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+        let (whole, fraction) = digits.split_at(1 + shift);
+        let point = if fraction.is_empty() { "" } else { "." };
+        return positive_zero(format!(
+            "{}{}{}{}e{}",
+            sign,
+            whole,
+            point,
+            fraction,
+            exponent - shift as i32
+        ));
+    }
+    scientific(x, decimals)
+}
+
+/// 保留 digits 位有效数字，指数在 [-5, 15) 内时用普通小数表示，否则用科学计数法
+fn significant(x: f64, digits: usize) -> String {
+    let text = exponential(x, digits - 1);
+    let (_, exponent) = split_exponent(&text);
+    if (-5..15).contains(&exponent) {
+        let decimals = (digits as i32 - 1 - exponent).max(0) as usize;
+        let rounded: f64 = text.parse().unwrap_or(x);
+        positive_zero(format!("{:.*}", decimals, rounded))
+    } else {
+        positive_zero(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fix() {
+        assert_eq!(NumberFormat::Fix(2).format(0.1 + 0.2), "0.30");
+        assert_eq!(NumberFormat::Fix(0).format(2.5), "3");
+        // 中间值远离零舍入，按十进制表示判断是否恰在中间
+        assert_eq!(NumberFormat::Fix(2).format(0.125), "0.13");
+        assert_eq!(NumberFormat::Fix(0).format(-2.5), "-3");
+        assert_eq!(NumberFormat::Fix(0).format(0.5), "1");
+        assert_eq!(NumberFormat::Fix(2).format(1.005), "1.01");
+        assert_eq!(NumberFormat::Fix(1).format(0.04), "0.0");
+        assert_eq!(NumberFormat::Fix(2).format(1e300), format!("{:.2}", 1e300));
+        assert_eq!(NumberFormat::Fix(3).format(-1.0), "-1.000");
+        assert_eq!(NumberFormat::Fix(2).format(-0.001), "0.00");
+        assert_eq!(NumberFormat::Auto.format(0.1 + 0.2), "0.30000000000000004");
+    }
+
+    #[test]
+    fn test_sci() {
+        assert_eq!(NumberFormat::Sci(2).format(123456.0), "1.23e5");
+        // 输入的 0.00012345 恰在中间，和 FIX、SIG 一样远离零舍入
+        assert_eq!(NumberFormat::Sci(3).format(-0.00012345), "-1.235e-4");
+        assert_eq!(NumberFormat::Sci(0).format(1e21), "1e21");
+        assert_eq!(NumberFormat::Sci(2).format(0.0), "0.00e0");
+        assert_eq!(NumberFormat::Sci(1).format(1250.0), "1.3e3");
+    }
+
+    #[test]
+    fn test_eng() {
+        assert_eq!(NumberFormat::Eng(2).format(123456.0), "123.46e3");
+        assert_eq!(NumberFormat::Eng(3).format(0.0123), "12.300e-3");
+        assert_eq!(NumberFormat::Eng(1).format(1e21), "1.0e21");
+        assert_eq!(NumberFormat::Eng(2).format(-4700.0), "-4.70e3");
+        // 舍入进位到下一组指数
+        assert_eq!(NumberFormat::Eng(1).format(999.96), "1.0e3");
+        assert_eq!(NumberFormat::Eng(0).format(0.0), "0e0");
+    }
+
+    #[test]
+    fn test_sig() {
+        assert_eq!(NumberFormat::Sig(3).format(1.23456), "1.23");
+        assert_eq!(NumberFormat::Sig(3).format(123456.0), "123000");
+        assert_eq!(NumberFormat::Sig(2).format(0.000123), "0.00012");
+        assert_eq!(NumberFormat::Sig(4).format(2.0), "2.000");
+        assert_eq!(NumberFormat::Sig(3).format(1e21), "1.00e21");
+        assert_eq!(NumberFormat::Sig(3).format(9.996), "10.0");
+        assert_eq!(NumberFormat::Sig(2).format(0.125), "0.13");
+        assert_eq!(NumberFormat::Sig(1).format(-2.5), "-3");
+        assert_eq!(NumberFormat::Sig(2).format(1250.0), "1300");
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(NumberFormat::Auto.overflow(1e21, 20), "1e21");
+        assert_eq!(NumberFormat::Fix(2).overflow(1e21, 20), "1.00e21");
+        assert_eq!(
+            NumberFormat::Auto.overflow(1.0 / 3.0 * 1e25, 12),
+            "3.3333333e24"
+        );
+        assert_eq!(NumberFormat::Auto.format(f64::INFINITY), "inf");
+    }
+
+    #[test]
+    fn test_cycle() {
+        let format = NumberFormat::Auto.next();
+        assert_eq!(format, NumberFormat::Fix(4));
+        assert_eq!(format.with_digits(2).next(), NumberFormat::Sci(2));
+        assert_eq!(NumberFormat::Sig(1).with_digits(0), NumberFormat::Sig(1));
+        assert_eq!(NumberFormat::Fix(2).with_digits(99), NumberFormat::Fix(15));
+        assert_eq!(NumberFormat::Eng(3).to_string(), "ENG 3");
+        assert_eq!(NumberFormat::Sig(3).next(), NumberFormat::Auto);
+    }
+}
@@ -0,0 +1,325 @@
+use super::{CalcError, Value};
+use std::fmt;
+
+/// 条件数超过该阈值时视为病态方程组（结果大约只剩 4 位有效数字）
+pub const ILL_CONDITIONED_THRESHOLD: f64 = 1e12;
+
+/// 线性方程组求解错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinSolveError {
+    /// 系数矩阵不是方阵，或与常数向量维数不符
+    DimensionMismatch,
+    /// 系数矩阵奇异（主元在舍入误差范围内为零）
+    Singular,
+    /// 病态方程组，包含条件数估计
+    IllConditioned { condition: f64 },
+}
+
+impl fmt::Display for LinSolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinSolveError::DimensionMismatch => {
+                write!(f, "Matrix must be square and match the vector length")
+            }
+            LinSolveError::Singular => write!(f, "Singular system: no unique solution"),
+            LinSolveError::IllConditioned { condition } => {
+                write!(f, "Ill-conditioned system (cond ≈ {:.3e})", condition)
+            }
+        }
+    }
+}
+
+impl From<LinSolveError> for CalcError {
+    fn from(error: LinSolveError) -> Self {
+        match error {
+            LinSolveError::DimensionMismatch => CalcError::InvalidArguments(error.to_string()),
+            _ => CalcError::Math(error.to_string()),
+        }
+    }
+}
+
+/// 线性方程组的解
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// 解向量
+    pub x: Vec<f64>,
+    /// 1-范数条件数 κ₁(A) = ‖A‖₁·‖A⁻¹‖₁
+    pub condition: f64,
+}
+
+/// 部分主元 LU 分解：PA = LU
+#[derive(Debug, Clone)]
+pub struct LuDecomposition {
+    /// L（单位下三角，不存对角线）和 U 合并存储
+    lu: Vec<Vec<f64>>,
+    /// 第 i 行来自原矩阵的第 pivots[i] 行
+    pivots: Vec<usize>,
+}
+
+impl LuDecomposition {
+    /// 分解方阵，主元小于 n·ε·‖A‖₁ 时判定为奇异
+    pub fn new(matrix: &[Vec<f64>]) -> Result<Self, LinSolveError> {
+        let n = matrix.len();
+        if n == 0 || matrix.iter().any(|row| row.len() != n) {
+            return Err(LinSolveError::DimensionMismatch);
+        }
+
+        let tolerance = n as f64 * f64::EPSILON * norm_1(matrix);
+        let mut lu = matrix.to_vec();
+        let mut pivots: Vec<usize> = (0..n).collect();
+
+        for k in 0..n {
+            // 选取当前列绝对值最大的元素作为主元
+            let pivot_row = (k..n)
+                .max_by(|&a, &b| lu[a][k].abs().total_cmp(&lu[b][k].abs()))
+                .unwrap();
+            if lu[pivot_row][k].abs() <= tolerance || !lu[pivot_row][k].is_finite() {
+                return Err(LinSolveError::Singular);
+            }
+            lu.swap(k, pivot_row);
+            pivots.swap(k, pivot_row);
+
+            // 消去主元下方的元素，乘数存放在 L 的位置
+            let (upper, lower) = lu.split_at_mut(k + 1);
+            let pivot = &upper[k];
+            for row in lower.iter_mut() {
+                let factor = row[k] / pivot[k];
+                row[k] = factor;
+                for (value, &p) in row[k + 1..].iter_mut().zip(&pivot[k + 1..]) {
+                    *value -= factor * p;
+                }
+            }
+        }
+
+        Ok(Self { lu, pivots })
+    }
+
+    /// 求解 Ax = b（b 的长度必须与矩阵阶数相同）
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.lu.len();
+
+        // 前代：Ly = Pb
+        let mut x: Vec<f64> = self.pivots.iter().map(|&row| b[row]).collect();
+        for i in 0..n {
+            for j in 0..i {
+                x[i] -= self.lu[i][j] * x[j];
+            }
+        }
+
+        // 回代：Ux = y
+        for i in (0..n).rev() {
+            for j in (i + 1)..n {
+                x[i] -= self.lu[i][j] * x[j];
+            }
+            x[i] /= self.lu[i][i];
+        }
+
+        x
+    }
+
+    /// 通过逐列求解 A⁻¹ 计算 ‖A⁻¹‖₁
+    fn inverse_norm_1(&self) -> f64 {
+        let n = self.lu.len();
+        (0..n)
+            .map(|column| {
+                let mut unit = vec![0.0; n];
+                unit[column] = 1.0;
+                self.solve(&unit).iter().map(|v| v.abs()).sum::<f64>()
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+/// 矩阵的 1-范数（列绝对值和的最大值）
+fn norm_1(matrix: &[Vec<f64>]) -> f64 {
+    let columns = matrix.first().map_or(0, Vec::len);
+    (0..columns)
+        .map(|j| matrix.iter().map(|row| row[j].abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+/// 求解线性方程组 Ax = b，并检查奇异和病态情况
+pub fn solve(a: &[Vec<f64>], b: &[f64]) -> Result<Solution, LinSolveError> {
+    if a.len() != b.len() {
+        return Err(LinSolveError::DimensionMismatch);
+    }
+
+    let lu = LuDecomposition::new(a)?;
+    let condition = norm_1(a) * lu.inverse_norm_1();
+    if !condition.is_finite() || condition > ILL_CONDITIONED_THRESHOLD {
+        return Err(LinSolveError::IllConditioned { condition });
+    }
+
+    Ok(Solution {
+        x: lu.solve(b),
+        condition,
+    })
+}
+
+/// 用 Householder QR 分解求超定方程组 Ax ≈ b 的最小二乘解（A 为 m×n，m ≥ n）
+pub fn least_squares(a: &[Vec<f64>], b: &[f64]) -> Result<Vec<f64>, LinSolveError> {
+    let m = a.len();
+    let n = a.first().map_or(0, Vec::len);
+    if n == 0 || m < n || b.len() != m || a.iter().any(|row| row.len() != n) {
+        return Err(LinSolveError::DimensionMismatch);
+    }
+
+    let tolerance = m as f64 * f64::EPSILON * norm_1(a);
+    // 增广矩阵 [A | b]，反射同时作用于右端向量
+    let mut r: Vec<Vec<f64>> = a
+        .iter()
+        .zip(b)
+        .map(|(row, &bi)| row.iter().copied().chain([bi]).collect())
+        .collect();
+
+    for k in 0..n {
+        // 构造把第 k 列对角线以下元素消为零的反射向量 v = x - αe₁
+        let norm = (k..m).map(|i| r[i][k].powi(2)).sum::<f64>().sqrt();
+        if norm <= tolerance {
+            return Err(LinSolveError::Singular);
+        }
+        let alpha = if r[k][k] > 0.0 { -norm } else { norm };
+        let mut v: Vec<f64> = (k..m).map(|i| r[i][k]).collect();
+        v[0] -= alpha;
+        let v_norm_squared: f64 = v.iter().map(|x| x * x).sum();
+
+        // 对剩余列（含右端向量）应用反射 H = I - 2vvᵀ/‖v‖²
+        let rows = &mut r[k..];
+        for j in k..=n {
+            let dot: f64 = rows.iter().zip(&v).map(|(row, vi)| vi * row[j]).sum();
+            let factor = 2.0 * dot / v_norm_squared;
+            for (row, vi) in rows.iter_mut().zip(&v) {
+                row[j] -= factor * vi;
+            }
+        }
+    }
+
+    // 回代：Rx = Qᵀb 的前 n 行
+    let mut x: Vec<f64> = r.iter().take(n).map(|row| row[n]).collect();
+    for i in (0..n).rev() {
+        for j in (i + 1)..n {
+            x[i] -= r[i][j] * x[j];
+        }
+        x[i] /= r[i][i];
+    }
+
+    Ok(x)
+}
+
+/// 内置函数 linsolve(A, b)
+pub fn linsolve(args: &[Value]) -> Result<Value, CalcError> {
+    let [a, b] = args else {
+        return Err(CalcError::InvalidArguments(
+            "linsolve expects a matrix and a vector".to_string(),
+        ));
+    };
+    let (Some(a), Some(b)) = (a.as_matrix(), b.as_vector()) else {
+        return Err(CalcError::InvalidArguments(
+            "linsolve expects a matrix and a vector".to_string(),
+        ));
+    };
+
+    Ok(Value::from(solve(&a, &b)?.x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_solve_requires_pivoting() {
+        // 第一列首元素为零，不换行无法分解
+        let a = vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, -2.0, -3.0],
+            vec![-1.0, 1.0, 2.0],
+        ];
+        let solution = solve(&a, &[-8.0, 0.0, 3.0]).unwrap();
+        assert_close(&solution.x, &[-4.0, -5.0, 2.0]);
+    }
+
+    #[test]
+    fn test_condition_number() {
+        let identity = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert_eq!(solve(&identity, &[1.0, 2.0]).unwrap().condition, 1.0);
+
+        // ‖A‖₁ = 6，A⁻¹ = [[-2, 1], [1.5, -0.5]]，‖A⁻¹‖₁ = 3.5
+        let a = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        let solution = solve(&a, &[5.0, 6.0]).unwrap();
+        assert!((solution.condition - 21.0).abs() < 1e-9);
+        assert_close(&solution.x, &[-4.0, 4.5]);
+    }
+
+    #[test]
+    fn test_singular_system() {
+        let a = vec![vec![1.0, 2.0], vec![2.0, 4.0]];
+        assert_eq!(solve(&a, &[1.0, 2.0]), Err(LinSolveError::Singular));
+
+        let zero = vec![vec![0.0, 0.0], vec![0.0, 0.0]];
+        assert_eq!(solve(&zero, &[0.0, 0.0]), Err(LinSolveError::Singular));
+    }
+
+    #[test]
+    fn test_ill_conditioned_system() {
+        let a = vec![vec![1.0, 1.0], vec![1.0, 1.0 + 1e-14]];
+        match solve(&a, &[2.0, 2.0]) {
+            Err(LinSolveError::IllConditioned { condition }) => assert!(condition > 1e13),
+            other => panic!("expected ill-conditioned error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_least_squares() {
+        // 方阵时与直接求解一致
+        let a = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        assert_close(&least_squares(&a, &[5.0, 6.0]).unwrap(), &[-4.0, 4.5]);
+
+        // 拟合 y = 1 + 2x，数据点恰好在直线上
+        let a = vec![
+            vec![1.0, 0.0],
+            vec![1.0, 1.0],
+            vec![1.0, 2.0],
+            vec![1.0, 3.0],
+        ];
+        assert_close(
+            &least_squares(&a, &[1.0, 3.0, 5.0, 7.0]).unwrap(),
+            &[1.0, 2.0],
+        );
+
+        // 超定且有误差：点 (0,0) (1,1) (2,1) 的最佳直线为 y = 1/6 + x/2
+        let a = vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![1.0, 2.0]];
+        assert_close(
+            &least_squares(&a, &[0.0, 1.0, 1.0]).unwrap(),
+            &[1.0 / 6.0, 0.5],
+        );
+
+        let rank_deficient = vec![vec![1.0, 2.0], vec![2.0, 4.0], vec![3.0, 6.0]];
+        assert_eq!(
+            least_squares(&rank_deficient, &[1.0, 2.0, 3.0]),
+            Err(LinSolveError::Singular)
+        );
+        assert_eq!(
+            least_squares(&[vec![1.0, 2.0]], &[1.0]),
+            Err(LinSolveError::DimensionMismatch)
+        );
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let a = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        assert_eq!(solve(&a, &[1.0]), Err(LinSolveError::DimensionMismatch));
+        let rectangular = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]];
+        assert_eq!(
+            solve(&rectangular, &[1.0, 2.0]),
+            Err(LinSolveError::DimensionMismatch)
+        );
+        assert_eq!(solve(&[], &[]), Err(LinSolveError::DimensionMismatch));
+    }
+}
@@ -0,0 +1,317 @@
+//! 区域数字格式：小数点、千位分隔符和分组方式
+//!
+//! 表达式内部使用规范格式（`.` 为小数点，`,` 分隔函数参数）。小数点为 `,` 的区域里，
+//! 函数参数改用 `;` 分隔，如 `max(1,5; 2)`。解析时把区域格式的数字还原为规范格式：
+//! 分组符号只有在分组合法时才会被去掉，因此 `gcd(12,345)` 仍然是两个参数。
+
+/// 数字分组方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grouping {
+    /// 每三位一组：1,234,567
+    Thousands,
+    /// 印度的拉克分组：最后三位一组，之前每两位一组，12,34,567
+    Indian,
+}
+
+/// 区域数字格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Locale {
+    /// 区域代码
+    pub name: &'static str,
+    /// 小数点
+    pub decimal: char,
+    /// 分组符号
+    pub group: char,
+    /// 分组方式
+    pub grouping: Grouping,
+}
+
+/// 支持的区域，第一个为默认值
+pub const LOCALES: [Locale; 4] = [
+    Locale {
+        name: "en",
+        decimal: '.',
+        group: ',',
+        grouping: Grouping::Thousands,
+    },
+    Locale {
+        name: "de",
+        decimal: ',',
+        group: '.',
+        grouping: Grouping::Thousands,
+    },
+    Locale {
+        name: "ch",
+        decimal: '.',
+        group: '\'',
+        grouping: Grouping::Thousands,
+    },
+    Locale {
+        name: "in",
+        decimal: '.',
+        group: ',',
+        grouping: Grouping::Indian,
+    },
+];
+
+/// 以 `,` 作小数点的语言
+const COMMA_DECIMAL_LANGUAGES: &[&str] = &[
+    "cs", "da", "de", "el", "es", "fi", "fr", "hu", "id", "it", "nb", "nl", "pl", "pt", "ro", "ru",
+    "sv", "tr", "uk",
+];
+
+impl Default for Locale {
+    fn default() -> Self {
+        LOCALES[0]
+    }
+}
+
+impl Locale {
+    /// 下一个区域，供界面循环切换
+    pub fn next(self) -> Locale {
+        let index = LOCALES.iter().position(|locale| *locale == self);
+        LOCALES[index.map_or(0, |i| (i + 1) % LOCALES.len())]
+    }
+
+    /// 按区域代码（如 de）查找区域
+    pub fn named(name: &str) -> Option<Locale> {
+        LOCALES.iter().copied().find(|locale| locale.name == name)
+    }
+
+    /// 按系统区域标签（如 de_DE.UTF-8、en_IN）选择区域
+    pub fn from_tag(tag: &str) -> Locale {
+        let tag = tag.split(['.', '@']).next().unwrap_or_default();
+        let (language, region) = tag.split_once(['_', '-']).unwrap_or((tag, ""));
+        let language = language.to_ascii_lowercase();
+        match region.to_ascii_uppercase().as_str() {
+            "CH" | "LI" => LOCALES[2],
+            "IN" => LOCALES[3],
+            _ if COMMA_DECIMAL_LANGUAGES.contains(&language.as_str()) => LOCALES[1],
+            _ => LOCALES[0],
+        }
+    }
+
+    /// 按环境变量 LC_ALL、LC_NUMERIC、LANG 选择区域
+    pub fn from_env() -> Locale {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or_else(Locale::default, |tag| Locale::from_tag(&tag))
+    }
+
+    /// 函数参数分隔符：小数点为 `,` 时改用 `;`
+    pub fn argument_separator(self) -> char {
+        if self.decimal == ',' { ';' } else { ',' }
+    }
+
+    /// 状态栏上的示例，如 1.234.567,8
+    pub fn sample(self) -> String {
+        self.group(&self.localize("1234567.8"))
+    }
+
+    /// 把规范格式的文本（数字或表达式）转换为区域格式：替换小数点和参数分隔符
+    pub fn localize(self, text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                '.' => self.decimal,
+                ',' => self.argument_separator(),
+                _ => c,
+            })
+            .collect()
+    }
+
+    /// 给区域格式的数字加分组符号，如 1234567,8 → 1.234.567,8；不是纯数字的整数部分保持原样
+    pub fn group(self, number: &str) -> String {
+        let (sign, number) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", number),
+        };
+        let (integer, fraction) = match number.find(self.decimal) {
+            Some(index) => number.split_at(index),
+            None => (number, ""),
+        };
+        if !integer.chars().all(|c| c.is_ascii_digit()) {
+            return format!("{}{}", sign, number);
+        }
+
+        let digits: Vec<char> = integer.chars().collect();
+        let mut grouped = String::new();
+        for (i, digit) in digits.iter().enumerate() {
+            let remaining = digits.len() - i;
+            if i > 0 && self.group_boundary(remaining) {
+                grouped.push(self.group);
+            }
+            grouped.push(*digit);
+        }
+        format!("{}{}{}", sign, grouped, fraction)
+    }
+
+    /// 还剩 remaining 位数字时，前面是否需要分组符号
+    fn group_boundary(self, remaining: usize) -> bool {
+        match self.grouping {
+            Grouping::Thousands => remaining.is_multiple_of(3),
+            Grouping::Indian => remaining == 3 || (remaining > 3 && !remaining.is_multiple_of(2)),
+        }
+    }
+
+    /// 按分组符号拆开的整数部分是否符合本区域的分组方式
+    fn valid_groups(self, groups: &[&str]) -> bool {
+        let Some((first, rest)) = groups.split_first() else {
+            return false;
+        };
+        let (middle, last) = match rest.split_last() {
+            Some((last, middle)) => (middle, *last),
+            None => return true,
+        };
+        match self.grouping {
+            Grouping::Thousands => {
+                (1..=3).contains(&first.len()) && rest.iter().all(|group| group.len() == 3)
+            }
+            Grouping::Indian => {
+                (1..=2).contains(&first.len())
+                    && middle.iter().all(|group| group.len() == 2)
+                    && last.len() == 3
+            }
+        }
+    }
+
+    /// 把一段由数字、分组符号和小数点组成的文本还原为规范数字，不是合法的区域数字时返回 None
+    fn delocalize_number(self, run: &str, depth: usize) -> Option<String> {
+        let (integer, fraction) = match run.split_once(self.decimal) {
+            Some((_, fraction)) if fraction.contains(self.decimal) => return None,
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (run, None),
+        };
+        if fraction.is_some_and(|fraction| fraction.contains(self.group)) {
+            return None;
+        }
+        let groups: Vec<&str> = integer.split(self.group).collect();
+        if groups.len() > 1 {
+            // 分组符号兼作参数分隔符时，括号内的 `1,234,567` 是多个参数，一律不当作分组
+            let ambiguous = self.group == self.argument_separator() && depth > 0;
+            if ambiguous || !self.valid_groups(&groups) {
+                return None;
+            }
+        }
+        let mut number = groups.concat();
+        if let Some(fraction) = fraction {
+            number.push('.');
+            number.push_str(fraction);
+        }
+        Some(number)
+    }
+
+    /// 把区域格式的表达式还原为规范格式：去掉数字中的分组符号，小数点换成 `.`，
+    /// 参数分隔符换成 `,`；函数名和单位名中的数字不受影响
+    pub fn delocalize(self, expr: &str) -> String {
+        let chars: Vec<char> = expr.chars().collect();
+        let is_separator = |c: char| c == self.group || c == self.decimal;
+        let mut result = String::new();
+        let mut depth = 0usize;
+        let mut in_name = false;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if in_name && (c.is_alphanumeric() || c == '_') {
+                result.push(c);
+                i += 1;
+                continue;
+            }
+            in_name = c.is_alphabetic() || c == '_' || c == '°';
+
+            if c.is_ascii_digit() {
+                let mut end = i;
+                while end < chars.len()
+                    && (chars[end].is_ascii_digit()
+                        || (is_separator(chars[end])
+                            && chars.get(end + 1).is_some_and(char::is_ascii_digit)))
+                {
+                    end += 1;
+                }
+                let run: String = chars[i..end].iter().collect();
+                match self.delocalize_number(&run, depth) {
+                    Some(number) => result.push_str(&number),
+                    None => result.push_str(&run),
+                }
+                i = end;
+                continue;
+            }
+
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if c == self.argument_separator() {
+                result.push(',');
+            } else if c == self.decimal {
+                result.push('.');
+            } else {
+                result.push(c);
+            }
+            i += 1;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EN: Locale = LOCALES[0];
+    const DE: Locale = LOCALES[1];
+    const CH: Locale = LOCALES[2];
+    const IN: Locale = LOCALES[3];
+
+    #[test]
+    fn test_group() {
+        assert_eq!(EN.group("1234567.89"), "1,234,567.89");
+        assert_eq!(DE.group(&DE.localize("1234567.89")), "1.234.567,89");
+        assert_eq!(CH.group("-1234567.89"), "-1'234'567.89");
+        assert_eq!(IN.group("1234567"), "12,34,567");
+        assert_eq!(IN.group("123456789"), "12,34,56,789");
+        assert_eq!(IN.group("999"), "999");
+        assert_eq!(EN.group("1.5e21"), "1.5e21");
+        assert_eq!(EN.group("inf"), "inf");
+        assert_eq!(DE.sample(), "1.234.567,8");
+    }
+
+    #[test]
+    fn test_delocalize() {
+        assert_eq!(DE.delocalize("1.234.567,89"), "1234567.89");
+        assert_eq!(CH.delocalize("1'234'567.89*2"), "1234567.89*2");
+        assert_eq!(IN.delocalize("12,34,567+1"), "1234567+1");
+        assert_eq!(EN.delocalize("1,234,567.89"), "1234567.89");
+        assert_eq!(DE.delocalize("max(1,5; 2)"), "max(1.5, 2)");
+        // 参数之间的逗号不是分组符号
+        assert_eq!(EN.delocalize("gcd(12,345)"), "gcd(12,345)");
+        assert_eq!(EN.delocalize("max(1,2,3)"), "max(1,2,3)");
+        assert_eq!(EN.delocalize("max(1, 234)"), "max(1, 234)");
+        assert_eq!(EN.delocalize("mean({100,200,300})"), "mean({100,200,300})");
+        assert_eq!(EN.delocalize("max(100,200,300)"), "max(100,200,300)");
+        assert_eq!(EN.delocalize("{1,234,567}"), "{1,234,567}");
+        assert_eq!(EN.delocalize("powmod(123,456,789)"), "powmod(123,456,789)");
+        assert_eq!(EN.delocalize("max(1,234.5)"), "max(1,234.5)");
+        assert_eq!(DE.delocalize("max(1.234; 5)"), "max(1234, 5)");
+        // 不合法的分组保持原样，名字中的数字不受影响
+        assert_eq!(IN.delocalize("1,234,567"), "1,234,567");
+        assert_eq!(DE.delocalize("log10(1.000)"), "log10(1000)");
+        assert_eq!(DE.delocalize("2026-10-18"), "2026-10-18");
+    }
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::from_tag("de_DE.UTF-8"), DE);
+        assert_eq!(Locale::from_tag("fr_FR"), DE);
+        assert_eq!(Locale::from_tag("de_CH.UTF-8"), CH);
+        assert_eq!(Locale::from_tag("en_IN"), IN);
+        assert_eq!(Locale::from_tag("en_US.UTF-8"), EN);
+        assert_eq!(Locale::from_tag("C"), EN);
+        assert_eq!(EN.next(), DE);
+        assert_eq!(IN.next(), EN);
+        assert_eq!(Locale::named("ch"), Some(CH));
+        assert_eq!(Locale::named("xx"), None);
+    }
+}
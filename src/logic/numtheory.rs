@@ -0,0 +1,412 @@
+//! 数论函数，全部基于任意精度整数
+//!
+//! 素性检验使用前 20 个质数为底的 Miller-Rabin：n < 3.3×10²⁴ 时结果是确定的，
+//! 更大的 n 误判为质数的概率低于 4⁻²⁰。分解先试除小质数，剩余部分用 Pollard-Brent ρ 算法。
+
+use super::{CalcError, Value};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, One, Signed, Zero};
+use std::collections::BTreeMap;
+
+/// Miller-Rabin 的底，同时用于快速排除小因子
+const SMALL_PRIMES: [u32; 20] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71,
+];
+/// 分解时试除的上限，更大的因子交给 Pollard ρ
+const TRIAL_DIVISION_LIMIT: u32 = 10_000;
+/// Brent 算法中累乘多少步后求一次 gcd
+const BRENT_BATCH: u64 = 128;
+/// 一次分解中 Pollard ρ 最多迭代的步数，大约能找到 10¹⁰ 以内的因子，超过时放弃分解，
+/// 避免两个大质数的乘积让求值卡住
+const FACTOR_STEP_LIMIT: u64 = 1 << 17;
+
+/// 判断 n 是否为质数
+pub fn is_prime(n: &BigInt) -> bool {
+    if *n < BigInt::from(2) {
+        return false;
+    }
+    for &p in &SMALL_PRIMES {
+        if *n == BigInt::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+
+    // n - 1 = d·2ˢ，d 为奇数
+    let n_minus_one: BigInt = n - 1;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+    SMALL_PRIMES.iter().all(|&base| {
+        let mut x = BigInt::from(base).modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            return true;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// 大于 n 的最小质数
+pub fn next_prime(n: &BigInt) -> BigInt {
+    let two = BigInt::from(2);
+    if *n < two {
+        return two;
+    }
+    // 从下一个奇数开始逐个检查
+    let mut candidate: BigInt = n + 1;
+    if candidate.is_even() {
+        candidate += 1;
+    }
+    while !is_prime(&candidate) {
+        candidate += 2;
+    }
+    candidate
+}
+
+/// 质因数分解，n ≥ 1，按质数升序返回 (质数, 指数)；n = 1 时为空。
+/// 两个因子都太大、在步数上限内分不开时返回错误
+pub fn factorize(n: &BigInt) -> Result<Vec<(BigInt, u32)>, CalcError> {
+    let mut factors = BTreeMap::new();
+    let mut rest = n.clone();
+
+    let mut divisor = 2u32;
+    while divisor < TRIAL_DIVISION_LIMIT && BigInt::from(divisor * divisor) <= rest {
+        while (&rest % divisor).is_zero() {
+            rest /= divisor;
+            *factors.entry(BigInt::from(divisor)).or_insert(0) += 1;
+        }
+        divisor += if divisor == 2 { 1 } else { 2 };
+    }
+    let mut steps = FACTOR_STEP_LIMIT;
+    split(rest, &mut factors, &mut steps)?;
+
+    Ok(factors.into_iter().collect())
+}
+
+/// 递归拆分 n 并把质因子计入 factors，steps 为剩余的迭代步数
+fn split(n: BigInt, factors: &mut BTreeMap<BigInt, u32>, steps: &mut u64) -> Result<(), CalcError> {
+    if n.is_one() {
+        return Ok(());
+    }
+    if is_prime(&n) {
+        *factors.entry(n).or_insert(0) += 1;
+        return Ok(());
+    }
+    // 换不同的多项式常数直到找到非平凡因子
+    let mut c = 1;
+    let divisor = loop {
+        if let Some(divisor) = pollard_brent(&n, c, steps)? {
+            break divisor;
+        }
+        c += 1;
+    };
+    let quotient = &n / &divisor;
+    split(divisor, factors, steps)?;
+    split(quotient, factors, steps)
+}
+
+/// Pollard ρ 算法的 Brent 变体，迭代 x ↦ x² + c (mod n)，失败时返回 None，
+/// 用完剩余步数时返回错误
+fn pollard_brent(n: &BigInt, c: u64, steps: &mut u64) -> Result<Option<BigInt>, CalcError> {
+    let c = BigInt::from(c);
+    let mut step = |x: &BigInt| {
+        *steps = steps
+            .checked_sub(1)
+            .ok_or_else(|| CalcError::Math("number too large to factor".to_string()))?;
+        Ok::<_, CalcError>((x * x + &c) % n)
+    };
+
+    let mut y = BigInt::from(2);
+    let mut x = y.clone();
+    let mut saved = y.clone();
+    let mut product = BigInt::one();
+    let mut divisor = BigInt::one();
+    let mut cycle_length = 1u64;
+
+    while divisor.is_one() {
+        x = y.clone();
+        for _ in 0..cycle_length {
+            y = step(&y)?;
+        }
+        let mut k = 0;
+        while k < cycle_length && divisor.is_one() {
+            saved = y.clone();
+            for _ in 0..BRENT_BATCH.min(cycle_length - k) {
+                y = step(&y)?;
+                product = product * (&x - &y).abs() % n;
+            }
+            divisor = product.gcd(n);
+            k += BRENT_BATCH;
+        }
+        cycle_length *= 2;
+    }
+
+    if divisor == *n {
+        // 累乘越过了因子，从保存的位置逐步重算
+        loop {
+            saved = step(&saved)?;
+            divisor = (&x - &saved).abs().gcd(n);
+            if !divisor.is_one() {
+                break;
+            }
+        }
+    }
+    Ok((divisor != *n).then_some(divisor))
+}
+
+/// 欧拉函数 φ(n)，n ≥ 1
+pub fn totient(n: &BigInt) -> Result<BigInt, CalcError> {
+    Ok(factorize(n)?
+        .into_iter()
+        .map(|(prime, exponent)| {
+            num_traits::pow(prime.clone(), exponent as usize - 1) * (prime - 1)
+        })
+        .product())
+}
+
+/// 扩展欧几里得算法，返回 (g, x, y) 满足 ax + by = g，g ≥ 0
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+        let next_r = &old_r - &quotient * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &quotient * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &quotient * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// a 模 m 的乘法逆元，m > 0，不互质时返回 None
+pub fn mod_inverse(a: &BigInt, m: &BigInt) -> Option<BigInt> {
+    let (g, x, _) = extended_gcd(a, m);
+    g.is_one().then(|| x.mod_floor(m))
+}
+
+/// 转换为精确整数：整数直接使用，浮点数必须是整数值
+fn to_integer(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Number(num) if num.fract() == 0.0 => BigInt::from_f64(*num),
+        _ => value.as_integer(),
+    }
+}
+
+/// 检查参数为 count 个整数
+fn expect_integers(name: &str, args: &[Value], count: usize) -> Result<Vec<BigInt>, CalcError> {
+    let integers: Option<Vec<BigInt>> = args.iter().map(to_integer).collect();
+    match integers {
+        Some(integers) if integers.len() == count => Ok(integers),
+        _ => Err(CalcError::InvalidArguments(format!(
+            "{} expects {} integer{}",
+            name,
+            count,
+            if count == 1 { "" } else { "s" }
+        ))),
+    }
+}
+
+/// 检查参数为单个正整数
+fn expect_positive(name: &str, args: &[Value]) -> Result<BigInt, CalcError> {
+    let n = expect_integers(name, args, 1)?.remove(0);
+    if !n.is_positive() {
+        return Err(CalcError::InvalidArguments(format!(
+            "{} expects a positive integer",
+            name
+        )));
+    }
+    Ok(n)
+}
+
+/// 检查模数为正
+fn check_modulus(m: &BigInt) -> Result<(), CalcError> {
+    if m.is_positive() {
+        Ok(())
+    } else {
+        Err(CalcError::InvalidArguments(
+            "modulus must be positive".to_string(),
+        ))
+    }
+}
+
+/// 内置函数 isprime(n)，质数返回 1，否则返回 0
+pub fn isprime(args: &[Value]) -> Result<Value, CalcError> {
+    let n = &expect_integers("isprime", args, 1)?[0];
+    Ok(Value::Integer(BigInt::from(is_prime(n) as u8)))
+}
+
+/// 内置函数 factor(n)
+pub fn factor(args: &[Value]) -> Result<Value, CalcError> {
+    Ok(Value::Factors(factorize(&expect_positive(
+        "factor", args,
+    )?)?))
+}
+
+/// 内置函数 nextprime(n)
+pub fn nextprime(args: &[Value]) -> Result<Value, CalcError> {
+    let n = &expect_integers("nextprime", args, 1)?[0];
+    Ok(Value::Integer(next_prime(n)))
+}
+
+/// 内置函数 totient(n)
+pub fn totient_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    Ok(Value::Integer(totient(&expect_positive("totient", args)?)?))
+}
+
+/// 内置函数 powmod(a, b, m)，b 为负数时使用 a 的逆元
+pub fn powmod(args: &[Value]) -> Result<Value, CalcError> {
+    let integers = expect_integers("powmod", args, 3)?;
+    let (a, b, m) = (&integers[0], &integers[1], &integers[2]);
+    check_modulus(m)?;
+    let base = if b.is_negative() {
+        mod_inverse(a, m).ok_or_else(|| no_inverse(a, m))?
+    } else {
+        a.clone()
+    };
+    Ok(Value::Integer(base.modpow(&b.abs(), m)))
+}
+
+/// 不存在逆元时的错误
+fn no_inverse(a: &BigInt, m: &BigInt) -> CalcError {
+    CalcError::Math(format!("{} has no inverse modulo {}", a, m))
+}
+
+/// 内置函数 modinv(a, m)
+pub fn modinv(args: &[Value]) -> Result<Value, CalcError> {
+    let integers = expect_integers("modinv", args, 2)?;
+    let (a, m) = (&integers[0], &integers[1]);
+    check_modulus(m)?;
+    mod_inverse(a, m)
+        .map(Value::Integer)
+        .ok_or_else(|| no_inverse(a, m))
+}
+
+/// 内置函数 gcd(a, b)
+pub fn gcd(args: &[Value]) -> Result<Value, CalcError> {
+    let integers = expect_integers("gcd", args, 2)?;
+    Ok(Value::Integer(integers[0].gcd(&integers[1])))
+}
+
+/// 内置函数 egcd(a, b)，返回 [g, x, y]，满足 ax + by = g
+pub fn egcd(args: &[Value]) -> Result<Value, CalcError> {
+    let integers = expect_integers("egcd", args, 2)?;
+    let (g, x, y) = extended_gcd(&integers[0], &integers[1]);
+    Ok(Value::List(vec![
+        Value::Integer(g),
+        Value::Integer(x),
+        Value::Integer(y),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_primality() {
+        let primes: Vec<u32> = (0..50).filter(|&n| is_prime(&BigInt::from(n))).collect();
+        assert_eq!(
+            primes,
+            [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47]
+        );
+        // Carmichael 数和强伪素数
+        assert!(!is_prime(&BigInt::from(561)));
+        assert!(!is_prime(&big("3215031751")));
+        // 梅森素数 2¹²⁷ - 1
+        assert!(is_prime(&big("170141183460469231731687303715884105727")));
+        assert!(!is_prime(&big("170141183460469231731687303715884105729")));
+        assert_eq!(next_prime(&BigInt::from(13)), BigInt::from(17));
+        assert_eq!(next_prime(&BigInt::from(-5)), BigInt::from(2));
+        assert_eq!(next_prime(&big("1000000000000")), big("1000000000039"));
+    }
+
+    #[test]
+    fn test_factorize() {
+        let to_pairs = |factors: Vec<(BigInt, u32)>| -> Vec<(u64, u32)> {
+            factors
+                .into_iter()
+                .map(|(p, e)| (p.try_into().unwrap(), e))
+                .collect()
+        };
+        assert_eq!(
+            to_pairs(factorize(&BigInt::from(120)).unwrap()),
+            [(2, 3), (3, 1), (5, 1)]
+        );
+        assert!(factorize(&BigInt::one()).unwrap().is_empty());
+        // 两个大质因子需要 Pollard ρ
+        assert_eq!(
+            to_pairs(factorize(&big("1000000016000000063")).unwrap()),
+            [(1_000_000_007, 1), (1_000_000_009, 1)]
+        );
+        // 2⁶⁴ + 1 = 274177 × 67280421310721
+        assert_eq!(
+            to_pairs(factorize(&big("18446744073709551617")).unwrap()),
+            [(274_177, 1), (67_280_421_310_721, 1)]
+        );
+        assert_eq!(totient(&BigInt::from(36)), Ok(BigInt::from(12)));
+        assert_eq!(totient(&BigInt::one()), Ok(BigInt::one()));
+    }
+
+    #[test]
+    fn test_factorize_gives_up() {
+        // 两个 30 位质数的乘积超出步数上限，返回错误而不是一直计算
+        let p = next_prime(&big("100000000000000000000000000000"));
+        let q = next_prime(&p);
+        assert_eq!(
+            factorize(&(&p * &q)),
+            Err(CalcError::Math("number too large to factor".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_modular_arithmetic() {
+        let (g, x, y) = extended_gcd(&BigInt::from(240), &BigInt::from(46));
+        assert_eq!(g, BigInt::from(2));
+        assert_eq!(BigInt::from(240) * x + BigInt::from(46) * y, g);
+        assert_eq!(
+            mod_inverse(&BigInt::from(3), &BigInt::from(11)),
+            Some(BigInt::from(4))
+        );
+        assert_eq!(
+            mod_inverse(&BigInt::from(-3), &BigInt::from(11)),
+            Some(BigInt::from(7))
+        );
+        assert_eq!(mod_inverse(&BigInt::from(6), &BigInt::from(9)), None);
+
+        // 教科书 RSA：p = 61，q = 53，e = 17，d = 2753
+        let args = |a: i64, b: i64, m: i64| [a, b, m].map(|n| Value::Integer(BigInt::from(n)));
+        assert_eq!(
+            powmod(&args(65, 17, 3233)),
+            Ok(Value::Integer(BigInt::from(2790)))
+        );
+        assert_eq!(
+            powmod(&args(2790, 2753, 3233)),
+            Ok(Value::Integer(BigInt::from(65)))
+        );
+        assert_eq!(
+            powmod(&args(3, -1, 11)),
+            Ok(Value::Integer(BigInt::from(4)))
+        );
+        assert!(powmod(&args(3, 2, 0)).is_err());
+        assert!(matches!(powmod(&args(6, -1, 9)), Err(CalcError::Math(_))));
+    }
+}
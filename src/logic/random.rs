@@ -0,0 +1,192 @@
+//! 可设置种子的伪随机数函数
+//!
+//! 使用 SplitMix64 生成器：整个状态就是一个 u64，任意时刻的状态都可以当作种子重新开始，
+//! 因此历史记录只需保存求值前的状态即可原样重放。算法固定在本文件中，不随依赖库版本变化。
+
+use super::{CalcError, Session, Value, expect_numbers};
+use std::f64::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SplitMix64 伪随机数生成器
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// 用给定种子创建生成器
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// 当前状态，作为种子传给 Rng::new 可以得到完全相同的后续序列
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// 下一个 64 位随机整数
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// [0, 1) 上均匀分布的浮点数，取高 53 位
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// [0, bound) 上均匀分布的整数，拒绝采样避免取模偏差
+    pub fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+
+    /// 标准正态分布随机数（Box-Muller 变换）
+    pub fn normal(&mut self) -> f64 {
+        // 1 - u 落在 (0, 1]，避免 ln 0
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+    }
+}
+
+impl Default for Rng {
+    /// 未设置种子时使用当前时间
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Self::new(nanos)
+    }
+}
+
+/// 转换为整数，否则报错
+fn integer(value: f64, name: &str) -> Result<i64, CalcError> {
+    if value.fract() == 0.0 && value.abs() < 2f64.powi(53) {
+        Ok(value as i64)
+    } else {
+        Err(CalcError::InvalidArguments(format!(
+            "{} expects integers",
+            name
+        )))
+    }
+}
+
+/// 内置函数 rand()，返回 [0, 1) 上的均匀随机数
+pub fn rand(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    expect_numbers("rand", args, 0, 0)?;
+    Ok(Value::Number(session.rng.next_f64()))
+}
+
+/// 内置函数 randint(a, b)，返回 [a, b] 上的均匀随机整数
+pub fn randint(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("randint", args, 2, 2)?;
+    let (low, high) = (
+        integer(numbers[0], "randint")?,
+        integer(numbers[1], "randint")?,
+    );
+    if low > high {
+        return Err(CalcError::InvalidArguments(
+            "randint expects a ≤ b".to_string(),
+        ));
+    }
+    let offset = session.rng.below((high - low) as u64 + 1);
+    Ok(Value::Number((low + offset as i64) as f64))
+}
+
+/// 内置函数 randn()，返回标准正态分布随机数
+pub fn randn(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    expect_numbers("randn", args, 0, 0)?;
+    Ok(Value::Number(session.rng.normal()))
+}
+
+/// 内置函数 choose(list)，从列表中等概率取一个元素
+pub fn choose(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    match args {
+        [Value::List(items)] if !items.is_empty() => {
+            let index = session.rng.below(items.len() as u64) as usize;
+            Ok(items[index].clone())
+        }
+        _ => Err(CalcError::InvalidArguments(
+            "choose expects a non-empty list".to_string(),
+        )),
+    }
+}
+
+/// 内置函数 seed(n)，重置会话的随机数种子，返回该种子
+pub fn seed(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    let numbers = expect_numbers("seed", args, 1, 1)?;
+    let seed = integer(numbers[0], "seed")?;
+    if seed < 0 {
+        return Err(CalcError::InvalidArguments(
+            "seed must be non-negative".to_string(),
+        ));
+    }
+    session.rng = Rng::new(seed as u64);
+    Ok(Value::Number(numbers[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible_sequence() {
+        // SplitMix64 参考实现以 0 为种子的前两个输出
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+        // 从任意状态重新开始得到相同的后续序列
+        let mut first = Rng::new(42);
+        first.next_u64();
+        let mut replay = Rng::new(first.state());
+        assert_eq!(first.next_u64(), replay.next_u64());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            assert!(rng.below(6) < 6);
+        }
+        // 大样本的均值和方差接近理论值
+        let samples: Vec<f64> = (0..20000).map(|_| rng.normal()).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!(mean.abs() < 0.05);
+        assert!((variance - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_builtins() {
+        let mut session = Session::default();
+        seed(&mut session, &[Value::Number(42.0)]).unwrap();
+        let first = randint(&mut session, &[Value::Number(1.0), Value::Number(6.0)]).unwrap();
+        seed(&mut session, &[Value::Number(42.0)]).unwrap();
+        let second = randint(&mut session, &[Value::Number(1.0), Value::Number(6.0)]).unwrap();
+        assert_eq!(first, second);
+        let value = first.as_number().unwrap();
+        assert!((1.0..=6.0).contains(&value) && value.fract() == 0.0);
+
+        let list = Value::from(vec![3.0, 5.0]);
+        let chosen = choose(&mut session, std::slice::from_ref(&list)).unwrap();
+        assert!(chosen == Value::Number(3.0) || chosen == Value::Number(5.0));
+
+        assert!(randint(&mut session, &[Value::Number(3.0), Value::Number(1.0)]).is_err());
+        assert!(choose(&mut session, &[Value::List(Vec::new())]).is_err());
+        assert!(seed(&mut session, &[Value::Number(-1.0)]).is_err());
+        assert!(rand(&mut session, &[Value::Number(1.0)]).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use super::linalg;
+use super::{CalcError, Session, Value};
+use std::fmt;
+
+/// 回归模型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Model {
+    /// y = a + bx
+    Linear,
+    /// y = c₀ + c₁x + … + cₙxⁿ，包含多项式次数
+    Polynomial(usize),
+    /// y = a·eᵇˣ（要求 y > 0）
+    Exponential,
+    /// y = a + b·ln x（要求 x > 0）
+    Logarithmic,
+}
+
+impl Model {
+    /// 拟合所需的最少数据点数
+    pub fn min_points(self) -> usize {
+        match self {
+            Model::Polynomial(degree) => degree + 1,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Model::Linear => write!(f, "Linear"),
+            Model::Polynomial(degree) => write!(f, "Polynomial (degree {})", degree),
+            Model::Exponential => write!(f, "Exponential"),
+            Model::Logarithmic => write!(f, "Logarithmic"),
+        }
+    }
+}
+
+/// 一次拟合的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fit {
+    pub model: Model,
+    /// 线性、指数和对数模型为 [a, b]，多项式为 [c₀, c₁, …, cₙ]
+    pub coefficients: Vec<f64>,
+    /// 决定系数，按原始 y 值计算（指数和对数模型也不取对数）
+    pub r_squared: f64,
+}
+
+impl Fit {
+    /// 用拟合的模型计算 x 处的预测值
+    pub fn predict(&self, x: f64) -> f64 {
+        let c = &self.coefficients;
+        match self.model {
+            Model::Linear | Model::Polynomial(_) => {
+                // Horner 法则
+                c.iter()
+                    .rev()
+                    .fold(0.0, |acc, &coefficient| acc * x + coefficient)
+            }
+            Model::Exponential => c[0] * (c[1] * x).exp(),
+            Model::Logarithmic => c[0] + c[1] * x.ln(),
+        }
+    }
+
+    /// 拟合方程的文字表示
+    pub fn equation(&self) -> String {
+        let c = &self.coefficients;
+        match self.model {
+            Model::Linear => format!("y = {} + {}x", c[0], c[1]),
+            Model::Polynomial(_) => {
+                let terms: Vec<String> = c
+                    .iter()
+                    .enumerate()
+                    .map(|(power, coefficient)| match power {
+                        0 => format!("{}", coefficient),
+                        1 => format!("{}x", coefficient),
+                        _ => format!("{}x^{}", coefficient, power),
+                    })
+                    .collect();
+                format!("y = {}", terms.join(" + "))
+            }
+            Model::Exponential => format!("y = {}·e^({}x)", c[0], c[1]),
+            Model::Logarithmic => format!("y = {} + {}·ln x", c[0], c[1]),
+        }
+    }
+}
+
+/// 最小二乘拟合 (xs, ys) 数据
+pub fn fit(model: Model, xs: &[f64], ys: &[f64]) -> Result<Fit, CalcError> {
+    if xs.len() != ys.len() {
+        return Err(CalcError::InvalidArguments(
+            "x and y lists must have the same length".to_string(),
+        ));
+    }
+    if xs.len() < model.min_points() {
+        return Err(CalcError::InvalidArguments(format!(
+            "{} regression needs at least {} points",
+            model,
+            model.min_points()
+        )));
+    }
+
+    // 把各模型转换为关于系数的线性最小二乘问题：设计矩阵的每行是基函数取值
+    let (design, targets): (Vec<Vec<f64>>, Vec<f64>) = match model {
+        Model::Linear | Model::Polynomial(_) => {
+            let degree = match model {
+                Model::Polynomial(degree) => degree,
+                _ => 1,
+            };
+            let design = xs
+                .iter()
+                .map(|&x| (0..=degree).map(|power| x.powi(power as i32)).collect())
+                .collect();
+            (design, ys.to_vec())
+        }
+        Model::Exponential => {
+            if ys.iter().any(|&y| y <= 0.0) {
+                return Err(CalcError::InvalidArguments(
+                    "exponential regression needs y > 0".to_string(),
+                ));
+            }
+            let design = xs.iter().map(|&x| vec![1.0, x]).collect();
+            (design, ys.iter().map(|y| y.ln()).collect())
+        }
+        Model::Logarithmic => {
+            if xs.iter().any(|&x| x <= 0.0) {
+                return Err(CalcError::InvalidArguments(
+                    "logarithmic regression needs x > 0".to_string(),
+                ));
+            }
+            let design = xs.iter().map(|&x| vec![1.0, x.ln()]).collect();
+            (design, ys.to_vec())
+        }
+    };
+
+    let mut coefficients = linalg::least_squares(&design, &targets).map_err(|_| {
+        CalcError::Math("Regression failed: x values do not determine a unique fit".to_string())
+    })?;
+    if model == Model::Exponential {
+        // ln y = ln a + bx
+        coefficients[0] = coefficients[0].exp();
+    }
+
+    let mut fit = Fit {
+        model,
+        coefficients,
+        r_squared: 0.0,
+    };
+    fit.r_squared = r_squared(&fit, xs, ys);
+    Ok(fit)
+}
+
+/// 决定系数 R² = 1 - SS_res / SS_tot
+fn r_squared(fit: &Fit, xs: &[f64], ys: &[f64]) -> f64 {
+    let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+    let ss_total: f64 = ys.iter().map(|y| (y - mean).powi(2)).sum();
+    let ss_residual: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(&x, &y)| (y - fit.predict(x)).powi(2))
+        .sum();
+    if ss_total == 0.0 {
+        // 所有 y 相同：完全拟合时视为 1
+        return if ss_residual <= f64::EPSILON {
+            1.0
+        } else {
+            0.0
+        };
+    }
+    1.0 - ss_residual / ss_total
+}
+
+/// 解析 (xs, ys) 参数对
+fn paired_lists(name: &str, args: &[Value]) -> Result<(Vec<f64>, Vec<f64>), CalcError> {
+    match args {
+        [xs, ys] => xs.as_vector().zip(ys.as_vector()),
+        _ => None,
+    }
+    .ok_or_else(|| CalcError::InvalidArguments(format!("{} expects an x list and a y list", name)))
+}
+
+/// 拟合并保存到会话中，返回系数列表
+fn fit_into_session(
+    session: &mut Session,
+    model: Model,
+    (xs, ys): (Vec<f64>, Vec<f64>),
+) -> Result<Value, CalcError> {
+    let fit = fit(model, &xs, &ys)?;
+    let coefficients = Value::from(fit.coefficients.clone());
+    session.last_fit = Some(fit);
+    Ok(coefficients)
+}
+
+/// 内置函数 linreg(xs, ys)
+pub fn linreg(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    fit_into_session(session, Model::Linear, paired_lists("linreg", args)?)
+}
+
+/// 内置函数 polyreg(xs, ys, n)
+pub fn polyreg(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    let degree = match args.get(2).and_then(Value::as_number) {
+        Some(n) if args.len() == 3 && n >= 1.0 && n.fract() == 0.0 => n as usize,
+        _ => {
+            return Err(CalcError::InvalidArguments(
+                "polyreg expects an x list, a y list and a positive integer degree".to_string(),
+            ));
+        }
+    };
+    let data = paired_lists("polyreg", &args[..2])?;
+    fit_into_session(session, Model::Polynomial(degree), data)
+}
+
+/// 内置函数 expreg(xs, ys)
+pub fn expreg(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    fit_into_session(session, Model::Exponential, paired_lists("expreg", args)?)
+}
+
+/// 内置函数 logreg(xs, ys)
+pub fn logreg(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    fit_into_session(session, Model::Logarithmic, paired_lists("logreg", args)?)
+}
+
+/// 内置函数 predict(x)，使用最近一次拟合
+pub fn predict(session: &mut Session, args: &[Value]) -> Result<Value, CalcError> {
+    let fit = session
+        .last_fit
+        .as_ref()
+        .ok_or_else(|| CalcError::Math("No regression fit yet".to_string()))?;
+    match args.first().and_then(Value::as_number) {
+        Some(x) if args.len() == 1 => Ok(Value::Number(fit.predict(x))),
+        _ => Err(CalcError::InvalidArguments(
+            "predict expects one number".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_linear_fit() {
+        let xs = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let ys = [2.0, 4.0, 5.0, 4.0, 5.0];
+        let fit = fit(Model::Linear, &xs, &ys).unwrap();
+        assert_close(fit.coefficients[0], 2.2);
+        assert_close(fit.coefficients[1], 0.6);
+        assert_close(fit.r_squared, 0.6);
+        assert_close(fit.predict(6.0), 5.8);
+    }
+
+    #[test]
+    fn test_polynomial_fit() {
+        // y = 1 - 2x + 3x²
+        let xs = [-2.0, -1.0, 0.0, 1.0, 2.0, 3.0];
+        let ys: Vec<f64> = xs.iter().map(|x| 1.0 - 2.0 * x + 3.0 * x * x).collect();
+        let fit = fit(Model::Polynomial(2), &xs, &ys).unwrap();
+        for (actual, expected) in fit.coefficients.iter().zip([1.0, -2.0, 3.0]) {
+            assert_close(*actual, expected);
+        }
+        assert_close(fit.r_squared, 1.0);
+        assert_close(fit.predict(4.0), 41.0);
+    }
+
+    #[test]
+    fn test_exponential_and_logarithmic_fit() {
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        let ys: Vec<f64> = xs.iter().map(|x: &f64| 2.0 * (0.5 * x).exp()).collect();
+        let fit = fit(Model::Exponential, &xs, &ys).unwrap();
+        assert_close(fit.coefficients[0], 2.0);
+        assert_close(fit.coefficients[1], 0.5);
+        assert_close(fit.r_squared, 1.0);
+
+        let xs = [1.0, 2.0, 4.0, 8.0];
+        let ys: Vec<f64> = xs.iter().map(|x: &f64| 3.0 + 2.0 * x.ln()).collect();
+        let fit = super::fit(Model::Logarithmic, &xs, &ys).unwrap();
+        assert_close(fit.coefficients[0], 3.0);
+        assert_close(fit.coefficients[1], 2.0);
+    }
+
+    #[test]
+    fn test_fit_errors() {
+        assert!(fit(Model::Linear, &[1.0], &[1.0]).is_err());
+        assert!(fit(Model::Linear, &[1.0, 2.0], &[1.0]).is_err());
+        assert!(fit(Model::Polynomial(3), &[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]).is_err());
+        assert!(fit(Model::Exponential, &[1.0, 2.0], &[1.0, -1.0]).is_err());
+        assert!(fit(Model::Logarithmic, &[0.0, 2.0], &[1.0, 1.0]).is_err());
+        // 所有 x 相同，无法确定斜率
+        assert!(matches!(
+            fit(Model::Linear, &[1.0, 1.0, 1.0], &[1.0, 2.0, 3.0]),
+            Err(CalcError::Math(_))
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+//! 零售计算：税、加价、折扣链、小费和分摊
+//!
+//! 零售表达式由一个基础金额和若干步骤组成，从左到右依次作用在当前金额上：
+//!
+//! - `200-10%-5%`：折扣链，先减 10% 再减 5%（不是一次减 15%），`+8%` 为加价
+//! - `100+tax`、`108-tax`：按记忆的税率加税、扣税
+//! - `80mu25`：成本加价 25%；`80mg20`：按 20% 毛利率定价
+//! - `100tip15`：加 15% 小费；`115split3`：三人分摊
+//!
+//! 百分数后的 `%` 可以省略，步骤之间可以写 `then`。每一步的金额四舍五入到分，
+//! 小费和分摊的取整方式由 [`Rounding`] 决定。
+
+use super::finance::Cents;
+use super::{CalcError, evaluate};
+use serde::{Deserialize, Serialize};
+
+/// 分摊允许的最多人数
+const MAX_PEOPLE: u32 = 1000;
+
+/// 小费和分摊的取整方式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Rounding {
+    /// 四舍五入到分，分摊时余下的分由前几个人各多付一分，合计恰好等于总额
+    #[default]
+    Cent,
+    /// 每人份额向上取整到分，合计不少于总额
+    Up,
+    /// 小费使总额向上取整到整数，每人份额也向上取整到整数
+    Whole,
+}
+
+impl Rounding {
+    /// 下一种取整方式，供按键循环切换
+    pub fn next(self) -> Self {
+        match self {
+            Rounding::Cent => Rounding::Up,
+            Rounding::Up => Rounding::Whole,
+            Rounding::Whole => Rounding::Cent,
+        }
+    }
+
+    /// 按键和状态栏上显示的名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Rounding::Cent => "0.01",
+            Rounding::Up => "↑0.01",
+            Rounding::Whole => "↑1",
+        }
+    }
+}
+
+/// 零售模式的设置
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// 记忆的税率（百分数）
+    pub tax_rate: f64,
+    /// 小费和分摊的取整方式
+    pub rounding: Rounding,
+}
+
+/// 零售表达式的一个步骤
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    /// 按百分比加价，负数为折扣
+    Percent(f64),
+    /// 按记忆的税率加税
+    AddTax,
+    /// 从含税金额中扣除税
+    RemoveTax,
+    /// 成本加价百分比
+    Markup(f64),
+    /// 按毛利率定价
+    Margin(f64),
+    /// 小费百分比
+    Tip(f64),
+    /// 分摊人数
+    Split(u32),
+}
+
+/// 计算明细：每一步一行，结果为最终金额（分摊时为每人最多付的金额）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakdown {
+    /// 显示在结果上方的明细行
+    pub lines: Vec<String>,
+    /// 最终金额
+    pub result: Cents,
+}
+
+/// 解析开头的非负数字，返回数值和剩余文本
+fn parse_number(text: &str) -> Option<(f64, &str)> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let number = text[..end].parse().ok()?;
+    Some((number, &text[end..]))
+}
+
+/// 解析百分数，`%` 可以省略
+fn parse_percent(text: &str) -> Option<(f64, &str)> {
+    let (number, rest) = parse_number(text.trim_start())?;
+    Some((number, rest.strip_prefix('%').unwrap_or(rest)))
+}
+
+/// 解析全部步骤，有无法识别的内容时返回 None
+fn parse_steps(mut text: &str) -> Option<Vec<Step>> {
+    let mut steps = Vec::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return (!steps.is_empty()).then_some(steps);
+        }
+        if let Some(rest) = text.strip_prefix("then") {
+            if steps.is_empty() {
+                return None;
+            }
+            text = rest;
+            continue;
+        }
+        let (step, rest) = if let Some(rest) = text.strip_prefix("+tax") {
+            (Step::AddTax, rest)
+        } else if let Some(rest) = text.strip_prefix("-tax") {
+            (Step::RemoveTax, rest)
+        } else if let Some(rest) = text.strip_prefix("mu") {
+            let (percent, rest) = parse_percent(rest)?;
+            (Step::Markup(percent), rest)
+        } else if let Some(rest) = text.strip_prefix("mg") {
+            let (percent, rest) = parse_percent(rest)?;
+            (Step::Margin(percent), rest)
+        } else if let Some(rest) = text.strip_prefix("tip") {
+            let (percent, rest) = parse_percent(rest)?;
+            (Step::Tip(percent), rest)
+        } else if let Some(rest) = text.strip_prefix("split") {
+            let (people, rest) = parse_number(rest.trim_start())?;
+            if people.fract() != 0.0 || !(1.0..=MAX_PEOPLE as f64).contains(&people) {
+                return None;
+            }
+            (Step::Split(people as u32), rest)
+        } else {
+            let sign = match text.chars().next()? {
+                '+' => 1.0,
+                '-' => -1.0,
+                _ => return None,
+            };
+            let (percent, rest) = parse_number(text[1..].trim_start())?;
+            (Step::Percent(sign * percent), rest.strip_prefix('%')?)
+        };
+        steps.push(step);
+        text = rest;
+    }
+}
+
+/// 拆分零售表达式：返回基础金额文本和步骤，不是零售表达式时返回 None
+fn split(expr: &str) -> Option<(&str, Vec<Step>)> {
+    expr.char_indices()
+        .filter(|&(i, c)| i > 0 && (c == '+' || c == '-' || c.is_ascii_lowercase()))
+        .find_map(|(i, _)| {
+            let base = expr[..i].trim();
+            let steps = parse_steps(&expr[i..])?;
+            (!base.is_empty()).then_some((base, steps))
+        })
+}
+
+/// 金额乘以比例，四舍五入到分
+fn scale(amount: Cents, factor: f64) -> Result<Cents, CalcError> {
+    Cents::round(amount.to_f64() * factor)
+        .ok_or_else(|| CalcError::Math("Amount is out of range".to_string()))
+}
+
+/// 带符号的金额，正数前加 +
+fn signed(amount: Cents) -> String {
+    if amount.0 < 0 {
+        amount.to_string()
+    } else {
+        format!("+{}", amount)
+    }
+}
+
+/// 向上取整的整数除法
+fn ceil_div(a: i64, b: i64) -> i64 {
+    -(-a).div_euclid(b)
+}
+
+/// 把金额向上取整到 unit 分的整数倍
+fn ceil_to(amount: Cents, unit: i64) -> Cents {
+    Cents(ceil_div(amount.0, unit) * unit)
+}
+
+/// 按取整方式把总额分摊给 people 人，返回 (份额, 人数) 列表，份额从大到小
+fn shares(total: Cents, people: u32, rounding: Rounding) -> Vec<(Cents, u32)> {
+    let n = people as i64;
+    match rounding {
+        Rounding::Cent => {
+            let (share, extra) = (total.0.div_euclid(n), total.0.rem_euclid(n) as u32);
+            [(Cents(share + 1), extra), (Cents(share), people - extra)]
+                .into_iter()
+                .filter(|&(_, count)| count > 0)
+                .collect()
+        }
+        Rounding::Up => vec![(Cents(ceil_div(total.0, n)), people)],
+        Rounding::Whole => vec![(ceil_to(Cents(ceil_div(total.0, n)), 100), people)],
+    }
+}
+
+/// 依次执行步骤，生成计算明细
+fn run(base: Cents, steps: &[Step], settings: &Settings) -> Result<Breakdown, CalcError> {
+    let mut amount = base;
+    let mut lines = vec![format!("Amount {}", base)];
+    let mut result = None;
+    let tax = settings.tax_rate;
+
+    for &step in steps {
+        if result.is_some() {
+            return Err(CalcError::InvalidArguments(
+                "split must be the last step".to_string(),
+            ));
+        }
+        let (label, next) = match step {
+            Step::Percent(percent) => (
+                format!(
+                    "{}{}%",
+                    if percent < 0.0 { "-" } else { "+" },
+                    percent.abs()
+                ),
+                scale(amount, 1.0 + percent / 100.0)?,
+            ),
+            Step::AddTax => (format!("Tax {}%", tax), scale(amount, 1.0 + tax / 100.0)?),
+            Step::RemoveTax => (
+                format!("Excl. tax {}%", tax),
+                scale(amount, 1.0 / (1.0 + tax / 100.0))?,
+            ),
+            Step::Markup(percent) => (
+                format!("Markup {}%", percent),
+                scale(amount, 1.0 + percent / 100.0)?,
+            ),
+            Step::Margin(percent) if percent < 100.0 => (
+                format!("Margin {}%", percent),
+                scale(amount, 1.0 / (1.0 - percent / 100.0))?,
+            ),
+            Step::Margin(_) => {
+                return Err(CalcError::Math("Margin must be below 100%".to_string()));
+            }
+            Step::Tip(percent) => {
+                let total = scale(amount, 1.0 + percent / 100.0)?;
+                let total = match settings.rounding {
+                    Rounding::Whole => ceil_to(total, 100),
+                    Rounding::Cent | Rounding::Up => total,
+                };
+                (format!("Tip {}%", percent), total)
+            }
+            Step::Split(people) => {
+                let shares = shares(amount, people, settings.rounding);
+                let parts: Vec<String> = shares
+                    .iter()
+                    .map(|(share, count)| format!("{} × {}", count, share))
+                    .collect();
+                lines.push(format!("Split {}: {}", people, parts.join(", ")));
+                result = Some(shares[0].0);
+                continue;
+            }
+        };
+        lines.push(format!(
+            "{} {} → {}",
+            label,
+            signed(Cents(next.0 - amount.0)),
+            next
+        ));
+        amount = next;
+    }
+
+    Ok(Breakdown {
+        lines,
+        result: result.unwrap_or(amount),
+    })
+}
+
+/// 按零售规则计算表达式：不是零售表达式时返回 None，由普通求值处理
+pub fn evaluate_retail(expr: &str, settings: &Settings) -> Option<Result<Breakdown, CalcError>> {
+    let (base, steps) = split(expr)?;
+    Some(
+        evaluate(base)
+            .and_then(|value| {
+                value.as_number().and_then(Cents::round).ok_or_else(|| {
+                    CalcError::InvalidArguments("Amount must be a number".to_string())
+                })
+            })
+            .and_then(|base| run(base, &steps, settings)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(expr: &str, settings: &Settings) -> Vec<String> {
+        evaluate_retail(expr, settings).unwrap().unwrap().lines
+    }
+
+    fn result(expr: &str, settings: &Settings) -> Cents {
+        evaluate_retail(expr, settings).unwrap().unwrap().result
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse_steps("-10%then-5%"),
+            Some(vec![Step::Percent(-10.0), Step::Percent(-5.0)])
+        );
+        assert_eq!(
+            parse_steps("tip 15% split 3"),
+            Some(vec![Step::Tip(15.0), Step::Split(3)])
+        );
+        assert_eq!(parse_steps("+tax"), Some(vec![Step::AddTax]));
+        assert_eq!(parse_steps("+5"), None);
+        assert_eq!(parse_steps("split 0"), None);
+        assert!(split("(100+20)-10%").is_some_and(|(base, _)| base == "(100+20)"));
+        // 没有零售步骤的表达式交给普通求值
+        assert_eq!(split("3+4"), None);
+        assert_eq!(split("50%"), None);
+        assert_eq!(split("sin(30)"), None);
+    }
+
+    #[test]
+    fn test_discount_chain() {
+        let settings = Settings::default();
+        assert_eq!(result("200-10%-5%", &settings), Cents(17100));
+        assert_eq!(
+            lines("200-10% then -5%", &settings),
+            [
+                "Amount 200.00",
+                "-10% -20.00 → 180.00",
+                "-5% -9.00 → 171.00"
+            ]
+        );
+        assert_eq!(result("19.99+8%", &settings), Cents(2159));
+    }
+
+    #[test]
+    fn test_tax() {
+        let settings = Settings {
+            tax_rate: 8.0,
+            ..Settings::default()
+        };
+        assert_eq!(result("100+tax", &settings), Cents(10800));
+        assert_eq!(result("108-tax", &settings), Cents(10000));
+        assert_eq!(
+            lines("50-10%+tax", &settings),
+            ["Amount 50.00", "-10% -5.00 → 45.00", "Tax 8% +3.60 → 48.60"]
+        );
+    }
+
+    #[test]
+    fn test_markup_margin() {
+        let settings = Settings::default();
+        assert_eq!(result("80mu25", &settings), Cents(10000));
+        assert_eq!(result("80mg20%", &settings), Cents(10000));
+        assert!(evaluate_retail("80mg100", &settings).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_tip_split() {
+        let mut settings = Settings::default();
+        assert_eq!(result("100tip15", &settings), Cents(11500));
+        assert_eq!(
+            lines("100split3", &settings),
+            ["Amount 100.00", "Split 3: 1 × 33.34, 2 × 33.33"]
+        );
+        assert_eq!(result("100split3", &settings), Cents(3334));
+
+        settings.rounding = Rounding::Up;
+        assert_eq!(lines("100split3", &settings)[1], "Split 3: 3 × 33.34");
+        assert_eq!(result("99split3", &settings), Cents(3300));
+
+        settings.rounding = Rounding::Whole;
+        assert_eq!(
+            lines("87.30tip15split4", &settings),
+            [
+                "Amount 87.30",
+                "Tip 15% +13.70 → 101.00",
+                "Split 4: 4 × 26.00"
+            ]
+        );
+        assert!(
+            evaluate_retail("100split2tip10", &settings)
+                .unwrap()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use super::{CalcError, Value};
+
+/// 将参数展开为数值序列，支持 mean(1,2,3) 和 mean({1,2,3}) 两种写法
+pub fn flatten_numbers(args: &[Value]) -> Vec<f64> {
+    let mut numbers = Vec::new();
+    for arg in args {
+        match arg {
+            Value::List(items) => numbers.extend(flatten_numbers(items)),
+            _ => numbers.extend(arg.as_number()),
+        }
+    }
+    numbers
+}
+
+/// 求和
+pub fn sum(data: &[f64]) -> f64 {
+    data.iter().sum()
+}
+
+/// 算术平均值
+pub fn mean(data: &[f64]) -> Option<f64> {
+    (!data.is_empty()).then(|| sum(data) / data.len() as f64)
+}
+
+/// 返回升序排列的副本
+fn sorted(data: &[f64]) -> Vec<f64> {
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+/// 中位数
+pub fn median(data: &[f64]) -> Option<f64> {
+    quantile(data, 0.5)
+}
+
+/// 分位数，p 取 0 到 1，在相邻两个值之间线性插值（与 Excel QUARTILE.INC / R type 7 相同）
+pub fn quantile(data: &[f64], p: f64) -> Option<f64> {
+    if data.is_empty() || !(0.0..=1.0).contains(&p) {
+        return None;
+    }
+    let sorted = sorted(data);
+    let position = p * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f64;
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * fraction)
+}
+
+/// 众数，出现次数并列最多的值全部返回（升序）
+pub fn mode(data: &[f64]) -> Vec<f64> {
+    let sorted = sorted(data);
+    let mut modes = Vec::new();
+    let mut best_count = 0;
+    let mut i = 0;
+    while i < sorted.len() {
+        let count = sorted[i..].iter().take_while(|&&v| v == sorted[i]).count();
+        if count > best_count {
+            best_count = count;
+            modes.clear();
+        }
+        if count == best_count {
+            modes.push(sorted[i]);
+        }
+        i += count;
+    }
+    modes
+}
+
+/// 方差，sample 为 true 时使用样本方差（除以 n-1），否则为总体方差（除以 n）
+pub fn variance(data: &[f64], sample: bool) -> Option<f64> {
+    let n = data.len();
+    if n == 0 || (sample && n < 2) {
+        return None;
+    }
+    let mean = mean(data)?;
+    let squares: f64 = data.iter().map(|x| (x - mean).powi(2)).sum();
+    Some(squares / if sample { n - 1 } else { n } as f64)
+}
+
+/// 标准差，sample 含义同 variance
+pub fn stdev(data: &[f64], sample: bool) -> Option<f64> {
+    variance(data, sample).map(f64::sqrt)
+}
+
+/// 最小值
+pub fn min(data: &[f64]) -> Option<f64> {
+    data.iter().copied().reduce(f64::min)
+}
+
+/// 最大值
+pub fn max(data: &[f64]) -> Option<f64> {
+    data.iter().copied().reduce(f64::max)
+}
+
+/// 数据集的汇总统计，用于数据面板显示
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub sum: f64,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub mode: Vec<f64>,
+    pub sample_stdev: Option<f64>,
+    pub population_stdev: Option<f64>,
+    pub sample_variance: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub q1: Option<f64>,
+    pub q3: Option<f64>,
+}
+
+impl Summary {
+    /// 计算数据集的全部汇总统计
+    pub fn of(data: &[f64]) -> Self {
+        Self {
+            count: data.len(),
+            sum: sum(data),
+            mean: mean(data),
+            median: median(data),
+            mode: mode(data),
+            sample_stdev: stdev(data, true),
+            population_stdev: stdev(data, false),
+            sample_variance: variance(data, true),
+            min: min(data),
+            max: max(data),
+            q1: quantile(data, 0.25),
+            q3: quantile(data, 0.75),
+        }
+    }
+}
+
+/// 将统计结果转换为函数返回值，数据不足时报错
+fn require(result: Option<f64>, name: &str) -> Result<Value, CalcError> {
+    result
+        .map(Value::Number)
+        .ok_or_else(|| CalcError::InvalidArguments(format!("{} needs more data", name)))
+}
+
+/// 内置函数 sum(...)
+pub fn sum_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    Ok(Value::Number(sum(&flatten_numbers(args))))
+}
+
+/// 内置函数 mean(...)
+pub fn mean_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(mean(&flatten_numbers(args)), "mean")
+}
+
+/// 内置函数 median(...)
+pub fn median_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(median(&flatten_numbers(args)), "median")
+}
+
+/// 内置函数 mode(...)，只有一个众数时返回数值，否则返回列表
+pub fn mode_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    match mode(&flatten_numbers(args)).as_slice() {
+        [] => require(None, "mode"),
+        [single] => Ok(Value::Number(*single)),
+        modes => Ok(Value::from(modes.to_vec())),
+    }
+}
+
+/// 内置函数 stdev(...)（样本标准差）
+pub fn stdev_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(stdev(&flatten_numbers(args), true), "stdev")
+}
+
+/// 内置函数 stdevp(...)（总体标准差）
+pub fn stdevp_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(stdev(&flatten_numbers(args), false), "stdevp")
+}
+
+/// 内置函数 var(...)（样本方差）
+pub fn var_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(variance(&flatten_numbers(args), true), "var")
+}
+
+/// 内置函数 varp(...)（总体方差）
+pub fn varp_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(variance(&flatten_numbers(args), false), "varp")
+}
+
+/// 内置函数 min(...)
+pub fn min_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(min(&flatten_numbers(args)), "min")
+}
+
+/// 内置函数 max(...)
+pub fn max_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    require(max(&flatten_numbers(args)), "max")
+}
+
+/// 内置函数 quantile(list, p)
+pub fn quantile_builtin(args: &[Value]) -> Result<Value, CalcError> {
+    let Some((data, p)) = (match args {
+        [data, p] => p.as_number().map(|p| (data, p)),
+        _ => None,
+    }) else {
+        return Err(CalcError::InvalidArguments(
+            "quantile expects a list and a probability".to_string(),
+        ));
+    };
+    if !(0.0..=1.0).contains(&p) {
+        return Err(CalcError::InvalidArguments(
+            "quantile probability must be between 0 and 1".to_string(),
+        ));
+    }
+    require(
+        quantile(&flatten_numbers(std::slice::from_ref(data)), p),
+        "quantile",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: [f64; 8] = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+    #[test]
+    fn test_central_tendency() {
+        assert_eq!(sum(&DATA), 40.0);
+        assert_eq!(mean(&DATA), Some(5.0));
+        assert_eq!(median(&DATA), Some(4.5));
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(mode(&DATA), vec![4.0]);
+        assert_eq!(mode(&[1.0, 2.0, 2.0, 3.0, 3.0]), vec![2.0, 3.0]);
+        assert_eq!(mean(&[]), None);
+    }
+
+    #[test]
+    fn test_spread() {
+        assert_eq!(variance(&DATA, false), Some(4.0));
+        assert_eq!(stdev(&DATA, false), Some(2.0));
+        assert!((variance(&DATA, true).unwrap() - 32.0 / 7.0).abs() < 1e-12);
+        assert_eq!(variance(&[1.0], true), None);
+        assert_eq!(variance(&[1.0], false), Some(0.0));
+        assert_eq!(min(&DATA), Some(2.0));
+        assert_eq!(max(&DATA), Some(9.0));
+    }
+
+    #[test]
+    fn test_quantile() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(quantile(&data, 0.0), Some(1.0));
+        assert_eq!(quantile(&data, 0.25), Some(2.0));
+        assert_eq!(quantile(&data, 1.0), Some(5.0));
+        assert_eq!(quantile(&[1.0, 2.0], 0.3), Some(1.3));
+        assert_eq!(quantile(&data, 1.5), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::of(&DATA);
+        assert_eq!(summary.count, 8);
+        assert_eq!(summary.population_stdev, Some(2.0));
+        assert_eq!(summary.q1, Some(4.0));
+        assert_eq!(summary.q3, Some(5.5));
+
+        let empty = Summary::of(&[]);
+        assert_eq!(empty.count, 0);
+        assert_eq!(empty.mean, None);
+        assert!(empty.mode.is_empty());
+    }
+}
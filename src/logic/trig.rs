@@ -0,0 +1,188 @@
+use super::{CalcError, Value, defined, expect_numbers};
+
+/// 单参数函数的公共实现：检查参数并在结果为 NaN 时报告定义域错误
+fn unary(name: &str, args: &[Value], function: fn(f64) -> f64) -> Result<Value, CalcError> {
+    let x = expect_numbers(name, args, 1, 1)?[0];
+    defined(name, function(x))
+}
+
+/// [-45°, 45°] 内角度的 (sin, cos)，0°、±30°、±45° 直接给出精确值
+fn sin_cos_offset(offset: f64) -> (f64, f64) {
+    let sign = offset.signum();
+    match offset.abs() {
+        0.0 => (0.0, 1.0),
+        30.0 => (sign * 0.5, 3.0_f64.sqrt() / 2.0),
+        45.0 => (
+            sign * std::f64::consts::FRAC_1_SQRT_2,
+            std::f64::consts::FRAC_1_SQRT_2,
+        ),
+        _ => offset.to_radians().sin_cos(),
+    }
+}
+
+/// 角度制的 (sin, cos)，30° 和 45° 的整数倍处结果精确
+fn sin_cos_degrees(degrees: f64) -> (f64, f64) {
+    // 先在角度制下把角度约化到 [-45°, 45°] 再换算为弧度，避免 sin(π) ≠ 0 这类误差
+    let reduced = degrees % 360.0;
+    let quadrant = (reduced / 90.0).round();
+    let (sin, cos) = sin_cos_offset(reduced - quadrant * 90.0);
+    let (sin, cos) = match quadrant.rem_euclid(4.0) as u8 {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    };
+    // 加 0 把 -0 规范为 0，避免显示为 "-0"
+    (sin + 0.0, cos + 0.0)
+}
+
+/// 角度制正弦，30° 和 45° 的整数倍处结果精确
+pub fn sin_degrees(degrees: f64) -> f64 {
+    sin_cos_degrees(degrees).0
+}
+
+/// 角度制余弦，30° 和 45° 的整数倍处结果精确
+pub fn cos_degrees(degrees: f64) -> f64 {
+    sin_cos_degrees(degrees).1
+}
+
+/// 角度制正切，45° 的整数倍处结果精确，90° 的奇数倍处没有定义
+pub fn tan_degrees(degrees: f64) -> f64 {
+    let (sin, cos) = sin_cos_degrees(degrees);
+    if cos == 0.0 { f64::NAN } else { sin / cos }
+}
+
+/// 内置函数 sin(x)，x 为弧度
+pub fn sin(args: &[Value]) -> Result<Value, CalcError> {
+    unary("sin", args, f64::sin)
+}
+
+/// 内置函数 cos(x)
+pub fn cos(args: &[Value]) -> Result<Value, CalcError> {
+    unary("cos", args, f64::cos)
+}
+
+/// 内置函数 tan(x)
+pub fn tan(args: &[Value]) -> Result<Value, CalcError> {
+    unary("tan", args, f64::tan)
+}
+
+/// 内置函数 sec(x) = 1/cos(x)
+pub fn sec(args: &[Value]) -> Result<Value, CalcError> {
+    unary("sec", args, |x| 1.0 / x.cos())
+}
+
+/// 内置函数 csc(x) = 1/sin(x)
+pub fn csc(args: &[Value]) -> Result<Value, CalcError> {
+    unary("csc", args, |x| 1.0 / x.sin())
+}
+
+/// 内置函数 cot(x) = 1/tan(x)
+pub fn cot(args: &[Value]) -> Result<Value, CalcError> {
+    unary("cot", args, |x| 1.0 / x.tan())
+}
+
+/// 内置函数 asin(x)，定义域 [-1, 1]
+pub fn asin(args: &[Value]) -> Result<Value, CalcError> {
+    unary("asin", args, f64::asin)
+}
+
+/// 内置函数 acos(x)，定义域 [-1, 1]
+pub fn acos(args: &[Value]) -> Result<Value, CalcError> {
+    unary("acos", args, f64::acos)
+}
+
+/// 内置函数 atan(x)
+pub fn atan(args: &[Value]) -> Result<Value, CalcError> {
+    unary("atan", args, f64::atan)
+}
+
+/// 内置函数 sinh(x)
+pub fn sinh(args: &[Value]) -> Result<Value, CalcError> {
+    unary("sinh", args, f64::sinh)
+}
+
+/// 内置函数 cosh(x)
+pub fn cosh(args: &[Value]) -> Result<Value, CalcError> {
+    unary("cosh", args, f64::cosh)
+}
+
+/// 内置函数 tanh(x)
+pub fn tanh(args: &[Value]) -> Result<Value, CalcError> {
+    unary("tanh", args, f64::tanh)
+}
+
+/// 内置函数 asinh(x)
+pub fn asinh(args: &[Value]) -> Result<Value, CalcError> {
+    unary("asinh", args, f64::asinh)
+}
+
+/// 内置函数 acosh(x)，定义域 x ≥ 1
+pub fn acosh(args: &[Value]) -> Result<Value, CalcError> {
+    unary("acosh", args, f64::acosh)
+}
+
+/// 内置函数 atanh(x)，定义域 (-1, 1)，±1 处为 ±∞
+pub fn atanh(args: &[Value]) -> Result<Value, CalcError> {
+    unary("atanh", args, f64::atanh)
+}
+
+/// 内置函数 sind(x)，x 为角度
+pub fn sind(args: &[Value]) -> Result<Value, CalcError> {
+    unary("sind", args, sin_degrees)
+}
+
+/// 内置函数 cosd(x)，x 为角度
+pub fn cosd(args: &[Value]) -> Result<Value, CalcError> {
+    unary("cosd", args, cos_degrees)
+}
+
+/// 内置函数 tand(x)，x 为角度
+pub fn tand(args: &[Value]) -> Result<Value, CalcError> {
+    unary("tand", args, tan_degrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degrees() {
+        assert_eq!(sin_degrees(0.0), 0.0);
+        assert_eq!(sin_degrees(90.0), 1.0);
+        assert_eq!(sin_degrees(180.0), 0.0);
+        assert_eq!(sin_degrees(-90.0), -1.0);
+        assert_eq!(sin_degrees(720.0 + 270.0), -1.0);
+        assert_eq!(cos_degrees(90.0), 0.0);
+        assert_eq!(cos_degrees(180.0), -1.0);
+        assert!((sin_degrees(30.0) - 0.5).abs() < 1e-16);
+        assert!((cos_degrees(60.0) - 0.5).abs() < 1e-15);
+        assert!((sin_degrees(45.0) - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-16);
+        // 30° 和 45° 的整数倍处结果精确
+        assert_eq!(sin_degrees(30.0), 0.5);
+        assert_eq!(sin_degrees(150.0), 0.5);
+        assert_eq!(sin_degrees(-210.0), 0.5);
+        assert_eq!(cos_degrees(60.0), 0.5);
+        assert_eq!(cos_degrees(120.0), -0.5);
+        assert_eq!(cos_degrees(300.0), 0.5);
+        assert_eq!(sin_degrees(60.0), cos_degrees(30.0));
+        assert_eq!(tan_degrees(45.0), 1.0);
+        assert_eq!(tan_degrees(135.0), -1.0);
+        assert_eq!(tan_degrees(-45.0), -1.0);
+        assert_eq!(tan_degrees(180.0), 0.0);
+        assert!(tan_degrees(90.0).is_nan());
+        assert!((tan_degrees(10.0) - 10f64.to_radians().tan()).abs() < 1e-16);
+    }
+
+    #[test]
+    fn test_domain_errors() {
+        assert!(asin(&[Value::Number(2.0)]).is_err());
+        assert!(acosh(&[Value::Number(0.5)]).is_err());
+        assert!(atanh(&[Value::Number(2.0)]).is_err());
+        assert_eq!(
+            atanh(&[Value::Number(1.0)]),
+            Ok(Value::Number(f64::INFINITY))
+        );
+        assert!(sin(&[]).is_err());
+    }
+}
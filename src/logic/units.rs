@@ -0,0 +1,561 @@
+//! 物理单位与量纲分析
+//!
+//! 带单位的量统一以国际单位制（SI）数值保存，并记录七个 SI 基本量纲、信息量（比特）和货币的指数。
+//! 货币以汇率文件的基准货币为单位（显示为 ¤），汇率由 currency 模块在求值时查询。
+//! 加减要求量纲完全相同，乘除时量纲指数相加减，量纲抵消后退化为普通数值。
+//! `to` 运算符只改变显示单位，不改变数值。
+//! 表达式中的温度只支持开尔文：摄氏度和华氏度带偏移量，不能参与乘除运算，
+//! 只在单位换算器中通过 convert 换算。
+
+use super::{CalcError, Operation, Value, dates, superscript};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 基本量纲的符号，顺序与 Dimension 中的指数一致
+const BASE_SYMBOLS: [&str; 9] = ["kg", "m", "s", "A", "K", "mol", "cd", "bit", "¤"];
+
+/// 量纲：七个 SI 基本量纲（质量、长度、时间、电流、温度、物质的量、发光强度）、信息量和货币的指数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dimension([i8; 9]);
+
+impl Dimension {
+    /// 无量纲
+    pub const NONE: Dimension = Dimension([0; 9]);
+
+    /// 是否无量纲
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// 逐项组合两个量纲的指数，f 返回 None 表示指数超出范围
+    fn combine(
+        self,
+        other: Dimension,
+        f: impl Fn(i8, i8) -> Option<i8>,
+    ) -> Result<Dimension, CalcError> {
+        let mut exponents = [0; 9];
+        for (i, exponent) in exponents.iter_mut().enumerate() {
+            *exponent = f(self.0[i], other.0[i])
+                .ok_or_else(|| CalcError::Math("unit exponent out of range".to_string()))?;
+        }
+        Ok(Dimension(exponents))
+    }
+}
+
+/// 以基本单位显示量纲，例如 kg·m/s²；恰好等于常用导出单位时显示导出单位
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((name, ..)) = UNITS.iter().find(|(name, scale, dimension)| {
+            DERIVED_NAMES.contains(name) && *scale == 1.0 && dimension == self
+        }) {
+            return write!(f, "{}", name);
+        }
+        if self.is_none() {
+            return write!(f, "1");
+        }
+
+        let part = |sign: i8| -> String {
+            BASE_SYMBOLS
+                .iter()
+                .zip(self.0)
+                .filter(|(_, exponent)| exponent.signum() == sign)
+                .map(|(symbol, exponent)| match exponent.unsigned_abs() {
+                    1 => symbol.to_string(),
+                    n => format!("{}{}", symbol, superscript(n as u32)),
+                })
+                .collect::<Vec<_>>()
+                .join("·")
+        };
+        let (numerator, denominator) = (part(1), part(-1));
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (_, true) => write!(f, "{}", numerator),
+            (true, false) => write!(f, "1/{}", denominator),
+            (false, false) => write!(f, "{}/{}", numerator, denominator),
+        }
+    }
+}
+
+const LENGTH: Dimension = Dimension([0, 1, 0, 0, 0, 0, 0, 0, 0]);
+const MASS: Dimension = Dimension([1, 0, 0, 0, 0, 0, 0, 0, 0]);
+pub const TIME: Dimension = Dimension([0, 0, 1, 0, 0, 0, 0, 0, 0]);
+const CURRENT: Dimension = Dimension([0, 0, 0, 1, 0, 0, 0, 0, 0]);
+const TEMPERATURE: Dimension = Dimension([0, 0, 0, 0, 1, 0, 0, 0, 0]);
+const AMOUNT: Dimension = Dimension([0, 0, 0, 0, 0, 1, 0, 0, 0]);
+const LUMINOUS_INTENSITY: Dimension = Dimension([0, 0, 0, 0, 0, 0, 1, 0, 0]);
+const INFORMATION: Dimension = Dimension([0, 0, 0, 0, 0, 0, 0, 1, 0]);
+pub const MONEY: Dimension = Dimension([0, 0, 0, 0, 0, 0, 0, 0, 1]);
+const AREA: Dimension = Dimension([0, 2, 0, 0, 0, 0, 0, 0, 0]);
+const VOLUME: Dimension = Dimension([0, 3, 0, 0, 0, 0, 0, 0, 0]);
+const VELOCITY: Dimension = Dimension([0, 1, -1, 0, 0, 0, 0, 0, 0]);
+const FREQUENCY: Dimension = Dimension([0, 0, -1, 0, 0, 0, 0, 0, 0]);
+const FORCE: Dimension = Dimension([1, 1, -2, 0, 0, 0, 0, 0, 0]);
+const ENERGY: Dimension = Dimension([1, 2, -2, 0, 0, 0, 0, 0, 0]);
+const POWER: Dimension = Dimension([1, 2, -3, 0, 0, 0, 0, 0, 0]);
+const PRESSURE: Dimension = Dimension([1, -1, -2, 0, 0, 0, 0, 0, 0]);
+const CHARGE: Dimension = Dimension([0, 0, 1, 1, 0, 0, 0, 0, 0]);
+const VOLTAGE: Dimension = Dimension([1, 2, -3, -1, 0, 0, 0, 0, 0]);
+const RESISTANCE: Dimension = Dimension([1, 2, -3, -2, 0, 0, 0, 0, 0]);
+
+/// 单位注册表：(符号, 换算到 SI 的比例, 量纲)
+pub const UNITS: &[(&str, f64, Dimension)] = &[
+    // 长度
+    ("m", 1.0, LENGTH),
+    ("km", 1e3, LENGTH),
+    ("cm", 1e-2, LENGTH),
+    ("mm", 1e-3, LENGTH),
+    ("um", 1e-6, LENGTH),
+    ("µm", 1e-6, LENGTH),
+    ("nm", 1e-9, LENGTH),
+    ("in", 0.0254, LENGTH),
+    ("ft", 0.3048, LENGTH),
+    ("yd", 0.9144, LENGTH),
+    ("mi", 1609.344, LENGTH),
+    ("nmi", 1852.0, LENGTH),
+    // 面积和体积
+    ("ha", 1e4, AREA),
+    ("acre", 4_046.856_422_4, AREA),
+    ("L", 1e-3, VOLUME),
+    ("mL", 1e-6, VOLUME),
+    ("gal", 3.785_411_784e-3, VOLUME),
+    // 质量
+    ("kg", 1.0, MASS),
+    ("g", 1e-3, MASS),
+    ("mg", 1e-6, MASS),
+    ("t", 1e3, MASS),
+    ("lb", 0.453_592_37, MASS),
+    ("oz", 0.028_349_523_125, MASS),
+    // 时间
+    ("s", 1.0, TIME),
+    ("ms", 1e-3, TIME),
+    ("us", 1e-6, TIME),
+    ("µs", 1e-6, TIME),
+    ("ns", 1e-9, TIME),
+    ("min", 60.0, TIME),
+    ("h", 3600.0, TIME),
+    ("day", 86400.0, TIME),
+    ("days", 86400.0, TIME),
+    ("week", 604_800.0, TIME),
+    ("weeks", 604_800.0, TIME),
+    // 速度
+    ("mph", 0.447_04, VELOCITY),
+    ("kn", 1852.0 / 3600.0, VELOCITY),
+    // 频率
+    ("Hz", 1.0, FREQUENCY),
+    ("kHz", 1e3, FREQUENCY),
+    ("MHz", 1e6, FREQUENCY),
+    ("GHz", 1e9, FREQUENCY),
+    // 力
+    ("N", 1.0, FORCE),
+    ("kN", 1e3, FORCE),
+    ("lbf", 4.448_221_615_260_5, FORCE),
+    // 能量
+    ("J", 1.0, ENERGY),
+    ("kJ", 1e3, ENERGY),
+    ("MJ", 1e6, ENERGY),
+    ("cal", 4.184, ENERGY),
+    ("kcal", 4184.0, ENERGY),
+    ("Wh", 3600.0, ENERGY),
+    ("kWh", 3.6e6, ENERGY),
+    ("eV", 1.602_176_634e-19, ENERGY),
+    // 功率
+    ("W", 1.0, POWER),
+    ("kW", 1e3, POWER),
+    ("MW", 1e6, POWER),
+    ("hp", 745.699_871_582_270_2, POWER),
+    // 压强
+    ("Pa", 1.0, PRESSURE),
+    ("kPa", 1e3, PRESSURE),
+    ("MPa", 1e6, PRESSURE),
+    ("GPa", 1e9, PRESSURE),
+    ("bar", 1e5, PRESSURE),
+    ("atm", 101_325.0, PRESSURE),
+    ("psi", 6_894.757_293_168, PRESSURE),
+    // 电学
+    ("A", 1.0, CURRENT),
+    ("mA", 1e-3, CURRENT),
+    ("C", 1.0, CHARGE),
+    ("V", 1.0, VOLTAGE),
+    ("mV", 1e-3, VOLTAGE),
+    ("kV", 1e3, VOLTAGE),
+    ("ohm", 1.0, RESISTANCE),
+    ("Ω", 1.0, RESISTANCE),
+    // 其余基本单位
+    ("K", 1.0, TEMPERATURE),
+    ("mol", 1.0, AMOUNT),
+    ("cd", 1.0, LUMINOUS_INTENSITY),
+    // 信息量，以比特为基本单位
+    ("bit", 1.0, INFORMATION),
+    ("B", 8.0, INFORMATION),
+    ("kB", 8e3, INFORMATION),
+    ("MB", 8e6, INFORMATION),
+    ("GB", 8e9, INFORMATION),
+    ("TB", 8e12, INFORMATION),
+    ("KiB", 8.0 * 1024.0, INFORMATION),
+    ("MiB", 8.0 * 1048576.0, INFORMATION),
+    ("GiB", 8.0 * 1073741824.0, INFORMATION),
+    ("TiB", 8.0 * 1099511627776.0, INFORMATION),
+];
+
+/// 带偏移量的温度单位：(符号, 比例, 偏移量)，开尔文值 = (数值 + 偏移量) × 比例
+const TEMPERATURE_SCALES: &[(&str, f64, f64)] = &[("°C", 1.0, 273.15), ("°F", 5.0 / 9.0, 459.67)];
+
+/// 单位换算器的分类：(名称, 可选单位)，单位是可以求值的单位表达式或带偏移量的温度单位
+pub const CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Length",
+        &[
+            "m", "km", "cm", "mm", "µm", "nm", "in", "ft", "yd", "mi", "nmi",
+        ],
+    ),
+    ("Mass", &["kg", "g", "mg", "t", "lb", "oz"]),
+    ("Temperature", &["K", "°C", "°F"]),
+    (
+        "Area",
+        &["m²", "km²", "cm²", "ha", "acre", "in²", "ft²", "mi²"],
+    ),
+    ("Volume", &["m³", "L", "mL", "cm³", "gal", "ft³"]),
+    ("Speed", &["m/s", "km/h", "mph", "kn", "ft/s"]),
+    ("Pressure", &["Pa", "kPa", "MPa", "bar", "atm", "psi"]),
+    (
+        "Energy",
+        &["J", "kJ", "MJ", "cal", "kcal", "Wh", "kWh", "eV"],
+    ),
+    (
+        "Data size",
+        &[
+            "bit", "B", "kB", "MB", "GB", "TB", "KiB", "MiB", "GiB", "TiB",
+        ],
+    ),
+    ("Time", &["s", "ms", "µs", "ns", "min", "h", "day", "week"]),
+];
+
+/// 结果恰好是这些导出单位的量纲时，用导出单位代替基本单位显示
+const DERIVED_NAMES: &[&str] = &["N", "J", "W", "Pa", "Hz", "C", "V", "Ω"];
+
+/// 查找单位，返回数值为 1 的该单位的量
+pub fn lookup(name: &str) -> Option<Quantity> {
+    UNITS
+        .iter()
+        .find(|(symbol, ..)| *symbol == name)
+        .map(|&(_, scale, dimension)| Quantity {
+            value: scale,
+            dimension,
+            display: None,
+        })
+}
+
+/// 是否为带偏移量的温度单位（°C、°F），这类单位不能在表达式中使用
+pub fn is_temperature_scale(name: &str) -> bool {
+    TEMPERATURE_SCALES
+        .iter()
+        .any(|(symbol, ..)| *symbol == name)
+}
+
+/// 单位换算到 SI 的 (比例, 偏移量, 量纲)：SI 数值 = (数值 + 偏移量) × 比例
+fn conversion(unit: &str) -> Result<(f64, f64, Dimension), CalcError> {
+    if let Some(&(_, scale, offset)) = TEMPERATURE_SCALES.iter().find(|(s, ..)| *s == unit) {
+        return Ok((scale, offset, TEMPERATURE));
+    }
+    match super::evaluate(unit)? {
+        Value::Quantity(q) => Ok((q.value, 0.0, q.dimension)),
+        _ => Err(CalcError::InvalidArguments(format!(
+            "{} is not a unit",
+            unit
+        ))),
+    }
+}
+
+/// 把 value 从单位 from 换算到单位 to，两者量纲必须相同；温度单位按偏移量换算
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, CalcError> {
+    let (from_scale, from_offset, from_dimension) = conversion(from)?;
+    let (to_scale, to_offset, to_dimension) = conversion(to)?;
+    if from_dimension != to_dimension {
+        return Err(incompatible(from_dimension, to_dimension));
+    }
+    Ok((value + from_offset) * from_scale / to_scale - to_offset)
+}
+
+/// 带单位的量
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    /// SI 单位下的数值
+    pub value: f64,
+    /// 量纲
+    pub dimension: Dimension,
+    /// `to` 指定的显示单位：(单位文本, 该单位换算到 SI 的比例)，为 None 时以 SI 单位显示
+    pub display: Option<(String, f64)>,
+}
+
+impl Quantity {
+    /// 可以重新求值的文本：时长写成总秒数，其余与显示相同
+    pub fn expression(&self) -> String {
+        match &self.display {
+            None if self.dimension == TIME => format!("{} s", self.value),
+            _ => self.to_string(),
+        }
+    }
+
+    /// 显示用的数值：汇率换算会在最后一位引入舍入误差（如 29999.999999999996），
+    /// 含货币量纲的量保留 15 位有效数字，其他量按原值显示
+    fn shown(&self, value: f64) -> f64 {
+        if self.dimension.0[8] != 0 {
+            format!("{:.14e}", value).parse().unwrap_or(value)
+        } else {
+            value
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.display {
+            // 时长同时显示为易读形式和总秒数
+            None if self.dimension == TIME => {
+                let human = dates::format_duration(self.value);
+                let seconds = format!("{} s", self.value);
+                if human == seconds {
+                    write!(f, "{}", seconds)
+                } else {
+                    write!(f, "{} ({})", human, seconds)
+                }
+            }
+            Some((unit, scale)) => write!(f, "{} {}", self.shown(self.value / scale), unit),
+            None => write!(f, "{} {}", self.shown(self.value), self.dimension),
+        }
+    }
+}
+
+/// 构造 SI 数值的结果，量纲抵消时返回普通数值
+fn quantity(value: f64, dimension: Dimension) -> Value {
+    if dimension.is_none() {
+        Value::Number(value)
+    } else {
+        Value::Quantity(Quantity {
+            value,
+            dimension,
+            display: None,
+        })
+    }
+}
+
+/// 保留显示单位：与同量纲的量相加减或与普通数值相乘除时，结果沿用原来的显示单位
+fn with_display(value: Value, display: Option<(String, f64)>) -> Value {
+    match value {
+        Value::Quantity(q) => Value::Quantity(Quantity { display, ..q }),
+        value => value,
+    }
+}
+
+/// 显示单位，普通数值和以 SI 单位显示的量返回 None
+fn display_of(value: &Value) -> Option<(String, f64)> {
+    match value {
+        Value::Quantity(q) => q.display.clone(),
+        _ => None,
+    }
+}
+
+/// 拆分为 SI 数值和量纲，普通数值视为无量纲
+fn split(value: &Value) -> Result<(f64, Dimension), CalcError> {
+    match value {
+        Value::Quantity(q) => Ok((q.value, q.dimension)),
+        _ => value
+            .as_number()
+            .map(|number| (number, Dimension::NONE))
+            .ok_or_else(|| CalcError::InvalidArguments("arithmetic on a list".to_string())),
+    }
+}
+
+/// 量纲不一致的错误
+fn incompatible(a: Dimension, b: Dimension) -> CalcError {
+    CalcError::IncompatibleUnits(a.to_string(), b.to_string())
+}
+
+/// 对带单位的操作数执行运算（至少一个操作数带单位，或运算为 `to`）
+pub(super) fn apply(op: &Operation, operands: &[Value]) -> Result<Value, CalcError> {
+    let (b, b_dimension) = split(&operands[operands.len() - 1])?;
+    if operands.len() == 1 {
+        return match op {
+            Operation::Negate => Ok(match &operands[0] {
+                Value::Quantity(q) => Value::Quantity(Quantity {
+                    value: -q.value,
+                    ..q.clone()
+                }),
+                _ => quantity(-b, b_dimension),
+            }),
+            _ => Err(CalcError::InvalidArguments(
+                "factorial of a quantity with units".to_string(),
+            )),
+        };
+    }
+
+    let (a, a_dimension) = split(&operands[0])?;
+    match op {
+        Operation::Plus | Operation::ImplicitPlus | Operation::Minus => {
+            if a_dimension != b_dimension {
+                return Err(incompatible(a_dimension, b_dimension));
+            }
+            let value = if *op == Operation::Minus {
+                a - b
+            } else {
+                a + b
+            };
+            Ok(with_display(
+                quantity(value, a_dimension),
+                display_of(&operands[0]),
+            ))
+        }
+        Operation::Times | Operation::ImplicitTimes => {
+            let display = match (a_dimension.is_none(), b_dimension.is_none()) {
+                (true, false) => display_of(&operands[1]),
+                (false, true) => display_of(&operands[0]),
+                _ => None,
+            };
+            Ok(with_display(
+                quantity(a * b, a_dimension.combine(b_dimension, i8::checked_add)?),
+                display,
+            ))
+        }
+        Operation::Divide => {
+            let display = b_dimension.is_none().then(|| display_of(&operands[0]));
+            Ok(with_display(
+                quantity(a / b, a_dimension.combine(b_dimension, i8::checked_sub)?),
+                display.flatten(),
+            ))
+        }
+        Operation::Power => {
+            if !b_dimension.is_none() {
+                return Err(CalcError::InvalidArguments(
+                    "exponent must be dimensionless".to_string(),
+                ));
+            }
+            // 指数必须让每个量纲指数仍为整数，例如 (m²)^0.5 = m
+            let exponents: Option<Vec<i8>> = a_dimension
+                .0
+                .iter()
+                .map(|&exponent| {
+                    let scaled = exponent as f64 * b;
+                    (scaled.fract() == 0.0 && scaled.abs() <= i8::MAX as f64)
+                        .then_some(scaled as i8)
+                })
+                .collect();
+            match exponents {
+                Some(exponents) => Ok(quantity(
+                    a.powf(b),
+                    Dimension(exponents.try_into().unwrap()),
+                )),
+                None => Err(CalcError::Math(format!(
+                    "{} cannot be raised to the power {}",
+                    a_dimension, b
+                ))),
+            }
+        }
+        Operation::Convert(unit) => {
+            let Value::Quantity(_) = operands[1] else {
+                return Err(CalcError::InvalidArguments(format!(
+                    "{} is not a unit",
+                    unit
+                )));
+            };
+            if a_dimension != b_dimension {
+                return Err(incompatible(a_dimension, b_dimension));
+            }
+            Ok(Value::Quantity(Quantity {
+                value: a,
+                dimension: a_dimension,
+                display: Some((unit.clone(), b)),
+            }))
+        }
+        Operation::Negate | Operation::Factorial => Err(CalcError::Syntax),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimension_display() {
+        assert_eq!(LENGTH.to_string(), "m");
+        assert_eq!(VELOCITY.to_string(), "m/s");
+        assert_eq!(FORCE.to_string(), "N");
+        assert_eq!(FREQUENCY.to_string(), "Hz");
+        assert_eq!(Dimension([1, 0, -2, 0, 0, 0, 0, 0, 0]).to_string(), "kg/s²");
+        assert_eq!(Dimension([0, -1, 0, 0, 0, 0, 0, 0, 0]).to_string(), "1/m");
+        assert_eq!(Dimension([2, 3, 0, 0, 0, 0, 0, 0, 0]).to_string(), "kg²·m³");
+    }
+
+    #[test]
+    fn test_registry() {
+        // 符号不重复，且都能被识别为单位名
+        for (i, (symbol, scale, _)) in UNITS.iter().enumerate() {
+            assert!(UNITS[..i].iter().all(|(other, ..)| other != symbol));
+            assert!(symbol.chars().all(char::is_alphabetic));
+            assert!(*scale > 0.0);
+        }
+        assert_eq!(lookup("km").unwrap().value, 1000.0);
+        assert!(lookup("furlong").is_none());
+    }
+
+    #[test]
+    fn test_apply() {
+        let metre = Value::Quantity(lookup("m").unwrap());
+        let second = Value::Quantity(lookup("s").unwrap());
+        assert_eq!(
+            apply(&Operation::Plus, &[metre.clone(), second.clone()]),
+            Err(CalcError::IncompatibleUnits(
+                "m".to_string(),
+                "s".to_string()
+            ))
+        );
+        assert_eq!(
+            apply(&Operation::Divide, &[metre.clone(), metre.clone()]),
+            Ok(Value::Number(1.0))
+        );
+        assert!(apply(&Operation::Power, &[metre.clone(), Value::Number(0.5)]).is_err());
+        assert!(apply(&Operation::Power, &[metre.clone(), second]).is_err());
+        assert!(apply(&Operation::Factorial, std::slice::from_ref(&metre)).is_err());
+        // 量纲指数超出范围时报错，不会溢出
+        let out_of_range = Err(CalcError::Math("unit exponent out of range".to_string()));
+        let power = |exponent: f64| {
+            apply(&Operation::Power, &[metre.clone(), Value::Number(exponent)]).unwrap()
+        };
+        assert_eq!(
+            apply(&Operation::Times, &[power(100.0), power(100.0)]),
+            out_of_range
+        );
+        assert_eq!(
+            apply(&Operation::Divide, &[power(-100.0), power(100.0)]),
+            out_of_range
+        );
+        assert!(
+            apply(
+                &Operation::Convert("m".to_string()),
+                &[metre, Value::Number(1.0)]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_convert() {
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-12 * b.abs().max(1.0);
+        assert!(close(convert(100.0, "°C", "°F").unwrap(), 212.0));
+        assert!(close(convert(-40.0, "°F", "°C").unwrap(), -40.0));
+        assert!(close(convert(0.0, "°C", "K").unwrap(), 273.15));
+        assert!(close(convert(300.0, "K", "°F").unwrap(), 80.33));
+        assert!(close(convert(1.0, "km²", "m²").unwrap(), 1e6));
+        assert!(close(convert(1.0, "ft³", "L").unwrap(), 28.316846592));
+        assert!(close(convert(90.0, "km/h", "m/s").unwrap(), 25.0));
+        assert!(close(convert(1.0, "GiB", "MB").unwrap(), 1073.741824));
+        assert!(close(convert(1.0, "h", "min").unwrap(), 60.0));
+        assert!(convert(1.0, "°C", "m").is_err());
+        assert!(convert(1.0, "m", "2").is_err());
+
+        // 换算器中的每个单位都能识别，且同一分类内量纲一致
+        for (name, units) in CATEGORIES {
+            for unit in units.iter() {
+                assert!(convert(1.0, units[0], unit).is_ok(), "{} {}", name, unit);
+            }
+        }
+    }
+}
@@ -1,47 +1,225 @@
+use gpui::prelude::FluentBuilder;
 use gpui::*;
+mod button;
 mod calculator;
+mod datapanel;
+mod historypanel;
+mod loanpanel;
 mod logic;
+mod matrixform;
+mod memorypanel;
+mod numberfield;
+mod regressionpanel;
 mod show;
 mod style;
 mod toucharea;
+mod unitconverter;
 
-use calculator::Calculator;
+use button::form_button;
+use calculator::{Calculator, CalculatorCore, Command, store};
+use datapanel::DataPanel;
+use historypanel::HistoryPanel;
+use loanpanel::LoanPanel;
+use matrixform::MatrixForm;
+use memorypanel::MemoryPanel;
+use regressionpanel::RegressionPanel;
 use show::Show;
 use toucharea::TouchArea;
+use unitconverter::UnitConverter;
+
+// 撤销和重做在整个窗口中可用，不需要先点击显示区
+actions!(mycal, [Undo, Redo]);
+
+/// 侧边面板类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Panel {
+    /// 计算历史
+    History,
+    /// 记忆寄存器
+    Memory,
+    /// 线性方程组表单
+    LinearSystem,
+    /// 统计数据面板
+    Statistics,
+    /// 回归拟合面板
+    Regression,
+    /// 单位换算器
+    Units,
+    /// 贷款还款计划
+    Loan,
+}
+
+impl Panel {
+    /// 所有面板，按标签栏顺序排列
+    const ALL: [Panel; 7] = [
+        Panel::History,
+        Panel::Memory,
+        Panel::LinearSystem,
+        Panel::Statistics,
+        Panel::Regression,
+        Panel::Units,
+        Panel::Loan,
+    ];
+
+    /// 标签栏上显示的名称
+    fn label(self) -> &'static str {
+        match self {
+            Panel::History => "Tape",
+            Panel::Memory => "Mem",
+            Panel::LinearSystem => "Ax=b",
+            Panel::Statistics => "Stats",
+            Panel::Regression => "Fit",
+            Panel::Units => "Units",
+            Panel::Loan => "Loan",
+        }
+    }
+}
 
 struct Root {
+    calculator: Entity<Calculator>,
     show: Entity<Show>,
     toucharea: Entity<TouchArea>,
+    history_panel: Entity<HistoryPanel>,
+    memory_panel: Entity<MemoryPanel>,
+    matrix_form: Entity<MatrixForm>,
+    data_panel: Entity<DataPanel>,
+    regression_panel: Entity<RegressionPanel>,
+    unit_converter: Entity<UnitConverter>,
+    loan_panel: Entity<LoanPanel>,
+    /// 当前显示的侧边面板
+    panel: Panel,
 }
 
 impl Root {
     fn new(cx: &mut App) -> Self {
-        let calculator = cx.new(|_| Calculator::new());
+        let calculator = cx.new(|_| {
+            let mut core = CalculatorCore::new();
+            core.load_rates();
+            core.use_system_locale();
+            let mut calculator = Calculator::new(core);
+            if let Some(path) = store::default_path() {
+                calculator.restore_session(path);
+            }
+            calculator
+        });
+        // 退出时保存会话
+        cx.on_app_quit({
+            let calculator = calculator.clone();
+            move |cx| {
+                calculator.update(cx, |calculator, _| calculator.save_session());
+                async {}
+            }
+        })
+        .detach();
         let show = cx.new(|cx| Show::new(calculator.clone(), cx));
         let toucharea = cx.new(|_| TouchArea::new(calculator.clone()));
-        Self { show, toucharea }
+        let history_panel = cx.new(|cx| HistoryPanel::new(calculator.clone(), cx));
+        let memory_panel = cx.new(|cx| MemoryPanel::new(calculator.clone(), cx));
+        let matrix_form = cx.new(MatrixForm::new);
+        let data_panel = cx.new(|cx| DataPanel::new(calculator.clone(), cx));
+        let regression_panel = cx.new(|cx| RegressionPanel::new(calculator.clone(), cx));
+        let unit_converter = cx.new(UnitConverter::new);
+        let loan_panel = cx.new(LoanPanel::new);
+        Self {
+            calculator,
+            show,
+            toucharea,
+            history_panel,
+            memory_panel,
+            matrix_form,
+            data_panel,
+            regression_panel,
+            unit_converter,
+            loan_panel,
+            panel: Panel::History,
+        }
+    }
+
+    /// 执行撤销或重做
+    fn apply(&mut self, command: Command, cx: &mut Context<Self>) {
+        self.calculator
+            .update(cx, |calculator, cx| calculator.apply(command, cx));
     }
 }
 
 impl Render for Root {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let tabs = Panel::ALL.into_iter().map(|panel| {
+            form_button(panel.label(), panel.label())
+                .when(panel == self.panel, |this| {
+                    this.bg(rgb(style::PRIMARY_COLOR))
+                        .text_color(rgb(style::DISPLAY_COLOR))
+                })
+                .on_click(cx.listener(move |this, _, _, cx| {
+                    this.panel = panel;
+                    cx.notify();
+                }))
+        });
+        let panel: AnyView = match self.panel {
+            Panel::History => self.history_panel.clone().into(),
+            Panel::Memory => self.memory_panel.clone().into(),
+            Panel::LinearSystem => self.matrix_form.clone().into(),
+            Panel::Statistics => self.data_panel.clone().into(),
+            Panel::Regression => self.regression_panel.clone().into(),
+            Panel::Units => self.unit_converter.clone().into(),
+            Panel::Loan => self.loan_panel.clone().into(),
+        };
+
         div()
             .size_full()
+            .on_action(cx.listener(|this, _: &Undo, _, cx| this.apply(Command::Undo, cx)))
+            .on_action(cx.listener(|this, _: &Redo, _, cx| this.apply(Command::Redo, cx)))
             .font_family(SharedString::from("JetBrainsMono Nerd Font"))
             .flex()
-            .flex_col()
             .child(
                 div()
-                    .h(DefiniteLength::Fraction(0.2))
-                    .child(self.show.clone()),
+                    .w(px(400.0))
+                    .h_full()
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .h(DefiniteLength::Fraction(0.2))
+                            .child(self.show.clone()),
+                    )
+                    .child(div().flex_1().child(self.toucharea.clone())),
+            )
+            // 侧边面板：标签栏 + 当前面板
+            .child(
+                div()
+                    .flex_1()
+                    .h_full()
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .flex()
+                            .gap_1()
+                            .p_1()
+                            .bg(rgb(style::DISPLAY_COLOR))
+                            .children(tabs),
+                    )
+                    .child(div().flex_1().min_h_0().child(panel)),
             )
-            .child(div().flex_1().child(self.toucharea.clone()))
     }
 }
 
 fn main() {
     Application::new().run(|cx| {
-        let bounds = Bounds::centered(None, size(px(400.0), px(500.0)), cx);
+        cx.bind_keys([
+            KeyBinding::new("ctrl-z", Undo, None),
+            KeyBinding::new("cmd-z", Undo, None),
+            KeyBinding::new("ctrl-shift-z", Redo, None),
+            KeyBinding::new("cmd-shift-z", Redo, None),
+        ]);
+        // 关闭窗口即退出应用，退出时保存会话
+        cx.on_window_closed(|cx| {
+            if cx.windows().is_empty() {
+                cx.quit();
+            }
+        })
+        .detach();
+        let bounds = Bounds::centered(None, size(px(800.0), px(500.0)), cx);
         cx.open_window(
             WindowOptions {
                 window_bounds: Some(WindowBounds::Windowed(bounds)),
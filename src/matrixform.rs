@@ -0,0 +1,193 @@
+use crate::button::form_button;
+use crate::logic::linalg::{self, Solution};
+use crate::numberfield::NumberField;
+use crate::style;
+use gpui::*;
+
+/// 方程组的最小阶数
+const MIN_SIZE: usize = 2;
+/// 方程组的最大阶数
+const MAX_SIZE: usize = 6;
+
+/// 线性方程组表单 - 逐格填写 N×N 系数矩阵和常数向量，求解 Ax = b
+pub struct MatrixForm {
+    /// 当前方程组阶数
+    size: usize,
+    /// 系数输入框（按最大阶数预先创建，只显示左上角 size×size 部分）
+    coefficients: Vec<Vec<NumberField>>,
+    /// 常数项输入框
+    constants: Vec<NumberField>,
+    /// 最近一次求解的结果或错误信息
+    result: Option<Result<Solution, String>>,
+}
+
+impl MatrixForm {
+    /// 创建新的方程组表单
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            size: 3,
+            coefficients: (0..MAX_SIZE)
+                .map(|_| (0..MAX_SIZE).map(|_| NumberField::new(cx)).collect())
+                .collect(),
+            constants: (0..MAX_SIZE).map(|_| NumberField::new(cx)).collect(),
+            result: None,
+        }
+    }
+
+    /// 调整方程组阶数，保留已填写的内容
+    fn set_size(&mut self, size: usize, cx: &mut Context<Self>) {
+        self.size = size.clamp(MIN_SIZE, MAX_SIZE);
+        self.result = None;
+        cx.notify();
+    }
+
+    /// 清空所有输入框
+    fn clear(&mut self, cx: &mut Context<Self>) {
+        self.coefficients
+            .iter_mut()
+            .flatten()
+            .for_each(NumberField::clear);
+        self.constants.iter_mut().for_each(NumberField::clear);
+        self.result = None;
+        cx.notify();
+    }
+
+    /// 读取输入框的数值，空白视为 0
+    fn read_field(field: &NumberField, name: &str) -> Result<f64, String> {
+        if field.text().trim().is_empty() {
+            return Ok(0.0);
+        }
+        field
+            .value()
+            .ok_or_else(|| format!("Invalid number in {}: {}", name, field.text()))
+    }
+
+    /// 读取当前输入并求解方程组
+    fn solve(&mut self, cx: &mut Context<Self>) {
+        let n = self.size;
+        let read_inputs = || -> Result<(Vec<Vec<f64>>, Vec<f64>), String> {
+            let mut a = Vec::with_capacity(n);
+            for (i, row) in self.coefficients.iter().take(n).enumerate() {
+                let row = row
+                    .iter()
+                    .take(n)
+                    .enumerate()
+                    .map(|(j, field)| Self::read_field(field, &format!("a{}{}", i + 1, j + 1)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                a.push(row);
+            }
+            let b = self
+                .constants
+                .iter()
+                .take(n)
+                .enumerate()
+                .map(|(i, field)| Self::read_field(field, &format!("b{}", i + 1)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((a, b))
+        };
+
+        self.result = Some(
+            read_inputs()
+                .and_then(|(a, b)| linalg::solve(&a, &b).map_err(|error| error.to_string())),
+        );
+        cx.notify();
+    }
+
+    /// 渲染求解结果区域
+    fn render_result(&self) -> Div {
+        let container = div().flex().flex_col().gap_1().text_sm();
+        match &self.result {
+            None => container
+                .text_color(rgb(style::MUTED_COLOR))
+                .child("Fill in A and b, then press Solve"),
+            Some(Err(message)) => container
+                .text_color(rgb(style::PRIMARY_COLOR))
+                .child(message.clone()),
+            Some(Ok(solution)) => container
+                .text_color(rgb(style::WHITE_COLOR))
+                .children(
+                    solution
+                        .x
+                        .iter()
+                        .enumerate()
+                        .map(|(i, x)| format!("x{} = {}", i + 1, x)),
+                )
+                .child(
+                    div()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(format!("cond ≈ {:.3e}", solution.condition)),
+                ),
+        }
+    }
+}
+
+impl Render for MatrixForm {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let n = self.size;
+
+        let rows = (0..n).map(|i| {
+            let coefficient_cells = (0..n).map(|j| {
+                self.coefficients[i][j]
+                    .render(("coefficient", i * MAX_SIZE + j), window)
+                    .on_key_down(cx.listener(move |this, event: &KeyDownEvent, _, cx| {
+                        if this.coefficients[i][j].handle_key(&event.keystroke) {
+                            this.result = None;
+                            cx.notify();
+                        }
+                    }))
+            });
+            let constant_cell = self.constants[i]
+                .render(("constant", i), window)
+                .on_key_down(cx.listener(move |this, event: &KeyDownEvent, _, cx| {
+                    if this.constants[i].handle_key(&event.keystroke) {
+                        this.result = None;
+                        cx.notify();
+                    }
+                }));
+
+            div()
+                .flex()
+                .gap_1()
+                .items_center()
+                .children(coefficient_cells)
+                .child(div().px_1().text_color(rgb(style::MUTED_COLOR)).child("|"))
+                .child(constant_cell)
+        });
+
+        div()
+            .id("matrix-form")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_2()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(
+                div()
+                    .flex()
+                    .gap_1()
+                    .items_center()
+                    .child(
+                        form_button("matrix-shrink", "−")
+                            .on_click(cx.listener(move |this, _, _, cx| this.set_size(n - 1, cx))),
+                    )
+                    .child(div().px_2().child(format!("{n} × {n}")))
+                    .child(
+                        form_button("matrix-grow", "+")
+                            .on_click(cx.listener(move |this, _, _, cx| this.set_size(n + 1, cx))),
+                    )
+                    .child(div().flex_1())
+                    .child(
+                        form_button("matrix-clear", "Clear")
+                            .on_click(cx.listener(|this, _, _, cx| this.clear(cx))),
+                    )
+                    .child(
+                        form_button("matrix-solve", "Solve")
+                            .on_click(cx.listener(|this, _, _, cx| this.solve(cx))),
+                    ),
+            )
+            .child(div().flex().flex_col().gap_1().children(rows))
+            .child(self.render_result())
+    }
+}
@@ -0,0 +1,114 @@
+use crate::button::form_button;
+use crate::calculator::{Calculator, Command};
+use crate::style;
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+/// 记忆面板 - 列出记忆寄存器 M 和编号寄存器 0–9 的内容，点击内容取回到输入
+pub struct MemoryPanel {
+    /// 计算器实体的引用，寄存器保存在计算器中
+    calculator: Entity<Calculator>,
+}
+
+impl MemoryPanel {
+    /// 创建新的记忆面板
+    pub fn new(calculator: Entity<Calculator>, cx: &mut Context<Self>) -> Self {
+        // 寄存器变化后重新渲染
+        cx.observe(&calculator, |_this, _calculator, cx| cx.notify())
+            .detach();
+
+        Self { calculator }
+    }
+
+    /// 向计算器发送命令
+    fn apply(&mut self, command: Command, cx: &mut Context<Self>) {
+        self.calculator
+            .update(cx, |calculator, cx| calculator.apply(command, cx));
+    }
+}
+
+impl Render for MemoryPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let core = self.calculator.read(cx).core();
+        let memory = core.memory();
+
+        // 第一行为 M，之后是编号寄存器；空寄存器显示为 —
+        let registers =
+            std::iter::once(("M".to_string(), &memory.m, Command::MemoryRecall)).chain(
+                memory.registers.iter().enumerate().map(|(index, value)| {
+                    (index.to_string(), value, Command::RecallRegister(index))
+                }),
+            );
+        let rows: Vec<Div> = registers
+            .enumerate()
+            .map(|(row, (name, value, command))| {
+                let filled = value.is_some();
+                let text = value
+                    .as_ref()
+                    .map_or("—".to_string(), |value| core.format_value(value));
+                div()
+                    .flex()
+                    .gap_2()
+                    .items_center()
+                    .child(
+                        div()
+                            .w_8()
+                            .text_sm()
+                            .text_color(rgb(style::MUTED_COLOR))
+                            .child(name),
+                    )
+                    .child(
+                        div()
+                            .id(("memory-value", row))
+                            .flex_1()
+                            .min_w_0()
+                            .text_lg()
+                            .text_color(rgb(if filled {
+                                style::PRIMARY_COLOR
+                            } else {
+                                style::MUTED_COLOR
+                            }))
+                            .child(text)
+                            .when(filled, |this| {
+                                this.cursor_pointer()
+                                    .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        this.apply(command.clone(), cx)
+                                    }))
+                            }),
+                    )
+            })
+            .collect();
+
+        div()
+            .id("memory-panel")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_2()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(form_button("memory-clear", "MC").on_click(
+                        cx.listener(|this, _, _, cx| this.apply(Command::MemoryClear, cx)),
+                    ))
+                    .child(form_button("registers-clear", "Clear 0–9").on_click(
+                        cx.listener(|this, _, _, cx| this.apply(Command::ClearRegisters, cx)),
+                    )),
+            )
+            .child(
+                div()
+                    .id("memory-rows")
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .gap_1()
+                    .overflow_y_scroll()
+                    .children(rows),
+            )
+    }
+}
@@ -0,0 +1,98 @@
+use crate::style;
+use gpui::*;
+
+/// 数字输入框 - 可获得焦点并接收键盘输入的单行数字文本
+pub struct NumberField {
+    /// 当前输入的文本
+    text: String,
+    /// 焦点句柄，点击输入框时获得焦点
+    focus_handle: FocusHandle,
+}
+
+impl NumberField {
+    /// 创建新的空输入框
+    pub fn new(cx: &mut App) -> Self {
+        Self {
+            text: String::new(),
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    /// 获取当前文本
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// 让输入框获得焦点
+    pub fn focus(&self, window: &mut Window) {
+        window.focus(&self.focus_handle);
+    }
+
+    /// 清空输入内容
+    pub fn clear(&mut self) {
+        self.text.clear();
+    }
+
+    /// 替换输入内容，用于显示由其他输入计算出的值
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+    }
+
+    /// 解析输入的数值，空白或非法输入返回 None
+    pub fn value(&self) -> Option<f64> {
+        self.text.trim().parse().ok()
+    }
+
+    /// 处理按键，返回内容是否发生变化
+    pub fn handle_key(&mut self, keystroke: &Keystroke) -> bool {
+        if keystroke.modifiers.control || keystroke.modifiers.platform {
+            return false;
+        }
+
+        if keystroke.key == "backspace" {
+            return self.text.pop().is_some();
+        }
+
+        match keystroke.key_char.as_deref() {
+            Some(input)
+                if input
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | 'e' | 'E')) =>
+            {
+                self.text.push_str(input);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 渲染输入框，键盘事件由调用方通过 on_key_down 转交给 handle_key
+    pub fn render(&self, id: impl Into<ElementId>, window: &Window) -> Stateful<Div> {
+        let is_focused = self.focus_handle.is_focused(window);
+
+        div()
+            .id(id)
+            .track_focus(&self.focus_handle)
+            .flex()
+            .flex_1()
+            .h_8()
+            .px_2()
+            .items_center()
+            .justify_end()
+            .rounded_md()
+            .border_1()
+            .border_color(rgb(if is_focused {
+                style::PRIMARY_COLOR
+            } else {
+                style::FIELD_COLOR
+            }))
+            .bg(rgb(if is_focused {
+                style::FIELD_FOCUS_COLOR
+            } else {
+                style::FIELD_COLOR
+            }))
+            .text_color(rgb(style::WHITE_COLOR))
+            .overflow_hidden()
+            .child(self.text.clone())
+    }
+}
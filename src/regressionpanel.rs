@@ -0,0 +1,310 @@
+use crate::button::form_button;
+use crate::calculator::{Calculator, Command};
+use crate::logic::CalcError;
+use crate::logic::regression::{self, Fit, Model};
+use crate::numberfield::NumberField;
+use crate::style;
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+/// 多项式回归的最高次数
+const MAX_DEGREE: usize = 6;
+
+/// 可选的模型类型（多项式次数单独保存）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ModelKind {
+    Linear,
+    Polynomial,
+    Exponential,
+    Logarithmic,
+}
+
+impl ModelKind {
+    /// 所有模型，按按钮顺序排列
+    const ALL: [ModelKind; 4] = [
+        ModelKind::Linear,
+        ModelKind::Polynomial,
+        ModelKind::Exponential,
+        ModelKind::Logarithmic,
+    ];
+
+    /// 按钮上显示的名称
+    fn label(self) -> &'static str {
+        match self {
+            ModelKind::Linear => "Lin",
+            ModelKind::Polynomial => "Poly",
+            ModelKind::Exponential => "Exp",
+            ModelKind::Logarithmic => "Log",
+        }
+    }
+}
+
+/// 回归面板 - 输入成对的 (x, y) 数据，实时计算最小二乘拟合
+pub struct RegressionPanel {
+    /// 计算器实体的引用，拟合结果写入计算器会话供 predict(x) 使用
+    calculator: Entity<Calculator>,
+    /// 每行一对 (x, y) 输入框
+    rows: Vec<(NumberField, NumberField)>,
+    /// 当前选择的模型
+    kind: ModelKind,
+    /// 多项式回归的次数
+    degree: usize,
+    /// 预测输入框
+    predict_field: NumberField,
+    /// 最近一次拟合结果
+    fit: Option<Result<Fit, CalcError>>,
+}
+
+impl RegressionPanel {
+    /// 创建新的回归面板
+    pub fn new(calculator: Entity<Calculator>, cx: &mut Context<Self>) -> Self {
+        Self {
+            calculator,
+            rows: vec![(NumberField::new(cx), NumberField::new(cx))],
+            kind: ModelKind::Linear,
+            degree: 2,
+            predict_field: NumberField::new(cx),
+            fit: None,
+        }
+    }
+
+    /// 当前选择对应的回归模型
+    fn model(&self) -> Model {
+        match self.kind {
+            ModelKind::Linear => Model::Linear,
+            ModelKind::Polynomial => Model::Polynomial(self.degree),
+            ModelKind::Exponential => Model::Exponential,
+            ModelKind::Logarithmic => Model::Logarithmic,
+        }
+    }
+
+    /// 用两列都可解析的行重新拟合，成功时同步到计算器
+    fn refit(&mut self, cx: &mut Context<Self>) {
+        let (xs, ys): (Vec<f64>, Vec<f64>) = self
+            .rows
+            .iter()
+            .filter_map(|(x, y)| x.value().zip(y.value()))
+            .unzip();
+
+        let fit = (!xs.is_empty()).then(|| regression::fit(self.model(), &xs, &ys));
+        if let Some(Ok(fit)) = &fit {
+            let fit = fit.clone();
+            self.calculator.update(cx, |calculator, cx| {
+                calculator.apply(Command::SetLastFit(fit), cx)
+            });
+        }
+        self.fit = fit;
+        cx.notify();
+    }
+
+    /// 切换模型
+    fn set_kind(&mut self, kind: ModelKind, cx: &mut Context<Self>) {
+        self.kind = kind;
+        self.refit(cx);
+    }
+
+    /// 调整多项式次数
+    fn set_degree(&mut self, degree: usize, cx: &mut Context<Self>) {
+        self.degree = degree.clamp(1, MAX_DEGREE);
+        self.refit(cx);
+    }
+
+    /// 在指定位置之后插入新行并聚焦其 x 输入框
+    fn insert_row(&mut self, after: usize, window: &mut Window, cx: &mut Context<Self>) {
+        let index = (after + 1).min(self.rows.len());
+        self.rows
+            .insert(index, (NumberField::new(cx), NumberField::new(cx)));
+        self.rows[index].0.focus(window);
+        cx.notify();
+    }
+
+    /// 清空所有数据
+    fn clear(&mut self, cx: &mut Context<Self>) {
+        self.rows = vec![(NumberField::new(cx), NumberField::new(cx))];
+        self.predict_field.clear();
+        self.fit = None;
+        cx.notify();
+    }
+
+    /// 处理数据行的按键：回车新增一行，上下键切换行，两格都为空时退格删除该行
+    fn handle_row_key(
+        &mut self,
+        index: usize,
+        column: usize,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let row_is_empty =
+            self.rows[index].0.text().is_empty() && self.rows[index].1.text().is_empty();
+
+        match event.keystroke.key.as_str() {
+            "enter" => self.insert_row(index, window, cx),
+            "up" if index > 0 => column_field(&self.rows[index - 1], column).focus(window),
+            "down" if index + 1 < self.rows.len() => {
+                column_field(&self.rows[index + 1], column).focus(window)
+            }
+            "backspace" if row_is_empty && self.rows.len() > 1 => {
+                self.rows.remove(index);
+                column_field(&self.rows[index.saturating_sub(1)], column).focus(window);
+                self.refit(cx);
+            }
+            _ => {
+                let row = &mut self.rows[index];
+                let field = if column == 0 { &mut row.0 } else { &mut row.1 };
+                if field.handle_key(&event.keystroke) {
+                    self.refit(cx);
+                }
+            }
+        }
+    }
+
+    /// 渲染拟合结果
+    fn render_fit(&self) -> Div {
+        let container = div().flex().flex_col().gap_1().text_sm();
+        match &self.fit {
+            None => container
+                .text_color(rgb(style::MUTED_COLOR))
+                .child("Enter x and y values; press Enter for a new row"),
+            Some(Err(error)) => container
+                .text_color(rgb(style::PRIMARY_COLOR))
+                .child(error.to_string()),
+            Some(Ok(fit)) => {
+                let prediction = self
+                    .predict_field
+                    .value()
+                    .map_or("—".to_string(), |x| fit.predict(x).to_string());
+                container
+                    .child(fit.equation())
+                    .children(
+                        fit.coefficients
+                            .iter()
+                            .enumerate()
+                            .map(|(i, c)| format!("c{} = {}", i, c)),
+                    )
+                    .child(format!("R² = {}", fit.r_squared))
+                    .child(format!("ŷ = {}", prediction))
+            }
+        }
+    }
+}
+
+/// 获取数据行中 x（column 为 0）或 y 输入框
+fn column_field(row: &(NumberField, NumberField), column: usize) -> &NumberField {
+    if column == 0 { &row.0 } else { &row.1 }
+}
+
+impl Render for RegressionPanel {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let rows = self.rows.iter().enumerate().map(|(index, (x, y))| {
+            let cell = |field: &NumberField, column: usize| {
+                field
+                    .render(("regression-cell", index * 2 + column), window)
+                    .on_key_down(cx.listener(move |this, event: &KeyDownEvent, window, cx| {
+                        this.handle_row_key(index, column, event, window, cx)
+                    }))
+            };
+            div()
+                .flex()
+                .gap_1()
+                .items_center()
+                .child(cell(x, 0))
+                .child(cell(y, 1))
+        });
+        let last_row = self.rows.len() - 1;
+        let degree = self.degree;
+
+        let model_buttons = ModelKind::ALL.into_iter().map(|kind| {
+            form_button(kind.label(), kind.label())
+                .when(kind == self.kind, |this| {
+                    this.bg(rgb(style::PRIMARY_COLOR))
+                        .text_color(rgb(style::DISPLAY_COLOR))
+                })
+                .on_click(cx.listener(move |this, _, _, cx| this.set_kind(kind, cx)))
+        });
+
+        div()
+            .id("regression-panel")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_2()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(
+                div()
+                    .flex()
+                    .gap_1()
+                    .items_center()
+                    .children(model_buttons)
+                    .when(self.kind == ModelKind::Polynomial, |this| {
+                        this.child(form_button("degree-down", "−").on_click(cx.listener(
+                            move |this, _, _, cx| this.set_degree(degree.saturating_sub(1), cx),
+                        )))
+                        .child(format!("n={}", degree))
+                        .child(form_button("degree-up", "+").on_click(
+                            cx.listener(move |this, _, _, cx| this.set_degree(degree + 1, cx)),
+                        ))
+                    })
+                    .child(div().flex_1())
+                    .child(form_button("regression-add", "Add").on_click(cx.listener(
+                        move |this, _, window, cx| this.insert_row(last_row, window, cx),
+                    )))
+                    .child(
+                        form_button("regression-clear", "Clear")
+                            .on_click(cx.listener(|this, _, _, cx| this.clear(cx))),
+                    ),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_1()
+                    .gap_3()
+                    .min_h_0()
+                    .child(
+                        div()
+                            .id("regression-rows")
+                            .flex()
+                            .flex_col()
+                            .flex_1()
+                            .gap_1()
+                            .overflow_y_scroll()
+                            .child(
+                                div()
+                                    .flex()
+                                    .text_sm()
+                                    .text_color(rgb(style::MUTED_COLOR))
+                                    .child(div().flex_1().child("x"))
+                                    .child(div().flex_1().child("y")),
+                            )
+                            .children(rows),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .flex_1()
+                            .gap_2()
+                            .child(self.render_fit())
+                            .child(
+                                div()
+                                    .flex()
+                                    .gap_1()
+                                    .items_center()
+                                    .text_sm()
+                                    .child("predict x =")
+                                    .child(
+                                        self.predict_field.render("predict-x", window).on_key_down(
+                                            cx.listener(|this, event: &KeyDownEvent, _, cx| {
+                                                if this.predict_field.handle_key(&event.keystroke) {
+                                                    cx.notify();
+                                                }
+                                            }),
+                                        ),
+                                    ),
+                            ),
+                    ),
+            )
+    }
+}
@@ -1,11 +1,23 @@
-use crate::calculator::Calculator;
+use crate::calculator::{Calculator, CalculatorEvent, Command};
+use crate::logic::Value;
+use crate::logic::format::NumberFormat;
+use crate::logic::locale::Locale;
 use crate::style;
+use gpui::prelude::FluentBuilder;
 use gpui::*;
 
 /// 显示组件 - 显示计算器的当前状态
 pub struct Show {
     /// 计算器实体的引用
     calculator: Entity<Calculator>,
+    /// 最近一次计算的错误信息，表达式变化后清除
+    error: Option<String>,
+    /// 显示区的焦点，获得焦点后可以用 Ctrl/Cmd+V 粘贴
+    focus_handle: FocusHandle,
+    /// 输入中的表达式的预览结果和表达式是否完整，渲染时只读取这里保存的值
+    preview: Option<(Value, bool)>,
+    /// 正在后台计算的预览，输入再次变化时丢弃
+    preview_task: Option<Task<()>>,
 }
 
 impl Show {
@@ -14,14 +26,102 @@ impl Show {
         // 订阅计算器事件，当计算器状态变化时重新渲染显示组件
         cx.observe(
             &calculator,
-            |_this: &mut Show, _calculator: Entity<Calculator>, cx| {
+            |this: &mut Show, _calculator: Entity<Calculator>, cx| {
                 // 当计算器状态变化时，触发显示组件的重新渲染
+                this.update_preview(cx);
                 cx.notify();
             },
         )
         .detach();
 
-        Self { calculator }
+        // 计算失败时结果显示为 0，错误信息显示在结果上方
+        Calculator::subscribe(
+            &calculator,
+            cx,
+            CalculatorEvent::result,
+            |this: &mut Show, result, cx| {
+                this.error = result.err().map(|error| error.to_string());
+                cx.notify();
+            },
+        )
+        .detach();
+        Calculator::subscribe(
+            &calculator,
+            cx,
+            CalculatorEvent::expression,
+            |this: &mut Show, _, cx| {
+                this.error = None;
+                cx.notify();
+            },
+        )
+        .detach();
+
+        let mut show = Self {
+            calculator,
+            error: None,
+            focus_handle: cx.focus_handle(),
+            preview: None,
+            preview_task: None,
+        };
+        // 恢复的会话中可能有未计算的表达式
+        show.update_preview(cx);
+        show
+    }
+
+    /// 输入变化后在后台重新计算预览，不在渲染时求值
+    fn update_preview(&mut self, cx: &mut Context<Self>) {
+        let Some(preview) = self.calculator.read(cx).core().preview() else {
+            self.preview = None;
+            self.preview_task = None;
+            return;
+        };
+        let complete = preview.complete();
+        self.preview_task = Some(cx.spawn(async move |this, cx| {
+            let value = cx
+                .background_executor()
+                .spawn(async move { preview.evaluate() })
+                .await;
+            this.update(cx, |this, cx| {
+                this.preview = value.map(|value| (value, complete));
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// 修改计算器的数值显示格式
+    fn set_number_format(&mut self, number_format: NumberFormat, cx: &mut Context<Self>) {
+        self.calculator.update(cx, |calculator, cx| {
+            calculator.apply(Command::SetNumberFormat(number_format), cx)
+        });
+    }
+
+    /// 修改计算器的区域数字格式
+    fn set_locale(&mut self, locale: Locale, cx: &mut Context<Self>) {
+        self.calculator.update(cx, |calculator, cx| {
+            calculator.apply(Command::SetLocale(locale), cx)
+        });
+    }
+
+    /// 执行格式栏上的命令（切换输入方式、撤销、重做）
+    fn apply(&mut self, command: Command, cx: &mut Context<Self>) {
+        self.calculator
+            .update(cx, |calculator, cx| calculator.apply(command, cx));
+    }
+
+    /// 处理显示区的按键：Ctrl/Cmd+V 把剪贴板中的文本粘贴到表达式；
+    /// 撤销和重做是窗口级的动作，由 Root 处理
+    fn handle_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        let modified = keystroke.modifiers.control || keystroke.modifiers.platform;
+        if !modified || keystroke.key != "v" {
+            return;
+        }
+        if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
+            self.calculator.update(cx, |calculator, cx| {
+                calculator.apply(Command::Paste(text), cx)
+            });
+        }
     }
 
     // 获取当前显示文本
@@ -33,23 +133,194 @@ impl Show {
 impl Render for Show {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         // 从计算器获取当前显示文本
-        let display_text = self.calculator.read(cx).display_text();
+        // 会话文件读取或保存失败时显示在右上角
+        let session_status = self.calculator.read(cx).session_status();
+        let calculator = self.calculator.read(cx).core();
+        let display_text = calculator.display().to_string();
+        // 汇率时间戳显示在右上角，让用户知道货币换算使用的是哪一份汇率
+        let rates_status = calculator.rates_status();
+        // 零售模式显示税率和取整方式，计算后在结果上方列出每一步的明细
+        let retail_status = calculator.retail_status();
+        let breakdown = calculator.breakdown_lines();
+        let number_format = calculator.number_format();
+        let locale = calculator.locale();
+        let can_undo = calculator.can_undo();
+        let can_redo = calculator.can_redo();
+        let memory_set = calculator.memory().m.is_some();
+        let rpn_mode = calculator.rpn_mode();
+        // 预览保存的是数值，按当前的显示格式格式化
+        let preview = self
+            .preview
+            .as_ref()
+            .map(|(value, complete)| (format!("= {}", calculator.format_value(value)), *complete));
+        // RPN 模式显示 X 以上的三层，T 在最上面
+        let stack_rows: Vec<Div> = if rpn_mode {
+            let levels = calculator.stack_levels();
+            ["T", "Z", "Y"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let value = levels.get(2 - i).cloned().unwrap_or_default();
+                    div()
+                        .flex()
+                        .w_full()
+                        .justify_between()
+                        .text_xs()
+                        .child(div().text_color(rgb(style::MUTED_COLOR)).child(name))
+                        .child(div().text_color(rgb(style::WHITE_COLOR)).child(value))
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // 显示格式：点击名称切换 AUTO/FIX/SCI/ENG/SIG，点击 − / + 调整位数
+        let format_bar = div()
+            .flex()
+            .gap_2()
+            .text_xs()
+            .text_color(rgb(style::MUTED_COLOR))
+            .child(
+                div()
+                    .id("number-format")
+                    .cursor_pointer()
+                    .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                    .child(number_format.to_string())
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        this.set_number_format(number_format.next(), cx)
+                    })),
+            )
+            .when_some(number_format.digits(), |this, digits| {
+                this.children(
+                    [
+                        ("format-digits-down", "−", -1),
+                        ("format-digits-up", "+", 1),
+                    ]
+                    .map(|(id, label, step)| {
+                        div()
+                            .id(id)
+                            .cursor_pointer()
+                            .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                            .child(label)
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                let digits = digits.saturating_add_signed(step);
+                                this.set_number_format(number_format.with_digits(digits), cx)
+                            }))
+                    }),
+                )
+            })
+            // 区域格式：显示示例数字，点击切换
+            .child(
+                div()
+                    .id("number-locale")
+                    .cursor_pointer()
+                    .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                    .child(locale.sample())
+                    .on_click(
+                        cx.listener(move |this, _, _, cx| this.set_locale(locale.next(), cx)),
+                    ),
+            )
+            // 输入方式：点击在代数输入和 RPN 之间切换
+            .child(
+                div()
+                    .id("entry-mode")
+                    .cursor_pointer()
+                    .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                    .child(if rpn_mode { "RPN" } else { "ALG" })
+                    .on_click(cx.listener(|this, _, _, cx| this.apply(Command::ToggleRpnMode, cx))),
+            )
+            // 记忆寄存器 M 不为空时显示 M
+            .when(memory_set, |this| {
+                this.child(div().text_color(rgb(style::PRIMARY_COLOR)).child("M"))
+            })
+            // 撤销和重做：没有可撤销或重做的修改时变暗
+            .children(
+                [
+                    ("undo", "↶", can_undo, Command::Undo),
+                    ("redo", "↷", can_redo, Command::Redo),
+                ]
+                .map(|(id, label, enabled, command)| {
+                    div()
+                        .id(id)
+                        .child(label)
+                        .when(!enabled, |this| this.opacity(0.4))
+                        .when(enabled, |this| {
+                            this.cursor_pointer()
+                                .hover(|this| this.text_color(rgb(style::WHITE_COLOR)))
+                                .on_click(cx.listener(move |this, _, _, cx| {
+                                    this.apply(command.clone(), cx)
+                                }))
+                        })
+                }),
+            );
 
         div()
             .id("calculator-display")
+            .track_focus(&self.focus_handle)
+            .on_click(cx.listener(|this, _, window, _| window.focus(&this.focus_handle)))
+            .on_key_down(
+                cx.listener(|this, event: &KeyDownEvent, _, cx| this.handle_key(event, cx)),
+            )
             //.h(DefiniteLength::Fraction(0.2))
             .h_full()
             .px_4()
             .w_full()
             .flex()
-            .items_center()
-            .justify_end()
+            .flex_col()
+            .justify_center()
+            .items_end()
             .text_color(rgb(style::PRIMARY_COLOR))
             .bg(rgb(style::DISPLAY_COLOR))
-            .text_3xl()
             .font_family("JetBrainsMono Nerd Font")
             // 确保文本不会被截断，允许滚动
             .overflow_x_scroll()
-            .child(display_text)
+            .child(format_bar)
+            .when_some(rates_status, |this, status| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(status),
+                )
+            })
+            .when_some(session_status, |this, status| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(status),
+                )
+            })
+            .when_some(retail_status, |this, status| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(style::MUTED_COLOR))
+                        .child(status),
+                )
+            })
+            // 零售计算的错误已经显示在明细中
+            .when_some(
+                self.error.clone().filter(|_| breakdown.is_empty()),
+                |this, error| this.child(div().text_xs().child(error)),
+            )
+            .children(breakdown.into_iter().map(|line| {
+                div()
+                    .text_xs()
+                    .text_color(rgb(style::WHITE_COLOR))
+                    .child(line)
+            }))
+            .children(stack_rows)
+            .child(div().text_3xl().child(display_text))
+            // 预览行始终占位，没有预览时留空，表达式不完整时变暗
+            .child(
+                div()
+                    .h_5()
+                    .text_sm()
+                    .text_color(rgb(style::MUTED_COLOR))
+                    .when_some(preview, |this, (text, complete)| {
+                        this.when(!complete, |this| this.opacity(0.5)).child(text)
+                    }),
+            )
     }
 }
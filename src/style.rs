@@ -5,3 +5,6 @@ pub const PAD_COLOR: u32 = 0x444444;
 pub const BUTTON_COLOR: u32 = 0x606160;
 pub const BUTTON_COLOR_HOVER: u32 = 0x4F4F4F;
 pub const WHITE_COLOR: u32 = 0xcbcbcb;
+pub const FIELD_COLOR: u32 = 0x353535;
+pub const FIELD_FOCUS_COLOR: u32 = 0x3d3d3d;
+pub const MUTED_COLOR: u32 = 0x8a8a8a;
@@ -1,8 +1,96 @@
-use crate::calculator::Calculator;
+use crate::calculator::{Calculator, Command};
 use crate::style;
 use gpui::*;
 
+/// 第二功能键的层级：按一次切换到反函数，再按一次切换到双曲函数，再按一次恢复
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ShiftLayer {
+    Normal,
+    Inverse,
+    Hyperbolic,
+}
+
+impl ShiftLayer {
+    /// 按下第二功能键后的层级
+    fn next(self) -> Self {
+        match self {
+            ShiftLayer::Normal => ShiftLayer::Inverse,
+            ShiftLayer::Inverse => ShiftLayer::Hyperbolic,
+            ShiftLayer::Hyperbolic => ShiftLayer::Normal,
+        }
+    }
+
+    /// 第二功能键上显示的名称
+    fn label(self) -> &'static str {
+        match self {
+            ShiftLayer::Normal => "2nd",
+            ShiftLayer::Inverse => "inv",
+            ShiftLayer::Hyperbolic => "hyp",
+        }
+    }
+}
+
+/// 三角函数按键
+#[derive(Debug, Clone, Copy)]
+enum TrigKey {
+    Sin,
+    Cos,
+    Tan,
+}
+
+impl TrigKey {
+    /// 当前层级下的 (函数名, 按钮标签)
+    fn function(self, layer: ShiftLayer) -> (&'static str, &'static str) {
+        match (self, layer) {
+            (TrigKey::Sin, ShiftLayer::Normal) => ("sin", "sin"),
+            (TrigKey::Sin, ShiftLayer::Inverse) => ("asin", "sin⁻¹"),
+            (TrigKey::Sin, ShiftLayer::Hyperbolic) => ("sinh", "sinh"),
+            (TrigKey::Cos, ShiftLayer::Normal) => ("cos", "cos"),
+            (TrigKey::Cos, ShiftLayer::Inverse) => ("acos", "cos⁻¹"),
+            (TrigKey::Cos, ShiftLayer::Hyperbolic) => ("cosh", "cosh"),
+            (TrigKey::Tan, ShiftLayer::Normal) => ("tan", "tan"),
+            (TrigKey::Tan, ShiftLayer::Inverse) => ("atan", "tan⁻¹"),
+            (TrigKey::Tan, ShiftLayer::Hyperbolic) => ("tanh", "tanh"),
+        }
+    }
+}
+
+/// 零售模式按键
+#[derive(Debug, Clone, Copy)]
+enum RetailKey {
+    /// 把当前值存为税率
+    Rate,
+    AddTax,
+    RemoveTax,
+    Markup,
+    Margin,
+    Tip,
+    Split,
+    /// 切换小费和分摊的取整方式
+    Rounding,
+}
+
+/// 记忆寄存器按键，STO 和 RCL 之后再按一个数字键选择寄存器
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MemoryKey {
+    Clear,
+    Recall,
+    Add,
+    Subtract,
+    Store,
+    RecallRegister,
+}
+
+/// RPN 模式的栈操作按键
+#[derive(Debug, Clone, Copy)]
+enum StackKey {
+    Swap,
+    RollDown,
+    LastX,
+}
+
 /// 按钮类型枚举
+#[derive(Debug, Clone, Copy)]
 enum ButtonType {
     Number(char),
     Operator(char),
@@ -11,61 +99,218 @@ enum ButtonType {
     Delete,
     Equals,
     CommaFormatting,
+    /// 第二功能键
+    Shift,
+    /// 三角函数键，实际函数取决于第二功能层级
+    Trig(TrigKey),
+    /// 零售模式开关
+    RetailMode,
+    /// 零售模式按键
+    Retail(RetailKey),
+    /// 记忆寄存器按键
+    Memory(MemoryKey),
+    /// RPN 模式的栈操作按键
+    Stack(StackKey),
 }
 
 /// 触摸区域组件 - 包含计算器按钮网格
 pub struct TouchArea {
     /// 计算器实体的引用
     calculator: Entity<Calculator>,
+    /// 当前第二功能层级，按下一个三角函数键后恢复为普通层级
+    shift: ShiftLayer,
+    /// 按下 STO 或 RCL 后等待输入寄存器编号
+    register: Option<MemoryKey>,
 }
 
 impl TouchArea {
     /// 创建新的触摸区域组件
     pub fn new(calculator: Entity<Calculator>) -> Self {
-        Self { calculator }
+        Self {
+            calculator,
+            shift: ShiftLayer::Normal,
+            register: None,
+        }
     }
 
-    /// 获取按钮网格配置
-    fn button_grid() -> Vec<Vec<(SharedString, ButtonType)>> {
-        vec![
-            vec![
-                ("←".into(), ButtonType::Delete),
-                ("AC".into(), ButtonType::Clear),
-                ("%".into(), ButtonType::Operator('%')),
-                ("÷".into(), ButtonType::Operator('÷')),
-            ],
-            vec![
-                ("7".into(), ButtonType::Number('7')),
-                ("8".into(), ButtonType::Number('8')),
-                ("9".into(), ButtonType::Number('9')),
-                ("×".into(), ButtonType::Operator('×')),
-            ],
-            vec![
-                ("4".into(), ButtonType::Number('4')),
-                ("5".into(), ButtonType::Number('5')),
-                ("6".into(), ButtonType::Number('6')),
-                ("-".into(), ButtonType::Operator('-')),
-            ],
+    /// 获取按钮网格配置，三角函数键的标签随第二功能层级变化，零售模式下换成零售按键，
+    /// RPN 模式下括号键换成栈操作键，= 键变为 ENTER
+    fn button_grid(&self, cx: &App) -> Vec<Vec<(SharedString, ButtonType)>> {
+        let trig = |key: TrigKey| -> (SharedString, ButtonType) {
+            (key.function(self.shift).1.into(), ButtonType::Trig(key))
+        };
+        let calculator = self.calculator.read(cx).core();
+        let top_rows = if calculator.retail_mode() {
             vec![
-                ("1".into(), ButtonType::Number('1')),
-                ("2".into(), ButtonType::Number('2')),
-                ("3".into(), ButtonType::Number('3')),
-                ("+".into(), ButtonType::Operator('+')),
-            ],
-            vec![
-                (",".into(), ButtonType::CommaFormatting),
-                ("0".into(), ButtonType::Number('0')),
-                (".".into(), ButtonType::Decimal),
-                ("=".into(), ButtonType::Equals),
-            ],
-        ]
+                vec![
+                    ("calc".into(), ButtonType::RetailMode),
+                    ("RATE".into(), ButtonType::Retail(RetailKey::Rate)),
+                    ("TAX+".into(), ButtonType::Retail(RetailKey::AddTax)),
+                    ("TAX−".into(), ButtonType::Retail(RetailKey::RemoveTax)),
+                    (
+                        calculator.rounding().label().into(),
+                        ButtonType::Retail(RetailKey::Rounding),
+                    ),
+                ],
+                vec![
+                    ("MU".into(), ButtonType::Retail(RetailKey::Markup)),
+                    ("MG".into(), ButtonType::Retail(RetailKey::Margin)),
+                    ("TIP".into(), ButtonType::Retail(RetailKey::Tip)),
+                    ("÷N".into(), ButtonType::Retail(RetailKey::Split)),
+                ],
+            ]
+        } else if calculator.rpn_mode() {
+            vec![vec![
+                (self.shift.label().into(), ButtonType::Shift),
+                trig(TrigKey::Sin),
+                trig(TrigKey::Cos),
+                trig(TrigKey::Tan),
+                ("x⇄y".into(), ButtonType::Stack(StackKey::Swap)),
+                ("R↓".into(), ButtonType::Stack(StackKey::RollDown)),
+                ("LSTx".into(), ButtonType::Stack(StackKey::LastX)),
+            ]]
+        } else {
+            vec![vec![
+                (self.shift.label().into(), ButtonType::Shift),
+                trig(TrigKey::Sin),
+                trig(TrigKey::Cos),
+                trig(TrigKey::Tan),
+                ("(".into(), ButtonType::Operator('(')),
+                (")".into(), ButtonType::Operator(')')),
+                ("$".into(), ButtonType::RetailMode),
+            ]]
+        };
+        let equals = if calculator.rpn_mode() { "ENTER" } else { "=" };
+        let memory_row = vec![
+            ("MC".into(), ButtonType::Memory(MemoryKey::Clear)),
+            ("MR".into(), ButtonType::Memory(MemoryKey::Recall)),
+            ("M+".into(), ButtonType::Memory(MemoryKey::Add)),
+            ("M−".into(), ButtonType::Memory(MemoryKey::Subtract)),
+            ("STO".into(), ButtonType::Memory(MemoryKey::Store)),
+            ("RCL".into(), ButtonType::Memory(MemoryKey::RecallRegister)),
+        ];
+        top_rows
+            .into_iter()
+            .chain([
+                memory_row,
+                vec![
+                    ("←".into(), ButtonType::Delete),
+                    ("AC".into(), ButtonType::Clear),
+                    ("%".into(), ButtonType::Operator('%')),
+                    ("÷".into(), ButtonType::Operator('÷')),
+                ],
+                vec![
+                    ("7".into(), ButtonType::Number('7')),
+                    ("8".into(), ButtonType::Number('8')),
+                    ("9".into(), ButtonType::Number('9')),
+                    ("×".into(), ButtonType::Operator('×')),
+                ],
+                vec![
+                    ("4".into(), ButtonType::Number('4')),
+                    ("5".into(), ButtonType::Number('5')),
+                    ("6".into(), ButtonType::Number('6')),
+                    ("-".into(), ButtonType::Operator('-')),
+                ],
+                vec![
+                    ("1".into(), ButtonType::Number('1')),
+                    ("2".into(), ButtonType::Number('2')),
+                    ("3".into(), ButtonType::Number('3')),
+                    ("+".into(), ButtonType::Operator('+')),
+                ],
+                vec![
+                    (",".into(), ButtonType::CommaFormatting),
+                    ("0".into(), ButtonType::Number('0')),
+                    (".".into(), ButtonType::Decimal),
+                    (equals.into(), ButtonType::Equals),
+                ],
+            ])
+            .collect()
+    }
+
+    /// 处理按钮点击
+    fn press(&mut self, button_type: ButtonType, cx: &mut Context<Self>) {
+        // STO / RCL 之后的数字键选择寄存器，其他按键取消选择
+        if let Some(key) = self.register.take() {
+            cx.notify();
+            if let ButtonType::Number(digit) = button_type {
+                let register = digit.to_digit(10).unwrap_or(0) as usize;
+                let command = match key {
+                    MemoryKey::Store => Command::StoreRegister(register),
+                    _ => Command::RecallRegister(register),
+                };
+                self.calculator
+                    .update(cx, |calculator, cx| calculator.apply(command, cx));
+                return;
+            }
+        }
+        let command = match button_type {
+            ButtonType::Shift => {
+                self.shift = self.shift.next();
+                cx.notify();
+                return;
+            }
+            ButtonType::Trig(key) => {
+                let (name, _) = key.function(self.shift);
+                self.shift = ShiftLayer::Normal;
+                cx.notify();
+                Command::Function(name.to_string())
+            }
+            ButtonType::RetailMode => {
+                self.shift = ShiftLayer::Normal;
+                cx.notify();
+                Command::ToggleRetailMode
+            }
+            ButtonType::Retail(key) => {
+                // 取整方式显示在按键上，需要重新渲染
+                cx.notify();
+                match key {
+                    RetailKey::Rate => Command::StoreTaxRate,
+                    RetailKey::AddTax => Command::ApplyTax(true),
+                    RetailKey::RemoveTax => Command::ApplyTax(false),
+                    RetailKey::Markup => Command::RetailStep("mu".to_string()),
+                    RetailKey::Margin => Command::RetailStep("mg".to_string()),
+                    RetailKey::Tip => Command::RetailStep("tip".to_string()),
+                    RetailKey::Split => Command::RetailStep("split".to_string()),
+                    RetailKey::Rounding => Command::CycleRounding,
+                }
+            }
+            ButtonType::Memory(key) => match key {
+                MemoryKey::Clear => Command::MemoryClear,
+                MemoryKey::Recall => Command::MemoryRecall,
+                MemoryKey::Add => Command::MemoryAdd(true),
+                MemoryKey::Subtract => Command::MemoryAdd(false),
+                MemoryKey::Store | MemoryKey::RecallRegister => {
+                    self.register = Some(key);
+                    cx.notify();
+                    return;
+                }
+            },
+            ButtonType::Stack(key) => match key {
+                StackKey::Swap => Command::Swap,
+                StackKey::RollDown => Command::RollDown,
+                StackKey::LastX => Command::LastX,
+            },
+            ButtonType::Number(num) => Command::Number(num),
+            ButtonType::Operator(op) if op == '(' || op == ')' => Command::Parenthesis(op),
+            ButtonType::Operator(op) => Command::Operator(op),
+            ButtonType::Decimal => Command::Decimal,
+            ButtonType::Clear => Command::Clear,
+            ButtonType::Delete => Command::Delete,
+            ButtonType::CommaFormatting => Command::ToggleCommaFormatting,
+            ButtonType::Equals => Command::Equals,
+        };
+
+        self.calculator
+            .update(cx, |calculator, cx| calculator.apply(command, cx));
     }
 }
 
 impl Render for TouchArea {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        let calculator = self.calculator.clone();
-        let button_grid = Self::button_grid();
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let button_grid = self.button_grid(cx);
+        let shift_active = self.shift != ShiftLayer::Normal;
+        let retail_active = self.calculator.read(cx).core().retail_mode();
+        let register = self.register;
 
         div()
             .id("calculator-keypad")
@@ -78,11 +323,16 @@ impl Render for TouchArea {
             .children(button_grid.into_iter().map(|row| {
                 div().flex().flex_1().gap_1().children(row.into_iter().map(
                     |(label, button_type)| {
-                        let calculator = calculator.clone();
-
                         // 确定按钮的样式类
                         let is_number = matches!(button_type, ButtonType::Number(_));
-                        let is_operator = matches!(button_type, ButtonType::Operator(_));
+                        let is_operator = matches!(
+                            button_type,
+                            ButtonType::Operator(_)
+                                | ButtonType::Trig(_)
+                                | ButtonType::Retail(_)
+                                | ButtonType::Memory(_)
+                                | ButtonType::Stack(_)
+                        );
                         let is_special = matches!(
                             button_type,
                             ButtonType::Clear
@@ -103,7 +353,26 @@ impl Render for TouchArea {
                             .child(label.clone());
 
                         // 应用样式
-                        let styled_button = if is_number {
+                        let styled_button = if let Some(active) = match button_type {
+                            ButtonType::Shift => Some(shift_active),
+                            ButtonType::RetailMode => Some(retail_active),
+                            ButtonType::Memory(
+                                key @ (MemoryKey::Store | MemoryKey::RecallRegister),
+                            ) => Some(register == Some(key)),
+                            _ => None,
+                        } {
+                            // 第二功能键、零售模式键和等待寄存器编号的 STO / RCL 键激活时高亮
+                            if active {
+                                base_style
+                                    .bg(rgb(style::PRIMARY_COLOR))
+                                    .text_color(rgb(style::DISPLAY_COLOR))
+                            } else {
+                                base_style
+                                    .bg(rgb(style::BUTTON_COLOR))
+                                    .text_color(rgb(style::PRIMARY_COLOR))
+                                    .hover(|this| this.bg(rgb(0x707070)))
+                            }
+                        } else if is_number {
                             base_style
                                 .bg(rgb(style::BUTTON_COLOR))
                                 .text_color(rgb(style::WHITE_COLOR))
@@ -122,35 +391,11 @@ impl Render for TouchArea {
                         };
 
                         // 添加点击事件处理
-                        styled_button.on_click(move |_event, _window, cx| {
-                            calculator.update(cx, |calculator, cx| match button_type {
-                                ButtonType::Number(num) => {
-                                    calculator.input_number(num, cx);
-                                }
-                                ButtonType::Operator(op) => {
-                                    if op == '(' || op == ')' {
-                                        calculator.input_parenthesis(op, cx);
-                                    } else {
-                                        calculator.input_operator(op, cx);
-                                    }
-                                }
-                                ButtonType::Decimal => {
-                                    calculator.input_decimal(cx);
-                                }
-                                ButtonType::Clear => {
-                                    calculator.clear(cx);
-                                }
-                                ButtonType::Delete => {
-                                    calculator.delete(cx);
-                                }
-                                ButtonType::CommaFormatting => {
-                                    calculator.toggle_comma_formatting(cx);
-                                }
-                                ButtonType::Equals => {
-                                    calculator.calculate(cx);
-                                }
-                            });
-                        })
+                        styled_button.on_click(
+                            cx.listener(move |this, _event, _window, cx| {
+                                this.press(button_type, cx)
+                            }),
+                        )
                     },
                 ))
             }))
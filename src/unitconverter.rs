@@ -0,0 +1,188 @@
+use crate::button::form_button;
+use crate::logic::units::{self, CATEGORIES};
+use crate::numberfield::NumberField;
+use crate::style;
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+/// 换算器的两侧输入
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    From,
+    To,
+}
+
+impl Side {
+    /// 在 units 和 fields 数组中的下标
+    fn index(self) -> usize {
+        match self {
+            Side::From => 0,
+            Side::To => 1,
+        }
+    }
+
+    /// 另一侧
+    fn other(self) -> Side {
+        match self {
+            Side::From => Side::To,
+            Side::To => Side::From,
+        }
+    }
+}
+
+/// 格式化换算结果：保留 12 位有效数字，过大或过小时使用科学计数法
+fn format_number(x: f64) -> String {
+    let rounded: f64 = format!("{:.11e}", x).parse().unwrap_or(x);
+    if rounded == 0.0 || (1e-6..1e15).contains(&rounded.abs()) {
+        format!("{}", rounded)
+    } else {
+        format!("{:e}", rounded)
+    }
+}
+
+/// 单位换算器 - 选择分类和两侧单位，编辑任一侧时实时换算另一侧
+pub struct UnitConverter {
+    /// 当前分类在 CATEGORIES 中的下标
+    category: usize,
+    /// 两侧单位在当前分类单位列表中的下标
+    units: [usize; 2],
+    /// 两侧输入框
+    fields: [NumberField; 2],
+    /// 最近编辑的一侧，切换单位时以它为准重新换算另一侧
+    source: Side,
+    /// 输入无法解析或换算失败时的错误信息
+    error: Option<String>,
+}
+
+impl UnitConverter {
+    /// 创建新的单位换算器
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            category: 0,
+            units: [0, 1],
+            fields: [NumberField::new(cx), NumberField::new(cx)],
+            source: Side::From,
+            error: None,
+        }
+    }
+
+    /// 某一侧当前选择的单位
+    fn unit(&self, side: Side) -> &'static str {
+        CATEGORIES[self.category].1[self.units[side.index()]]
+    }
+
+    /// 切换分类，两侧单位重置为分类中的前两个
+    fn set_category(&mut self, category: usize, cx: &mut Context<Self>) {
+        self.category = category;
+        self.units = [0, 1];
+        self.convert(cx);
+    }
+
+    /// 切换某一侧的单位
+    fn set_unit(&mut self, side: Side, unit: usize, cx: &mut Context<Self>) {
+        self.units[side.index()] = unit;
+        self.convert(cx);
+    }
+
+    /// 以最近编辑的一侧为准，重新计算另一侧
+    fn convert(&mut self, cx: &mut Context<Self>) {
+        let (source, target) = (self.source, self.source.other());
+        let text = self.fields[source.index()].text().trim().to_string();
+        let result = match self.fields[source.index()].value() {
+            Some(value) => units::convert(value, self.unit(source), self.unit(target))
+                .map(Some)
+                .map_err(|error| error.to_string()),
+            None if text.is_empty() => Ok(None),
+            None => Err(format!("Invalid number: {}", text)),
+        };
+
+        let target_field = &mut self.fields[target.index()];
+        match result {
+            Ok(Some(value)) => target_field.set_text(format_number(value)),
+            _ => target_field.clear(),
+        }
+        self.error = result.err();
+        cx.notify();
+    }
+
+    /// 处理输入框的按键，内容变化时换算另一侧
+    fn handle_key(&mut self, side: Side, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        if self.fields[side.index()].handle_key(&event.keystroke) {
+            self.source = side;
+            self.convert(cx);
+        }
+    }
+
+    /// 渲染一侧：输入框和单位选择按钮
+    fn render_side(&self, side: Side, window: &Window, cx: &mut Context<Self>) -> Div {
+        let unit_buttons = CATEGORIES[self.category]
+            .1
+            .iter()
+            .enumerate()
+            .map(|(i, unit)| {
+                form_button(("unit", side.index() * 100 + i), *unit)
+                    .when(i == self.units[side.index()], |this| {
+                        this.bg(rgb(style::PRIMARY_COLOR))
+                            .text_color(rgb(style::DISPLAY_COLOR))
+                    })
+                    .on_click(cx.listener(move |this, _, _, cx| this.set_unit(side, i, cx)))
+            });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(
+                div()
+                    .flex()
+                    .gap_2()
+                    .items_center()
+                    .child(
+                        self.fields[side.index()]
+                            .render(("unit-field", side.index()), window)
+                            .on_key_down(cx.listener(move |this, event: &KeyDownEvent, _, cx| {
+                                this.handle_key(side, event, cx)
+                            })),
+                    )
+                    .child(div().w_16().child(self.unit(side))),
+            )
+            .child(div().flex().flex_wrap().gap_1().children(unit_buttons))
+    }
+}
+
+impl Render for UnitConverter {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let category_buttons = CATEGORIES.iter().enumerate().map(|(i, (name, _))| {
+            form_button(("unit-category", i), *name)
+                .when(i == self.category, |this| {
+                    this.bg(rgb(style::PRIMARY_COLOR))
+                        .text_color(rgb(style::DISPLAY_COLOR))
+                })
+                .on_click(cx.listener(move |this, _, _, cx| this.set_category(i, cx)))
+        });
+
+        let footer = div().text_sm();
+        let footer = match &self.error {
+            Some(message) => footer
+                .text_color(rgb(style::PRIMARY_COLOR))
+                .child(message.clone()),
+            None => footer
+                .text_color(rgb(style::MUTED_COLOR))
+                .child("Type in either field; the other updates as you type"),
+        };
+
+        div()
+            .id("unit-converter")
+            .flex()
+            .flex_col()
+            .size_full()
+            .p_2()
+            .gap_3()
+            .bg(rgb(style::PAD_COLOR))
+            .text_color(rgb(style::WHITE_COLOR))
+            .child(div().flex().flex_wrap().gap_1().children(category_buttons))
+            .child(self.render_side(Side::From, window, cx))
+            .child(self.render_side(Side::To, window, cx))
+            .child(footer)
+    }
+}